@@ -0,0 +1,58 @@
+/*
+ * Run dwcpn for one pixel from C and check the result, exits with a non-zero status on failure.
+ *
+ *   cargo build --release
+ *   cc -I include examples/c/dwcpn_example.c target/release/libdwcpn.a -lpthread -ldl -lm -o dwcpn_example
+ *   ./dwcpn_example
+ */
+
+#include <math.h>
+#include <stdio.h>
+
+#include "dwcpn.h"
+
+int main(void) {
+    DwcpnInputs inputs;
+    DwcpnSettings settings;
+    /* the depth profile makes the outputs 4 kB, keep them off the stack */
+    static DwcpnOutputs outputs;
+
+    dwcpn_default_inputs(&inputs);
+    dwcpn_default_settings(&settings);
+
+    inputs.lat = 18.71;
+    inputs.lon = -18.625;
+    inputs.z_bottom = 2950.468;
+    inputs.iday = 121;
+    inputs.alpha_b = 0.1518;
+    inputs.pmb = 3.9059;
+    inputs.z_m = 23.094;
+    inputs.mld = 31.975;
+    inputs.chl = 1.718;
+    inputs.rho = 0.8247;
+    inputs.sigma = 27.556;
+    inputs.yel_sub = 0.3;
+    inputs.par = 55.8677;
+
+    int status = dwcpn_calc_production(&inputs, &settings, &outputs);
+    if (status != DWCPN_OK) {
+        fprintf(stderr, "dwcpn_calc_production failed with status %d\n", status);
+        return 1;
+    }
+
+    printf("pp_day %.2f mgC m^-2 d^-1, euphotic depth %.2f m\n", outputs.pp_day, outputs.euphotic_depth);
+
+    /* the same pixel and 1% tolerance as mauritania_upwelling_test_pp in src/lib.rs */
+    if (fabs(outputs.pp_day / 2341.988 - 1.0) > 0.01) {
+        fprintf(stderr, "unexpected pp_day\n");
+        return 1;
+    }
+
+    inputs.iday = 0;
+    if (dwcpn_calc_production(&inputs, &settings, &outputs) != DWCPN_INVALID_ARGUMENT) {
+        fprintf(stderr, "invalid day was accepted\n");
+        return 1;
+    }
+
+    return 0;
+}
@@ -0,0 +1,151 @@
+! Fortran interface to the dwcpn C ABI (src/ffi.rs, include/dwcpn.h). The derived types are
+! bind(C) so that they have the same layout as the C structs; keep them in step with src/ffi.rs.
+module dwcpn
+    use, intrinsic :: iso_c_binding, only: c_double, c_int32_t, c_size_t
+    implicit none
+
+    integer, parameter :: DWCPN_WL_COUNT = 61
+    integer, parameter :: DWCPN_DEPTH_PROFILE_COUNT = 500
+
+    integer(c_int32_t), parameter :: DWCPN_OK = 0
+    integer(c_int32_t), parameter :: DWCPN_MODEL_ERROR = 1
+    integer(c_int32_t), parameter :: DWCPN_PP_TOO_HIGH = 2
+    integer(c_int32_t), parameter :: DWCPN_NULL_POINTER = -1
+    integer(c_int32_t), parameter :: DWCPN_INVALID_ARGUMENT = -2
+    integer(c_int32_t), parameter :: DWCPN_PANIC = -3
+
+    integer(c_int32_t), parameter :: DWCPN_LOW_SUN_TRIANGLE = 0
+    integer(c_int32_t), parameter :: DWCPN_LOW_SUN_SPECTRAL = 1
+
+    integer(c_int32_t), parameter :: DWCPN_PRODUCTION_SPECTRAL = 0
+    integer(c_int32_t), parameter :: DWCPN_PRODUCTION_BROADBAND = 1
+
+    integer(c_int32_t), parameter :: DWCPN_PRECISION_F64 = 0
+    integer(c_int32_t), parameter :: DWCPN_PRECISION_F32 = 1
+
+    integer(c_int32_t), parameter :: DWCPN_SOLAR_SPECTRUM_LEGACY = 0
+    integer(c_int32_t), parameter :: DWCPN_SOLAR_SPECTRUM_REFERENCE = 1
+
+    integer(c_int32_t), parameter :: DWCPN_TEMPERATURE_NONE = 0
+    integer(c_int32_t), parameter :: DWCPN_TEMPERATURE_EPPLEY = 1
+    integer(c_int32_t), parameter :: DWCPN_TEMPERATURE_Q10 = 2
+
+    integer(c_int32_t), parameter :: DWCPN_INTEGRATION_EUPHOTIC_DEPTH = 0
+    integer(c_int32_t), parameter :: DWCPN_INTEGRATION_TENTH_PERCENT_LIGHT = 1
+    integer(c_int32_t), parameter :: DWCPN_INTEGRATION_MIXED_LAYER = 2
+    integer(c_int32_t), parameter :: DWCPN_INTEGRATION_FIXED_DEPTH = 3
+    integer(c_int32_t), parameter :: DWCPN_INTEGRATION_SEABED = 4
+
+    integer(c_int32_t), parameter :: DWCPN_DAYLIGHT_NORMAL = 0
+    integer(c_int32_t), parameter :: DWCPN_DAYLIGHT_POLAR_DAY = 1
+    integer(c_int32_t), parameter :: DWCPN_DAYLIGHT_POLAR_NIGHT = 2
+
+    type, bind(C) :: dwcpn_inputs
+        real(c_double) :: lat
+        real(c_double) :: lon
+        real(c_double) :: z_bottom
+        integer(c_int32_t) :: iday
+        ! calendar date, used instead of iday when year is not zero
+        integer(c_int32_t) :: year
+        integer(c_int32_t) :: month
+        integer(c_int32_t) :: day
+        ! hours ahead of UTC for the reported clock times, NaN for UTC
+        real(c_double) :: utc_offset
+        real(c_double) :: alpha_b
+        real(c_double) :: pmb
+        real(c_double) :: z_m
+        real(c_double) :: mld
+        real(c_double) :: chl
+        real(c_double) :: rho
+        real(c_double) :: sigma
+        real(c_double) :: cloud
+        real(c_double) :: yel_sub
+        real(c_double) :: par
+        ! sea surface temperature, NaN when not known
+        real(c_double) :: sst
+        real(c_double) :: bw(DWCPN_WL_COUNT)
+        real(c_double) :: bbr(DWCPN_WL_COUNT)
+        real(c_double) :: ay(DWCPN_WL_COUNT)
+    end type dwcpn_inputs
+
+    type, bind(C) :: dwcpn_settings
+        integer(c_int32_t) :: mld_only
+        integer(c_int32_t) :: iom_only
+        real(c_double) :: zenith_cutoff
+        integer(c_int32_t) :: low_sun_mode
+        integer(c_int32_t) :: production_mode
+        integer(c_int32_t) :: precision
+        integer(c_int32_t) :: solar_spectrum
+        real(c_double) :: reference_spectrum(DWCPN_WL_COUNT)
+        integer(c_int32_t) :: temperature_dependence
+        real(c_double) :: temperature_coefficient
+        real(c_double) :: reference_temperature
+        ! fixed_depth is only read for DWCPN_INTEGRATION_FIXED_DEPTH
+        integer(c_int32_t) :: integration_limit
+        real(c_double) :: fixed_depth
+    end type dwcpn_settings
+
+    type, bind(C) :: dwcpn_outputs
+        real(c_double) :: pp_day
+        real(c_double) :: pp_low_sun
+        real(c_double) :: euphotic_depth
+        real(c_double) :: integration_depth
+        real(c_double) :: pp_above_mld
+        real(c_double) :: pp_below_mld
+        real(c_double) :: spectral_i_star
+        real(c_double) :: par_noon_max
+        real(c_double) :: par
+        real(c_double) :: sunrise
+        real(c_double) :: solar_noon
+        real(c_double) :: sunset
+        integer(c_int32_t) :: daylight_regime
+        real(c_double) :: pp_profile(DWCPN_DEPTH_PROFILE_COUNT)
+    end type dwcpn_outputs
+
+    interface
+        function dwcpn_default_inputs(inputs) bind(C, name="dwcpn_default_inputs") result(status)
+            import :: dwcpn_inputs, c_int32_t
+            type(dwcpn_inputs), intent(out) :: inputs
+            integer(c_int32_t) :: status
+        end function dwcpn_default_inputs
+
+        function dwcpn_default_settings(settings) bind(C, name="dwcpn_default_settings") result(status)
+            import :: dwcpn_settings, c_int32_t
+            type(dwcpn_settings), intent(out) :: settings
+            integer(c_int32_t) :: status
+        end function dwcpn_default_settings
+
+        function dwcpn_calc_production(inputs, settings, outputs) &
+                bind(C, name="dwcpn_calc_production") result(status)
+            import :: dwcpn_inputs, dwcpn_settings, dwcpn_outputs, c_int32_t
+            type(dwcpn_inputs), intent(in) :: inputs
+            type(dwcpn_settings), intent(in) :: settings
+            type(dwcpn_outputs), intent(out) :: outputs
+            integer(c_int32_t) :: status
+        end function dwcpn_calc_production
+
+        function dwcpn_calc_production_par_series(inputs, settings, times, par, count, outputs) &
+                bind(C, name="dwcpn_calc_production_par_series") result(status)
+            import :: dwcpn_inputs, dwcpn_settings, dwcpn_outputs, c_double, c_int32_t, c_size_t
+            type(dwcpn_inputs), intent(in) :: inputs
+            type(dwcpn_settings), intent(in) :: settings
+            real(c_double), intent(in) :: times(*)
+            real(c_double), intent(in) :: par(*)
+            integer(c_size_t), value :: count
+            type(dwcpn_outputs), intent(out) :: outputs
+            integer(c_int32_t) :: status
+        end function dwcpn_calc_production_par_series
+
+        function dwcpn_calc_production_batch(inputs, count, settings, outputs, statuses) &
+                bind(C, name="dwcpn_calc_production_batch") result(status)
+            import :: dwcpn_inputs, dwcpn_settings, dwcpn_outputs, c_int32_t, c_size_t
+            type(dwcpn_inputs), intent(in) :: inputs(*)
+            integer(c_size_t), value :: count
+            type(dwcpn_settings), intent(in) :: settings
+            type(dwcpn_outputs), intent(out) :: outputs(*)
+            integer(c_int32_t), intent(out) :: statuses(*)
+            integer(c_int32_t) :: status
+        end function dwcpn_calc_production_batch
+    end interface
+
+end module dwcpn
@@ -0,0 +1,62 @@
+! Run dwcpn for a row of pixels from Fortran and check the results, stops with an error on
+! failure.
+!
+!   cargo build --release
+!   gfortran fortran/dwcpn.f90 fortran/dwcpn_example.f90 target/release/libdwcpn.a \
+!       -lpthread -ldl -lm -o dwcpn_example
+!   ./dwcpn_example
+program dwcpn_example
+    use, intrinsic :: iso_c_binding, only: c_int32_t, c_size_t
+    use dwcpn
+    implicit none
+
+    integer, parameter :: n = 3
+    type(dwcpn_inputs) :: inputs(n)
+    type(dwcpn_settings) :: settings
+    type(dwcpn_outputs), allocatable :: outputs(:)
+    integer(c_int32_t) :: statuses(n)
+    integer(c_int32_t) :: status
+    integer :: i
+
+    allocate(outputs(n))
+
+    status = dwcpn_default_settings(settings)
+
+    do i = 1, n
+        status = dwcpn_default_inputs(inputs(i))
+        inputs(i)%lat = 18.71d0
+        inputs(i)%lon = -18.625d0
+        inputs(i)%z_bottom = 2950.468d0
+        inputs(i)%iday = 121
+        inputs(i)%alpha_b = 0.1518d0
+        inputs(i)%pmb = 3.9059d0
+        inputs(i)%z_m = 23.094d0
+        inputs(i)%mld = 31.975d0
+        inputs(i)%chl = 1.718d0
+        inputs(i)%rho = 0.8247d0
+        inputs(i)%sigma = 27.556d0
+        inputs(i)%yel_sub = 0.3d0
+        inputs(i)%par = 55.8677d0
+    end do
+
+    ! the last pixel has an invalid day
+    inputs(n)%iday = 0
+
+    status = dwcpn_calc_production_batch(inputs, int(n, c_size_t), settings, outputs, statuses)
+    if (status /= DWCPN_OK) error stop "dwcpn_calc_production_batch failed"
+
+    do i = 1, n
+        print '(a, i0, a, i0, a, f10.2)', "pixel ", i, " status ", statuses(i), " pp_day ", outputs(i)%pp_day
+    end do
+
+    ! the same pixel and 1% tolerance as mauritania_upwelling_test_pp in src/lib.rs
+    if (statuses(1) /= DWCPN_OK) error stop "pixel 1 failed"
+    if (abs(outputs(1)%pp_day / 2341.988d0 - 1.0d0) > 0.01d0) error stop "unexpected pp_day"
+    if (outputs(2)%pp_day /= outputs(1)%pp_day) error stop "batch results differ"
+    if (statuses(n) /= DWCPN_INVALID_ARGUMENT) error stop "invalid day was accepted"
+
+    ! a single pixel
+    status = dwcpn_calc_production(inputs(1), settings, outputs(1))
+    if (status /= DWCPN_OK) error stop "dwcpn_calc_production failed"
+    if (outputs(1)%daylight_regime /= DWCPN_DAYLIGHT_NORMAL) error stop "unexpected daylight regime"
+end program dwcpn_example
@@ -0,0 +1,196 @@
+#ifndef DWCPN_H
+#define DWCPN_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define DWCPN_WL_COUNT 61
+
+#define DWCPN_DEPTH_PROFILE_COUNT 500
+
+#define DWCPN_OK 0
+
+#define DWCPN_MODEL_ERROR 1
+
+#define DWCPN_PP_TOO_HIGH 2
+
+#define DWCPN_NULL_POINTER -1
+
+#define DWCPN_INVALID_ARGUMENT -2
+
+#define DWCPN_PANIC -3
+
+#define DWCPN_LOW_SUN_TRIANGLE 0
+
+#define DWCPN_LOW_SUN_SPECTRAL 1
+
+#define DWCPN_PRODUCTION_SPECTRAL 0
+
+#define DWCPN_PRODUCTION_BROADBAND 1
+
+#define DWCPN_PRECISION_F64 0
+
+#define DWCPN_PRECISION_F32 1
+
+#define DWCPN_SOLAR_SPECTRUM_LEGACY 0
+
+#define DWCPN_SOLAR_SPECTRUM_REFERENCE 1
+
+#define DWCPN_TEMPERATURE_NONE 0
+
+#define DWCPN_TEMPERATURE_EPPLEY 1
+
+#define DWCPN_TEMPERATURE_Q10 2
+
+#define DWCPN_INTEGRATION_EUPHOTIC_DEPTH 0
+
+#define DWCPN_INTEGRATION_TENTH_PERCENT_LIGHT 1
+
+#define DWCPN_INTEGRATION_MIXED_LAYER 2
+
+#define DWCPN_INTEGRATION_FIXED_DEPTH 3
+
+#define DWCPN_INTEGRATION_SEABED 4
+
+#define DWCPN_DAYLIGHT_NORMAL 0
+
+#define DWCPN_DAYLIGHT_POLAR_DAY 1
+
+#define DWCPN_DAYLIGHT_POLAR_NIGHT 2
+
+typedef struct DwcpnInputs {
+  double lat;
+  double lon;
+  double z_bottom;
+  int32_t iday;
+  int32_t year;
+  int32_t month;
+  int32_t day;
+  double utc_offset;
+  double alpha_b;
+  double pmb;
+  double z_m;
+  double mld;
+  double chl;
+  double rho;
+  double sigma;
+  double cloud;
+  double yel_sub;
+  double par;
+  double sst;
+  double bw[DWCPN_WL_COUNT];
+  double bbr[DWCPN_WL_COUNT];
+  double ay[DWCPN_WL_COUNT];
+} DwcpnInputs;
+
+typedef struct DwcpnSettings {
+  int32_t mld_only;
+  int32_t iom_only;
+  double zenith_cutoff;
+  int32_t low_sun_mode;
+  int32_t production_mode;
+  int32_t precision;
+  int32_t solar_spectrum;
+  double reference_spectrum[DWCPN_WL_COUNT];
+  int32_t temperature_dependence;
+  double temperature_coefficient;
+  double reference_temperature;
+  int32_t integration_limit;
+  double fixed_depth;
+} DwcpnSettings;
+
+typedef struct DwcpnOutputs {
+  double pp_day;
+  double pp_low_sun;
+  double euphotic_depth;
+  double integration_depth;
+  double pp_above_mld;
+  double pp_below_mld;
+  double spectral_i_star;
+  double par_noon_max;
+  double par;
+  double sunrise;
+  double solar_noon;
+  double sunset;
+  int32_t daylight_regime;
+  double pp_profile[DWCPN_DEPTH_PROFILE_COUNT];
+} DwcpnOutputs;
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Fill inputs with zeros, iday 1, utc_offset NaN and the default water, backscattering and
+ yellow substance spectra, so that callers only need to set the per pixel values.
+
+ # Safety
+ inputs must be null or point to a writable DwcpnInputs.
+ */
+int32_t dwcpn_default_inputs(struct DwcpnInputs *inputs);
+
+/*
+ Fill settings with the defaults of the original model: the Gaussian chlorophyll profile, an
+ 80 degree zenith cutoff with the triangle low sun approximation and the spectral model in f64.
+
+ # Safety
+ settings must be null or point to a writable DwcpnSettings.
+ */
+int32_t dwcpn_default_settings(struct DwcpnSettings *settings);
+
+/*
+ Run the model for one pixel.
+
+ # Safety
+ inputs and settings must be null or point to valid structs, outputs must be null or point to
+ a writable DwcpnOutputs.
+ */
+int32_t dwcpn_calc_production(const struct DwcpnInputs *inputs,
+                              const struct DwcpnSettings *settings,
+                              struct DwcpnOutputs *outputs);
+
+/*
+ Run the model for one pixel with surface PAR from a time series of count samples in place of
+ inputs.par: times are clock hours (UTC plus utc_offset), increasing, and par is in einsteins
+ m^-2 h^-1. The series must cover sunrise to sunset and DWCPN_INVALID_ARGUMENT is returned if
+ it does not.
+
+ # Safety
+ inputs and settings must be null or point to valid structs, times and par must be null or
+ point to arrays of at least count elements, and outputs must be null or point to a writable
+ DwcpnOutputs.
+ */
+int32_t dwcpn_calc_production_par_series(const struct DwcpnInputs *inputs,
+                                         const struct DwcpnSettings *settings,
+                                         const double *times,
+                                         const double *par,
+                                         size_t count,
+                                         struct DwcpnOutputs *outputs);
+
+/*
+ Run the model for count pixels, writing the status of each pixel to statuses. Returns
+ DWCPN_OK if all of the arguments are valid, whatever the status of the individual pixels.
+ The clear sky irradiance is computed once for each distinct day and latitude in the batch.
+
+ # Safety
+ inputs, outputs and statuses must be null or point to arrays of at least count elements, and
+ settings must be null or point to a valid DwcpnSettings.
+ */
+int32_t dwcpn_calc_production_batch(const struct DwcpnInputs *inputs,
+                                    size_t count,
+                                    const struct DwcpnSettings *settings,
+                                    struct DwcpnOutputs *outputs,
+                                    int32_t *statuses);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DWCPN_H */
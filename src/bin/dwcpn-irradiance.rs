@@ -0,0 +1,96 @@
+// Print the spectral irradiance just below the sea surface through a day as CSV, at local solar
+// times from midnight to midnight, either clear sky or scaled to a daily surface PAR (see
+// dwcpn::dwcpn::surface_irradiance). With --spectra the direct and diffuse spectra follow the PAR
+// columns, one column per wavelength. --reference-spectrum uses a reference extra-terrestrial
+// spectrum file (see dwcpn::dwcpn::solar_spectrum) in place of the legacy tables.
+//
+// usage: dwcpn-irradiance <lat> <day of year> [--par <einsteins m^-2 d^-1>] [--cloud <percent>]
+//        [--step <hours>] [--year-length <days>] [--reference-spectrum <file>] [--spectra]
+
+use dwcpn::dwcpn::modules::config::WL_ARRAY;
+use dwcpn::dwcpn::solar_spectrum::read_reference_spectrum;
+use dwcpn::dwcpn::surface_irradiance::{regular_times, ParScaling, SurfaceIrradianceDay};
+use dwcpn::SolarSpectrum;
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("usage: dwcpn-irradiance <lat> <day of year> [--par <einsteins m^-2 d^-1>] [--cloud <percent>]");
+    eprintln!("                        [--step <hours>] [--year-length <days>] [--reference-spectrum <file>] [--spectra]");
+    exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let lat: f64 = args.first().and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+    let day_of_year: u16 = args.get(1).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+    if !(-90.0..=90.0).contains(&lat) || !(1..=366).contains(&day_of_year) {
+        usage();
+    }
+
+    let mut scaling = ParScaling::ClearSky;
+    let mut cloud = 0.0;
+    let mut step = 0.5;
+    let mut year_length = 365.0;
+    let mut spectra = false;
+    let mut solar_spectrum = SolarSpectrum::Legacy;
+
+    let mut i = 2;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        if flag == "--spectra" {
+            spectra = true;
+            i += 1;
+            continue;
+        }
+        if flag == "--reference-spectrum" {
+            let path = args.get(i + 1).unwrap_or_else(|| usage());
+            let reference = read_reference_spectrum(path).unwrap_or_else(|e| {
+                eprintln!("could not read {}: {:?}", path, e);
+                exit(1);
+            });
+            solar_spectrum = SolarSpectrum::Reference(Box::new(reference));
+            i += 2;
+            continue;
+        }
+
+        i += 1;
+        let value: f64 = args.get(i).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+        match flag {
+            "--par" => scaling = ParScaling::DailyPar(value),
+            "--cloud" => cloud = value,
+            "--step" if value > 0.0 => step = value,
+            "--year-length" => year_length = value,
+            _ => usage()
+        }
+        i += 1;
+    }
+
+    let day = SurfaceIrradianceDay::new(day_of_year, year_length, lat, solar_spectrum);
+
+    let mut header = String::from("solar_time,zenith,par_above_surface,par_below_surface");
+    if spectra {
+        for component in ["direct", "diffuse"] {
+            for wavelength in WL_ARRAY {
+                header.push_str(&format!(",{}_{}", component, wavelength));
+            }
+        }
+    }
+    println!("{}", header);
+
+    for irradiance in day.series(&regular_times(step), cloud, scaling) {
+        let mut line = format!(
+            "{:.4},{:.4},{:.6},{:.6}",
+            irradiance.solar_time,
+            irradiance.zenith,
+            irradiance.par_above_surface,
+            irradiance.par_below_surface
+        );
+        if spectra {
+            for value in irradiance.direct.iter().chain(irradiance.diffuse.iter()) {
+                line.push_str(&format!(",{:.6e}", value));
+            }
+        }
+        println!("{}", line);
+    }
+}
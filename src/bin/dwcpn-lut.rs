@@ -0,0 +1,72 @@
+// Generate a lookup table for dwcpn::dwcpn::lookup with the standard axes, or check the accuracy
+// of an existing table against the full model for the pixels of a match-up file (see
+// dwcpn::dwcpn::validation for the file format, the observed_pp column is not used).
+//
+// usage: dwcpn-lut generate <table> [--low-sun-spectral] [--zenith-cutoff <degrees>]
+//        dwcpn-lut check <table> <matchups.csv>
+
+use dwcpn::dwcpn::lookup::{check_accuracy, generate_lookup_table, standard_reference, LookupAxes, LookupTable};
+use dwcpn::dwcpn::validation::read_matchups;
+use dwcpn::{LowSunMode, ModelInputs, ModelSettings};
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("usage: dwcpn-lut generate <table> [--low-sun-spectral] [--zenith-cutoff <degrees>]");
+    eprintln!("       dwcpn-lut check <table> <matchups.csv>");
+    exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut settings = ModelSettings::default();
+
+    match (args.first().map(|arg| arg.as_str()), args.get(1)) {
+        (Some("generate"), Some(path)) => {
+            let mut i = 2;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--low-sun-spectral" => settings.low_sun_mode = LowSunMode::Spectral,
+                    "--zenith-cutoff" => {
+                        i += 1;
+                        settings.zenith_cutoff = args.get(i).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+                    },
+                    _ => usage()
+                }
+                i += 1;
+            }
+
+            let table = generate_lookup_table(LookupAxes::standard(), &standard_reference(), &settings).unwrap_or_else(|e| {
+                eprintln!("could not generate table: {:?}", e);
+                exit(1);
+            });
+            if let Err(e) = table.write(path) {
+                eprintln!("could not write {}: {:?}", path, e);
+                exit(1);
+            }
+        },
+        (Some("check"), Some(path)) => {
+            let matchups_path = args.get(2).unwrap_or_else(|| usage());
+
+            let table = LookupTable::read(path).unwrap_or_else(|e| {
+                eprintln!("could not read {}: {:?}", path, e);
+                exit(1);
+            });
+            let matchups = read_matchups(matchups_path).unwrap_or_else(|e| {
+                eprintln!("could not read {}: {:?}", matchups_path, e);
+                exit(1);
+            });
+
+            settings.zenith_cutoff = table.zenith_cutoff;
+            settings.low_sun_mode = table.low_sun_mode;
+
+            let inputs: Vec<ModelInputs> = matchups.into_iter().map(|matchup| matchup.inputs).collect();
+            let statistics = check_accuracy(&table, &inputs, &settings);
+
+            println!("pixels within the table: {} of {}", statistics.count, inputs.len());
+            println!("bias {:.3} rmsd {:.3} r {:.4} slope {:.4}", statistics.bias, statistics.rmsd, statistics.pearson_r, statistics.type_ii_slope);
+            println!("log bias {:.4} log rmsd {:.4}", statistics.log_bias, statistics.log_rmsd);
+        },
+        _ => usage()
+    }
+}
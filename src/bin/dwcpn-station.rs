@@ -0,0 +1,111 @@
+// Run the model through a daily station time series and print the daily outputs as CSV, or the
+// monthly or annual totals with --monthly or --annual. See dwcpn::dwcpn::station for the file
+// format. Gaps are not filled unless --persist or --interpolate gives the longest gap (days) to fill.
+//
+// usage: dwcpn-station <station.csv> [--monthly | --annual] [--persist <days> | --interpolate <days>]
+//        [--low-sun-spectral] [--broadband] [--zenith-cutoff <degrees>]
+
+use dwcpn::dwcpn::modules::parameters::ModelParameter;
+use dwcpn::dwcpn::station::{read_station_series, run_station, GapFilling, PeriodTotal};
+use dwcpn::{LowSunMode, ModelSettings, ProductionMode};
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("usage: dwcpn-station <station.csv> [--monthly | --annual] [--persist <days> | --interpolate <days>]");
+    eprintln!("                     [--low-sun-spectral] [--broadband] [--zenith-cutoff <degrees>]");
+    exit(2);
+}
+
+fn format_option(value: Option<f64>) -> String {
+    value.map_or(String::new(), |value| format!("{:.4}", value))
+}
+
+fn format_names(parameters: &[ModelParameter]) -> String {
+    parameters.iter().map(|parameter| parameter.name()).collect::<Vec<&str>>().join(" ")
+}
+
+fn print_totals(totals: &[PeriodTotal]) {
+    println!("year,month,days,modelled_days,pp_total,pp_estimated_total");
+    for total in totals {
+        println!(
+            "{},{},{},{},{:.4},{:.4}",
+            total.year,
+            total.month.map_or(String::new(), |month| month.to_string()),
+            total.days,
+            total.modelled_days,
+            total.pp_total,
+            total.pp_estimated_total
+        );
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut path: Option<String> = None;
+    let mut monthly = false;
+    let mut annual = false;
+    let mut gap_filling = GapFilling::None;
+    let mut settings = ModelSettings::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        let next_value = |i: usize| args.get(i + 1).and_then(|value| value.parse::<f64>().ok()).unwrap_or_else(|| usage());
+        match args[i].as_str() {
+            "--monthly" => monthly = true,
+            "--annual" => annual = true,
+            "--persist" => {
+                gap_filling = GapFilling::Persistence { max_days: next_value(i) as usize };
+                i += 1;
+            },
+            "--interpolate" => {
+                gap_filling = GapFilling::Linear { max_days: next_value(i) as usize };
+                i += 1;
+            },
+            "--low-sun-spectral" => settings.low_sun_mode = LowSunMode::Spectral,
+            "--broadband" => settings.production_mode = ProductionMode::Broadband,
+            "--zenith-cutoff" => {
+                settings.zenith_cutoff = next_value(i);
+                i += 1;
+            },
+            "-h" | "--help" => usage(),
+            other if path.is_none() && !other.starts_with("--") => path = Some(other.to_string()),
+            _ => usage()
+        }
+        i += 1;
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+    if monthly && annual {
+        usage();
+    }
+
+    let run = read_station_series(&path)
+        .and_then(|series| run_station(&series, &settings, gap_filling))
+        .unwrap_or_else(|e| {
+            eprintln!("could not run {}: {:?}", path, e);
+            exit(1);
+        });
+
+    if monthly {
+        print_totals(&run.monthly);
+    } else if annual {
+        print_totals(&run.annual);
+    } else {
+        println!("date,pp_day,euphotic_depth,par,filled,missing,error");
+        for day in run.daily.iter() {
+            println!(
+                "{:04}-{:02}-{:02},{},{},{},{},{},{}",
+                day.date.year(),
+                day.date.month(),
+                day.date.day(),
+                format_option(day.pp_day),
+                format_option(day.euphotic_depth),
+                format_option(day.par),
+                format_names(&day.filled),
+                format_names(&day.missing),
+                day.error.as_ref().map_or(String::new(), |error| format!("{:?}", error))
+            );
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Compare modelled daily production with an in-situ match-up file and print validation
+// statistics, overall and per region. See dwcpn::dwcpn::validation for the file format.
+//
+// usage: dwcpn-validate <matchups.csv> [--mld-only] [--low-sun-spectral] [--broadband] [--zenith-cutoff <degrees>]
+
+use dwcpn::dwcpn::validation::{read_matchups, validate, ValidationStatistics};
+use dwcpn::{LowSunMode, ModelSettings, ProductionMode};
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!("usage: dwcpn-validate <matchups.csv> [--mld-only] [--low-sun-spectral] [--broadband] [--zenith-cutoff <degrees>]");
+    exit(2);
+}
+
+fn print_statistics(name: &str, statistics: &ValidationStatistics) {
+    println!(
+        "{:<20} {:>6} {:>10.2} {:>10.2} {:>7.3} {:>8.3} {:>9.3} {:>9.3} {:>7.3} {:>8.3}",
+        name,
+        statistics.count,
+        statistics.bias,
+        statistics.rmsd,
+        statistics.pearson_r,
+        statistics.type_ii_slope,
+        statistics.log_bias,
+        statistics.log_rmsd,
+        statistics.log_pearson_r,
+        statistics.log_type_ii_slope
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut path: Option<String> = None;
+    let mut settings = ModelSettings::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--mld-only" => settings.mld_only = true,
+            "--low-sun-spectral" => settings.low_sun_mode = LowSunMode::Spectral,
+            "--broadband" => settings.production_mode = ProductionMode::Broadband,
+            "--zenith-cutoff" => {
+                i += 1;
+                settings.zenith_cutoff = args.get(i).and_then(|value| value.parse().ok()).unwrap_or_else(|| usage());
+            },
+            "-h" | "--help" => usage(),
+            other if path.is_none() && !other.starts_with("--") => path = Some(other.to_string()),
+            _ => usage()
+        }
+        i += 1;
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+
+    let matchups = match read_matchups(&path) {
+        Ok(matchups) => matchups,
+        Err(e) => {
+            eprintln!("could not read {}: {:?}", path, e);
+            exit(1);
+        }
+    };
+
+    let report = validate(&matchups, &settings);
+
+    println!(
+        "{:<20} {:>6} {:>10} {:>10} {:>7} {:>8} {:>9} {:>9} {:>7} {:>8}",
+        "", "n", "bias", "rmsd", "r", "slope", "log bias", "log rmsd", "log r", "log slope"
+    );
+    print_statistics("all", &report.overall);
+    for (region, statistics) in report.regions.iter() {
+        print_statistics(region, statistics);
+    }
+
+    if !report.failed.is_empty() {
+        println!("\nmodel failed for {} match-ups: {}", report.failed.len(), report.failed.join(", "));
+    }
+}
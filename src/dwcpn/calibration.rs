@@ -0,0 +1,227 @@
+// Inverse calibration of model inputs (e.g. alpha_b, pmb or the Gaussian chlorophyll profile
+// parameters) against observed production, typically in-situ 14C profiles, using calc_production
+// as the forward model.
+
+use crate::dwcpn::dwcpn::calc_production;
+use crate::dwcpn::modules::chl_profile::gen_depth_array;
+use crate::dwcpn::modules::linear_interp::linear_interp;
+use crate::dwcpn::modules::optimise::nelder_mead;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::{DEPTH_PROFILE_COUNT, ModelInputs, ModelSettings, PPErrors};
+
+pub struct CalibrationParameter {
+    pub parameter: ModelParameter,
+    // the search is kept within these bounds
+    pub lower: f64,
+    pub upper: f64,
+}
+
+pub struct CalibrationObservations {
+    // (depth, daily production) pairs in m and mgC m^-3 d^-1
+    pub depth_production: Vec<(f64, f64)>,
+    // daily water column production in mgC m^-2 d^-1
+    pub water_column_production: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MisfitSpace {
+    // squared differences relative to the mean observation
+    Relative,
+    // squared differences of natural logs, suited to data spanning orders of magnitude
+    Log,
+}
+
+pub struct CalibrationSettings {
+    pub parameters: Vec<CalibrationParameter>,
+    pub misfit_space: MisfitSpace,
+    // weight of the water column term relative to the mean depth misfit
+    pub water_column_weight: f64,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+}
+
+pub struct CalibrationResult {
+    pub inputs: ModelInputs,
+    pub values: Vec<(ModelParameter, f64)>,
+    pub initial_cost: f64,
+    pub cost: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+fn misfit(modelled: f64, observed: f64, scale: f64, misfit_space: MisfitSpace) -> f64 {
+    match misfit_space {
+        MisfitSpace::Relative => ((modelled - observed) / scale).powi(2),
+        MisfitSpace::Log => (modelled.max(f64::MIN_POSITIVE).ln() - observed.max(f64::MIN_POSITIVE).ln()).powi(2),
+    }
+}
+
+// cost of a set of inputs against the observations, infinite if the model fails
+pub fn calibration_cost(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    observations: &CalibrationObservations,
+    misfit_space: MisfitSpace,
+    water_column_weight: f64
+) -> f64 {
+    let outputs = match calc_production(input, settings) {
+        Ok(outputs) => outputs,
+        Err(_) => return f64::INFINITY
+    };
+
+    let mut cost: f64 = 0.0;
+
+    if !observations.depth_production.is_empty() {
+        let depth_array = gen_depth_array();
+        let pp_profile = outputs.pp_profile.unwrap_or([0.0; DEPTH_PROFILE_COUNT]);
+
+        let scale = observations.depth_production.iter().map(|(_, p)| p.abs()).sum::<f64>()
+            / observations.depth_production.len() as f64;
+
+        let mut depth_cost: f64 = 0.0;
+        for (depth, observed) in observations.depth_production.iter() {
+            let depth = depth.clamp(depth_array[0], depth_array[depth_array.len() - 1]);
+            let modelled = linear_interp(&depth_array, &pp_profile, depth);
+            depth_cost += misfit(modelled, *observed, scale, misfit_space);
+        }
+        cost += depth_cost / observations.depth_production.len() as f64;
+    }
+
+    if let Some(observed) = observations.water_column_production {
+        let modelled = outputs.pp_day.unwrap_or(0.0);
+        cost += water_column_weight * misfit(modelled, observed, observed.abs(), misfit_space);
+    }
+
+    cost
+}
+
+// adjust the chosen inputs to minimise the misfit between modelled and observed production
+pub fn calibrate(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    observations: &CalibrationObservations,
+    calibration_settings: &CalibrationSettings
+) -> Result<CalibrationResult, PPErrors> {
+    let parameters = &calibration_settings.parameters;
+
+    let start: Vec<f64> = parameters.iter()
+        .map(|p| p.parameter.get(input).clamp(p.lower, p.upper))
+        .collect();
+
+    // initial simplex spans a tenth of each range, stepping away from the nearest bound
+    let step: Vec<f64> = parameters.iter().zip(start.iter())
+        .map(|(p, value)| {
+            let step = 0.1 * (p.upper - p.lower);
+            if value + step > p.upper { -step } else { step }
+        })
+        .collect();
+
+    let inputs_at = |point: &[f64]| -> ModelInputs {
+        let mut trial = input.clone();
+        for (p, value) in parameters.iter().zip(point.iter()) {
+            p.parameter.set(&mut trial, *value);
+        }
+        trial
+    };
+
+    let cost_at = |point: &[f64]| -> f64 {
+        let in_bounds = parameters.iter().zip(point.iter())
+            .all(|(p, value)| *value >= p.lower && *value <= p.upper && p.parameter.is_valid(*value));
+
+        if !in_bounds {
+            return f64::INFINITY;
+        }
+
+        calibration_cost(
+            &inputs_at(point),
+            settings,
+            observations,
+            calibration_settings.misfit_space,
+            calibration_settings.water_column_weight
+        )
+    };
+
+    let initial_cost = cost_at(&start);
+    if !initial_cost.is_finite() {
+        return Err(PPErrors::DWCPNError);
+    }
+
+    let result = nelder_mead(
+        cost_at,
+        &start,
+        &step,
+        calibration_settings.max_iterations,
+        calibration_settings.tolerance
+    );
+
+    Ok(
+        CalibrationResult {
+            inputs: inputs_at(&result.point),
+            values: parameters.iter().map(|p| p.parameter).zip(result.point.iter().copied()).collect(),
+            initial_cost,
+            cost: result.value,
+            iterations: result.iterations,
+            converged: result.converged
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_fixtures::model_inputs;
+
+    #[test]
+    fn test_recover_photosynthetic_parameters() {
+        let mut inputs = ModelInputs {
+            lat: 18.71,
+            lon: -18.625,
+            z_bottom: 2950.468,
+            iday: 121,
+            alpha_b: 0.1518,
+            pmb: 3.9059,
+            z_m: 23.094,
+            mld: 31.975,
+            chl: 1.718,
+            rho: 0.8247,
+            sigma: 27.556,
+            par: Some(55.8677),
+            ..model_inputs()
+        };
+
+        let settings = ModelSettings::default();
+
+        // synthetic observations from the true parameters
+        let truth = calc_production(&inputs, &settings).unwrap();
+        let depth_array = gen_depth_array();
+        let pp_profile = truth.pp_profile.unwrap();
+        let observations = CalibrationObservations {
+            depth_production: [0.0, 5.0, 10.0, 20.0, 30.0]
+                .iter()
+                .map(|depth| (*depth, linear_interp(&depth_array, &pp_profile, *depth)))
+                .collect(),
+            water_column_production: truth.pp_day
+        };
+
+        inputs.alpha_b = 0.1;
+        inputs.pmb = 5.0;
+
+        let calibration_settings = CalibrationSettings {
+            parameters: vec![
+                CalibrationParameter { parameter: ModelParameter::AlphaB, lower: 0.01, upper: 0.5 },
+                CalibrationParameter { parameter: ModelParameter::Pmb, lower: 0.5, upper: 15.0 },
+            ],
+            misfit_space: MisfitSpace::Relative,
+            water_column_weight: 1.0,
+            max_iterations: 150,
+            tolerance: 1e-8
+        };
+
+        let result = calibrate(&inputs, &settings, &observations, &calibration_settings).unwrap();
+
+        assert!(result.cost < result.initial_cost);
+        assert!((result.inputs.alpha_b / 0.1518 - 1.0).abs() < 0.02);
+        assert!((result.inputs.pmb / 3.9059 - 1.0).abs() < 0.02);
+    }
+}
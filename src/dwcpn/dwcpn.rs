@@ -1,37 +1,193 @@
+use crate::dwcpn::modules::broadband::calc_broadband_production;
 use crate::dwcpn::modules::chl_profile::{gen_chl_profile};
-use crate::dwcpn::modules::config::{DEPTH_PROFILE_STEP, TIMESTEPS};
-use crate::dwcpn::modules::irradiance::{compute_irradiance_components, correct_and_recompute_irradiance_components, lookup_thekaekara_correction};
-use crate::dwcpn::modules::pp_profile::{compute_pp_depth_profile, compute_prochloro_profile};
-use crate::dwcpn::modules::time::{compute_sunrise, generate_time_array};
-use crate::dwcpn::modules::zenith::{generate_zenith_array, compute_zenith_time};
-use std::f64::consts::PI;
-use crate::dwcpn::modules::light_profile::calc_light_decay_profile;
-use crate::{DEPTH_PROFILE_COUNT, ModelInputs, ModelOutputs, ModelSettings, PPErrors};
+use crate::dwcpn::modules::clear_sky::{ClearSkyCache, ClearSkyIrradiance};
+use crate::dwcpn::modules::config::{DEPTH_PROFILE_STEP, LOW_SUN_TIMESTEPS, TIMESTEPS, WL_COUNT};
+use crate::dwcpn::modules::irradiance::{compute_iom, compute_par_surface_irradiance, compute_polar_day_par_surface_irradiance, correct_and_recompute_irradiance_components};
+use crate::dwcpn::modules::pp_profile::{compute_pp_depth_profile, compute_prochloro_profile, compute_size_class_chl_profiles, compute_size_class_pp_profiles, PpProfile};
+use crate::dwcpn::modules::temperature::{adjust_pmb, temperature_adjusted_inputs};
+use crate::dwcpn::modules::time::{compute_solar_time_offset, DaylightRegime};
+use crate::dwcpn::modules::zenith::compute_zenith;
+use crate::dwcpn::modules::light_profile::{calc_size_class_alpha_coefficients, OpticalProfile};
+use crate::dwcpn::surface_irradiance::resolve_daily_par;
+use crate::{DEPTH_PROFILE_COUNT, IntegrationLimit, LowSunMode, ModelInputs, ModelOutputs, ModelSettings, PPErrors, ProductionMode, SizeClassOutputs};
 
 
 pub fn calc_production(input: &ModelInputs, settings: &ModelSettings) -> Result<ModelOutputs, PPErrors> {
+    let clear_sky = ClearSkyIrradiance::for_inputs(input, settings);
+    calc_production_with_clear_sky(input, settings, &clear_sky)
+}
+
+// run the model for a batch of pixels, computing the clear sky irradiance only once for each
+// distinct day and latitude (e.g. once per row of a global image), see ClearSkyCache
+pub fn calc_production_batch<'a>(
+    inputs: impl IntoIterator<Item = &'a ModelInputs>,
+    settings: &ModelSettings
+) -> Vec<Result<ModelOutputs, PPErrors>> {
+    let mut clear_sky_cache = ClearSkyCache::default();
+
+    inputs.into_iter()
+        .map(|input| calc_production_with_clear_sky(input, settings, clear_sky_cache.for_inputs(input, settings)))
+        .collect()
+}
+
+// run the model with clear sky irradiance that has already been computed for the day and
+// latitude of the inputs, see ClearSkyIrradiance
+pub fn calc_production_with_clear_sky(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    clear_sky: &ClearSkyIrradiance
+) -> Result<ModelOutputs, PPErrors> {
+
+    // a zenith angle between the zenith and the horizon, otherwise there is no time window to
+    // integrate over (or the whole day is one)
+    if !(0.0..=90.0).contains(&settings.zenith_cutoff) {
+        return Err(PPErrors::UnsupportedSettings);
+    }
+
+    if !clear_sky.matches(input, settings) {
+        return Err(PPErrors::ClearSkyMismatch);
+    }
+
+    // pmb at the sea surface temperature when the settings ask for it
+    let adjusted_input = temperature_adjusted_inputs(input, settings.temperature_dependence);
+    let input = adjusted_input.as_ref().unwrap_or(input);
 
     // generate chl depth profile
     let (depth_array, chl_profile) = gen_chl_profile(input, settings);
 
-    // compute sunrise and generate time array
-    let (sunrise, delta, phi) = compute_sunrise(input.iday, input.lat);
+    let sunrise = clear_sky.sunrise;
+    let delta = clear_sky.delta;
+    let phi = clear_sky.phi;
+    let daylight_regime = clear_sky.daylight_regime;
+
+    // offset used to report solar events in clock time
+    let solar_time_offset = compute_solar_time_offset(
+        clear_sky.day_of_year,
+        clear_sky.year_length,
+        input.lon,
+        input.utc_offset.unwrap_or(0.0)
+    );
+    let solar_noon = 12.0 - solar_time_offset;
+
+    // there is no sunrise or sunset to report during polar day or night
+    let (sunrise_clock, sunset_clock) = match daylight_regime {
+        DaylightRegime::Normal => (
+            Some(sunrise - solar_time_offset),
+            Some(24.0 - sunrise - solar_time_offset)
+        ),
+        _ => (None, None)
+    };
 
-    let zenith_80_time = compute_zenith_time(delta, phi, 80.0);
+    // with a PAR time series the daily PAR is its integral over the hours of daylight, which the
+    // series has to cover (the whole day during polar day, when sunrise is at midnight)
+    let par = match &input.par_series {
+        Some(series) => {
+            if !series.is_valid() {
+                return Err(PPErrors::InvalidParSeries);
+            }
+            match daylight_regime {
+                DaylightRegime::PolarNight => 0.0,
+                _ => {
+                    let (start, end) = (sunrise - solar_time_offset, 24.0 - sunrise - solar_time_offset);
+                    if !series.covers(start, end) {
+                        return Err(PPErrors::InvalidParSeries);
+                    }
+                    series.integrate(start, end)
+                }
+            }
+        },
+        None => resolve_daily_par(input, settings)
+    };
 
-    let (time_array, delta_t) = generate_time_array(zenith_80_time);
-    let (zenith_array, zenith_d_array) = generate_zenith_array(time_array, delta, phi);
+    // during continuous darkness there is no production, so skip the model entirely
+    if daylight_regime == DaylightRegime::PolarNight {
+        let pp_day = if settings.iom_only { None } else { Some(0.0) };
+        let size_classes = match (settings.size_classes, settings.production_mode, pp_day) {
+            (Some(_), ProductionMode::Spectral, Some(_)) => Some(
+                SizeClassOutputs {
+                    pp_day: [0.0; 3],
+                    pp_profile: [[0.0; DEPTH_PROFILE_COUNT]; 3],
+                    chl_profile: compute_size_class_chl_profiles(&chl_profile)
+                }
+            ),
+            _ => None
+        };
+        return Ok(
+            ModelOutputs {
+                pp_day,
+                pp_low_sun: pp_day,
+                pp_profile: pp_day.map(|_| [0.0; DEPTH_PROFILE_COUNT]),
+                euphotic_depth: None,
+                integration_depth: None,
+                pp_above_mld: None,
+                pp_below_mld: None,
+                spectral_i_star: None,
+                par_noon_max: Some(0.0),
+                par: Some(par),
+                sunrise: sunrise_clock,
+                solar_noon: Some(solar_noon),
+                sunset: sunset_clock,
+                daylight_regime: Some(daylight_regime),
+                pro_1_profile: None,
+                pro_2_profile: None,
+                pro_total_profile: None,
+                pp_prochloro_profile: None,
+                size_classes
+            }
+        )
+    }
+
+    // during polar day compute_sunrise places sunrise at midnight, so the
+    // sunrise to noon time array covers half of the 24 hour day
+    let day_length = 2.0 * (12.0 - sunrise);
+
+    // iom = noon time maximum
+    let iom = match &input.par_series {
+        Some(series) => series.at(solar_noon),
+        None => compute_iom(par, daylight_regime, day_length, delta, phi)
+    };
 
-    let mut start_time_idx: f64 = -1.0;
-    let mut day_length: f64 = 0.0;
-    let mut iom: f64 = 0.0;
-    let mut delta_prestart: f64 = 0.0;
+    if settings.iom_only {
+        return Ok(
+            ModelOutputs {
+                pp_day: None,
+                pp_low_sun: None,
+                pp_profile: None,
+                euphotic_depth: None,
+                integration_depth: None,
+                pp_above_mld: None,
+                pp_below_mld: None,
+                spectral_i_star: None,
+                par_noon_max: Some(iom),
+                par: Some(par),
+                sunrise: sunrise_clock,
+                solar_noon: Some(solar_noon),
+                sunset: sunset_clock,
+                daylight_regime: Some(daylight_regime),
+                pro_1_profile: None,
+                pro_2_profile: None,
+                pro_total_profile: None,
+                pp_prochloro_profile: None,
+                size_classes: None
+            }
+        )
+    }
+
+    let start_time = clear_sky.start_time;
+    let integration_window = clear_sky.integration_window;
+    let time_array = clear_sky.time_array;
+    let delta_t = clear_sky.delta_t;
+    let zenith_array = clear_sky.zenith_array;
+    let zenith_d_array = clear_sky.zenith_d_array;
+    let solar_correction = clear_sky.solar_correction;
 
-    let solar_correction = lookup_thekaekara_correction(input.iday);
+    // delta_prestart is time elapsed between dawn and start_time
+    // to be used for daily integration purposes later.
+    let delta_prestart: f64 = if integration_window { start_time - sunrise } else { 0.0 };
 
-    // arrays to store results
-    let mut pp: [f64; TIMESTEPS] = [0.0; TIMESTEPS];
-    let mut euphotic_depth: [f64; TIMESTEPS] = [0.0; TIMESTEPS];
+    // production with depth integrated over the day, accumulated with the same trapezoidal
+    // weights as pp_day
+    let mut daily_pp_profile: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
 
     let mut pro_1_profile: Option<[f64; DEPTH_PROFILE_COUNT]> = None;
     let mut pro_2_profile: Option<[f64; DEPTH_PROFILE_COUNT]> = None;
@@ -45,164 +201,335 @@ pub fn calc_production(input: &ModelInputs, settings: &ModelSettings) -> Result<
     };
     let mut pro_total_count: usize = 0;
 
+    // the size classes share the light field of the total but have their own chlorophyll, absorption
+    // and photosynthetic parameters, with pmb at the sea surface temperature as for the total
+    let size_class_alpha_coefficients = settings.size_classes
+        .map(|size_classes| calc_size_class_alpha_coefficients(size_classes.alpha_b));
+    let size_class_pmb = settings.size_classes.map(|size_classes| size_classes.pmb.map(|pmb| match input.sst {
+        Some(sst) => adjust_pmb(pmb, sst, settings.temperature_dependence),
+        None => pmb
+    }));
+    let size_class_chl_profiles = settings.size_classes.map(|_| compute_size_class_chl_profiles(&chl_profile));
+    let mut size_classes: Option<SizeClassOutputs> = size_class_chl_profiles.map(|chl_profile| SizeClassOutputs {
+        pp_day: [0.0; 3],
+        pp_profile: [[0.0; DEPTH_PROFILE_COUNT]; 3],
+        chl_profile
+    });
+
+    // production and production profile of each size class at a time step, integrated down to the
+    // euphotic depth of the total
+    let size_class_production = |size_class_i_alpha_profiles: Option<[[f64; DEPTH_PROFILE_COUNT]; 3]>, pp_profile: &PpProfile| {
+        match (&size_class_chl_profiles, size_class_i_alpha_profiles, size_class_pmb) {
+            (Some(chl_profiles), Some(i_alpha_profiles), Some(pmb)) => {
+                let pp_profiles = compute_size_class_pp_profiles(chl_profiles, &i_alpha_profiles, pmb, &pp_profile.pp_profile);
+                let pp = pp_profiles.map(|class_profile| {
+                    integrate_to_limit(&class_profile, pp_profile, settings.integration_limit)
+                });
+                Some((pp, pp_profiles))
+            },
+            _ => None
+        }
+    };
+
     // spectral i star is calculated as a running mean
     let mut spectral_i_star_sum: f64 = 0.0;
     let mut spectral_i_star_count: f64 = 0.0;
 
-    let mut start_time: f64;
+    // surface PAR at a given solar time, which the spectral irradiance is scaled to
+    let surface_par_at = |time: f64, zenith_r: f64| match (&input.par_series, daylight_regime) {
+        (Some(series), _) => series.at(time - solar_time_offset),
+        (None, DaylightRegime::PolarDay) => compute_polar_day_par_surface_irradiance(
+            par,
+            zenith_r,
+            delta,
+            phi
+        ),
+        (None, _) => compute_par_surface_irradiance(iom, time, sunrise, day_length)
+    };
 
-    // loop over time array (from sunrise to noon)
-    for t in 0..TIMESTEPS {
-        // if the zenith angle is yet to go below 80° then skip to the next time step
-        if zenith_d_array[t] >= 80.00005 {
-            continue;
+    if settings.production_mode == ProductionMode::Broadband {
+        if settings.integration_limit != IntegrationLimit::EuphoticDepth {
+            return Err(PPErrors::UnsupportedSettings);
         }
 
-        // update start_time if necessary and assign delta_prestart variable
-        // to be used for daily integration purposes later.
-        // t_start is t_idx value when Zenith angle becomes >80 degrees
-        // start_time is calculation start time, start_time_index is the index
-        // delta_prestart is time elapsed between dawn and start_time
-        if start_time_idx < 0.0 {
-            start_time_idx = t as f64;
-
-            // this line has start_time_idx corrected with +1 as the original code
-            // came from FORTRAN which uses indices that start at 1
-            // start_time = sunrise.clone() + delta_t.clone() * (start_time_idx);
-            start_time = zenith_80_time;
-
-            day_length = 2.0 * (12.0 - sunrise);
-
-            // iom = noon time maximum
-            iom = input.par * PI / (2.0 * day_length);
-            delta_prestart = start_time - sunrise;
-
-            if settings.iom_only == true {
-                return Ok(
-                    ModelOutputs {
-                        pp_day: None,
-                        euphotic_depth: None,
-                        spectral_i_star: None,
-                        par_noon_max: Some(iom),
-                        pro_1_profile: None,
-                        pro_2_profile: None,
-                        pro_total_profile: None,
-                        pp_prochloro_profile: None
-                    }
-                )
-            }
+        let mut broadband = calc_broadband_production(&chl_profile, input, sunrise, |time| {
+            surface_par_at(time, compute_zenith(time, delta, phi))
+        });
 
+        // the afternoon of a PAR time series, at the mirrored solar times as for the spectral model
+        if input.par_series.is_some() {
+            let afternoon = calc_broadband_production(&chl_profile, input, sunrise, |time| {
+                surface_par_at(24.0 - time, compute_zenith(time, delta, phi))
+            });
+            broadband = broadband.mean_with(&afternoon);
         }
 
-        // compute direct and diffuse irradiance components at sea level
-        let (direct, diffuse) =
-            compute_irradiance_components(zenith_array[t], zenith_d_array[t]);
+        return if broadband.pp_day > 10000.0 {
+            Err(PPErrors::PPTooHigh)
+        } else {
+            Ok(
+                ModelOutputs {
+                    pp_day: Some(broadband.pp_day),
+                    pp_low_sun: None,
+                    pp_profile: Some(broadband.pp_profile),
+                    euphotic_depth: Some(broadband.euphotic_depth),
+                    integration_depth: Some(broadband.euphotic_depth),
+                    pp_above_mld: None,
+                    pp_below_mld: None,
+                    spectral_i_star: None,
+                    par_noon_max: Some(iom),
+                    par: Some(par),
+                    sunrise: sunrise_clock,
+                    solar_noon: Some(solar_noon),
+                    sunset: sunset_clock,
+                    daylight_regime: Some(daylight_regime),
+                    pro_1_profile: None,
+                    pro_2_profile: None,
+                    pro_total_profile: None,
+                    pp_prochloro_profile: None,
+                    size_classes: None
+                }
+            )
+        };
+    }
 
+    // the water column optics depend only on the chlorophyll profile, not on time
+    let optical_profile = OpticalProfile::new(&chl_profile, input, settings.precision);
+
+    // the light field is followed below the 0.1% light level only when production may be
+    // integrated deeper
+    let par_cutoff = match settings.integration_limit {
+        IntegrationLimit::EuphoticDepth | IntegrationLimit::TenthPercentLight => 0.001,
+        _ => 0.0
+    };
+
+    // spectral light field with depth from the clear sky irradiance components at sea level,
+    // corrected for cloud and scaled to the surface PAR
+    let light_profiles_at = |direct: [f64; WL_COUNT], diffuse: [f64; WL_COUNT], zenith_r: f64, par_surface_irradiance: f64| {
         let (direct_corrected, diffuse_corrected) = correct_and_recompute_irradiance_components(
             direct,
             diffuse,
             solar_correction,
-            iom,
-            day_length,
-            sunrise,
-            zenith_array[t],
-            time_array[t],
+            par_surface_irradiance,
+            zenith_r,
             input.cloud
         );
 
-        let (i_alpha_profile, par_profile) = calc_light_decay_profile(
-            chl_profile,
+        optical_profile.light_decay_profile(
             direct_corrected,
             diffuse_corrected,
-            zenith_array[t],
-            &input
-        );
-
-
-        let pp_profile = compute_pp_depth_profile(
-            &chl_profile,
-            &depth_array,
-            &i_alpha_profile,
-            &par_profile,
-            &input
-        );
-
-        match pp_profile {
-            Ok(mut pp_profile) => {
-                euphotic_depth[t] = pp_profile.euphotic_depth;
-
-                if pp_profile.euph_index == 0 { pp_profile.euph_index = 1; }
-
-                for z in 0..pp_profile.euph_index {
-                    pp[t] = pp[t] + DEPTH_PROFILE_STEP * (pp_profile.pp_profile[z] + pp_profile.pp_profile[z + 1]) / 2.0;
-                }
-
-                pp[t] = pp[t]
-                    + pp_profile.pp_profile[pp_profile.euph_index]
-                    * (euphotic_depth[t]
-                    - (pp_profile.euph_index as f64 - 1.0) * DEPTH_PROFILE_STEP);
+            zenith_r,
+            size_class_alpha_coefficients.as_ref(),
+            par_cutoff
+        )
+    };
 
-                spectral_i_star_sum = spectral_i_star_sum + (pp_profile.spectral_i_star / (pp_profile.euph_index as f64).abs());
-                spectral_i_star_count = spectral_i_star_count + 1.0;
-            },
-            Err(_) => {}
-        }
+    // without a PAR time series the day is symmetric about noon, so the morning is integrated and
+    // doubled. A time series need not be, so the afternoon is integrated as well at the mirrored
+    // solar times 24 - t, where the clear sky irradiance and zenith angles are the same.
+    let halves: &[bool] = if input.par_series.is_some() { &[false, true] } else { &[false] };
 
+    let mut pp_day: f64 = 0.0;
+    let mut pp_low_sun: f64 = 0.0;
+    let mut max_euphotic_depth: f64 = 0.0;
+    let mut max_integration_depth: f64 = 0.0;
+    // production above and below the mixed layer depth, accumulated with the same weights as the
+    // daily production profile
+    let mut pp_layers: [f64; 2] = [0.0; 2];
+
+    for &afternoon in halves {
+        let solar_time = |time: f64| if afternoon { 24.0 - time } else { time };
+
+        // arrays to store results, the profile at start_time is kept for the low sun period
+        let mut pp: [f64; TIMESTEPS] = [0.0; TIMESTEPS];
+        let mut start_pp_profile: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
+        let mut euphotic_depth: [f64; TIMESTEPS] = [0.0; TIMESTEPS];
+        let mut start_size_class_production: Option<([f64; 3], [[f64; DEPTH_PROFILE_COUNT]; 3])> = None;
+        let mut start_pp_layers: [f64; 2] = [0.0; 2];
+
+        // loop over time array (from sunrise to noon)
+        for t in 0..TIMESTEPS {
+            // if the zenith angle is yet to go below the cutoff then skip to the next time step
+            if !integration_window || zenith_d_array[t] >= settings.zenith_cutoff + 0.00005 {
+                continue;
+            }
 
-        if settings.prochloro_inputs.is_some() {
-            let pro_surf = &settings.prochloro_inputs.as_ref().unwrap().prochloro_surface;
-            let pro_max = &settings.prochloro_inputs.as_ref().unwrap().prochloro_maximum;
+            let (i_alpha_profile, par_profile, size_class_i_alpha_profiles) = light_profiles_at(
+                clear_sky.direct[t],
+                clear_sky.diffuse[t],
+                zenith_array[t],
+                surface_par_at(solar_time(time_array[t]), zenith_array[t])
+            );
 
-            let prochloro_profile = compute_prochloro_profile(
+            let pp_profile = compute_pp_depth_profile(
                 &chl_profile,
                 &depth_array,
                 &i_alpha_profile,
                 &par_profile,
                 &input,
-                &pro_surf,
-                &pro_max
+                settings.integration_limit
             );
 
-            match prochloro_profile {
-                Ok(mut prochloro_profile) => {
-                    euphotic_depth[t] = prochloro_profile.euphotic_depth;
+            match pp_profile {
+                Ok(mut pp_profile) => {
+                    euphotic_depth[t] = pp_profile.euphotic_depth;
+                    max_integration_depth = max_integration_depth.max(pp_profile.integration_depth);
+                    pp[t] = integrate_pp_profile(&mut pp_profile, settings.integration_limit);
+
+                    let weight = if t == 0 || t == TIMESTEPS - 1 { delta_t / 2.0 } else { delta_t };
+                    accumulate_profile(&mut daily_pp_profile, &pp_profile.pp_profile, weight);
 
-                    if prochloro_profile.euph_index == 0 { prochloro_profile.euph_index = 1; }
+                    let layers = split_at_mld(&pp_profile, pp[t], input.mld);
+                    accumulate_layers(&mut pp_layers, layers, weight);
 
-                    let mut pp_prochloro_output_profile: [f64; DEPTH_PROFILE_COUNT] = pp_prochloro_profile.unwrap();
-                    let mut pro_total_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_total_profile.unwrap();
-                    let mut pro_1_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_1_profile.unwrap();
-                    let mut pro_2_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_2_profile.unwrap();
+                    let class_production = size_class_production(size_class_i_alpha_profiles, &pp_profile);
+                    if let (Some(outputs), Some(production)) = (size_classes.as_mut(), &class_production) {
+                        accumulate_size_classes(outputs, production, weight);
+                    }
 
-                    // TODO: double check if we need to cut off at euphotic depth for prochlorococcus
-                    for z in 0..prochloro_profile.euph_index {
-                        pp_prochloro_output_profile[z] = pp_prochloro_output_profile[z] + prochloro_profile.pp_prochloro_profile[z];
-                        pro_total_output_profile[z] = pro_total_output_profile[z] + prochloro_profile.pro_sum_profile[z];
-                        pro_1_output_profile[z] = pro_1_output_profile[z] + prochloro_profile.pro_1_profile[z];
-                        pro_2_output_profile[z] = pro_2_output_profile[z] + prochloro_profile.pro_2_profile[z];
+                    if t == 0 {
+                        start_pp_profile = pp_profile.pp_profile;
+                        start_size_class_production = class_production;
+                        start_pp_layers = layers;
                     }
 
-                    pro_total_count += 1;
-                    pp_prochloro_profile = Some(pp_prochloro_output_profile);
-                    pro_total_profile = Some(pro_total_output_profile);
-                    pro_1_profile = Some(pro_1_output_profile);
-                    pro_2_profile = Some(pro_2_output_profile);
+                    spectral_i_star_sum = spectral_i_star_sum + (pp_profile.spectral_i_star / (pp_profile.euph_index as f64).abs());
+                    spectral_i_star_count = spectral_i_star_count + 1.0;
                 },
-                Err(_) => ()
+                Err(_) => {}
             }
-        }
 
-    } // time loop
 
-    let mut pp_day = pp[0] * delta_prestart / 2.0;
-    let mut max_euphotic_depth: f64 = 0.0;
-    for t in 0..TIMESTEPS - 1 {
-        pp_day = pp_day + ((pp[t] + pp[t + 1]) * delta_t / 2.0);
+            if settings.prochloro_inputs.is_some() {
+                let pro_surf = &settings.prochloro_inputs.as_ref().unwrap().prochloro_surface;
+                let pro_max = &settings.prochloro_inputs.as_ref().unwrap().prochloro_maximum;
+
+                let prochloro_profile = compute_prochloro_profile(
+                    &chl_profile,
+                    &depth_array,
+                    &i_alpha_profile,
+                    &par_profile,
+                    &input,
+                    &pro_surf,
+                    &pro_max
+                );
+
+                match prochloro_profile {
+                    Ok(mut prochloro_profile) => {
+                        euphotic_depth[t] = prochloro_profile.euphotic_depth;
+
+                        if prochloro_profile.euph_index == 0 { prochloro_profile.euph_index = 1; }
+
+                        let mut pp_prochloro_output_profile: [f64; DEPTH_PROFILE_COUNT] = pp_prochloro_profile.unwrap();
+                        let mut pro_total_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_total_profile.unwrap();
+                        let mut pro_1_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_1_profile.unwrap();
+                        let mut pro_2_output_profile: [f64; DEPTH_PROFILE_COUNT] = pro_2_profile.unwrap();
+
+                        // TODO: double check if we need to cut off at euphotic depth for prochlorococcus
+                        for z in 0..prochloro_profile.euph_index {
+                            pp_prochloro_output_profile[z] = pp_prochloro_output_profile[z] + prochloro_profile.pp_prochloro_profile[z];
+                            pro_total_output_profile[z] = pro_total_output_profile[z] + prochloro_profile.pro_sum_profile[z];
+                            pro_1_output_profile[z] = pro_1_output_profile[z] + prochloro_profile.pro_1_profile[z];
+                            pro_2_output_profile[z] = pro_2_output_profile[z] + prochloro_profile.pro_2_profile[z];
+                        }
+
+                        pro_total_count += 1;
+                        pp_prochloro_profile = Some(pp_prochloro_output_profile);
+                        pro_total_profile = Some(pro_total_output_profile);
+                        pro_1_profile = Some(pro_1_output_profile);
+                        pro_2_profile = Some(pro_2_output_profile);
+                    },
+                    Err(_) => ()
+                }
+            }
+
+        } // time loop
+
+        // production between dawn and start_time, either approximated by a triangle rising from
+        // zero at sunrise to pp[0] or computed with the spectral model at extra time steps. During
+        // polar day sunrise is at midnight with the sun still up, so the triangle does not apply
+        let low_sun_mode = match daylight_regime {
+            DaylightRegime::PolarDay => LowSunMode::Spectral,
+            _ => settings.low_sun_mode
+        };
+        let pp_prestart = match low_sun_mode {
+            LowSunMode::Triangle => {
+                accumulate_profile(&mut daily_pp_profile, &start_pp_profile, delta_prestart / 2.0);
+                accumulate_layers(&mut pp_layers, start_pp_layers, delta_prestart / 2.0);
+                if let (Some(outputs), Some(production)) = (size_classes.as_mut(), &start_size_class_production) {
+                    accumulate_size_classes(outputs, production, delta_prestart / 2.0);
+                }
+                pp[0] * delta_prestart / 2.0
+            },
+            LowSunMode::Spectral => {
+                let low_sun = clear_sky.low_sun.as_ref().ok_or(PPErrors::ClearSkyMismatch)?;
+
+                let mut pp_low_sun: [f64; LOW_SUN_TIMESTEPS + 1] = [0.0; LOW_SUN_TIMESTEPS + 1];
+
+                for (i, pp_low_sun_i) in pp_low_sun.iter_mut().enumerate() {
+                    let weight = if i == 0 || i == LOW_SUN_TIMESTEPS { low_sun.delta_t / 2.0 } else { low_sun.delta_t };
+
+                    if i == LOW_SUN_TIMESTEPS && integration_window {
+                        *pp_low_sun_i = pp[0];
+                        accumulate_profile(&mut daily_pp_profile, &start_pp_profile, weight);
+                        accumulate_layers(&mut pp_layers, start_pp_layers, weight);
+                        if let (Some(outputs), Some(production)) = (size_classes.as_mut(), &start_size_class_production) {
+                            accumulate_size_classes(outputs, production, weight);
+                        }
+                        continue;
+                    }
+
+                    // the sun is on (or below) the horizon so there is no light
+                    if low_sun.zenith_d_array[i] >= 90.0 {
+                        continue;
+                    }
+
+                    let (i_alpha_profile, par_profile, size_class_i_alpha_profiles) = light_profiles_at(
+                        low_sun.direct[i],
+                        low_sun.diffuse[i],
+                        low_sun.zenith_array[i],
+                        surface_par_at(solar_time(low_sun.time_array[i]), low_sun.zenith_array[i])
+                    );
+
+                    if let Ok(mut pp_profile) = compute_pp_depth_profile(
+                        &chl_profile,
+                        &depth_array,
+                        &i_alpha_profile,
+                        &par_profile,
+                        input,
+                        settings.integration_limit
+                    ) {
+                        *pp_low_sun_i = integrate_pp_profile(&mut pp_profile, settings.integration_limit);
+                        accumulate_profile(&mut daily_pp_profile, &pp_profile.pp_profile, weight);
+                        accumulate_layers(&mut pp_layers, split_at_mld(&pp_profile, *pp_low_sun_i, input.mld), weight);
+                        if let (Some(outputs), Some(production)) = (
+                            size_classes.as_mut(),
+                            size_class_production(size_class_i_alpha_profiles, &pp_profile)
+                        ) {
+                            accumulate_size_classes(outputs, &production, weight);
+                        }
+                    }
+                }
+
+                pp_low_sun.windows(2)
+                    .map(|pair| (pair[0] + pair[1]) * low_sun.delta_t / 2.0)
+                    .sum()
+            }
+        };
 
-        if max_euphotic_depth.abs() < euphotic_depth[t].abs() {
-            max_euphotic_depth = euphotic_depth[t].abs();
+        let mut pp_half_day = pp_prestart;
+        for t in 0..TIMESTEPS - 1 {
+            pp_half_day = pp_half_day + ((pp[t] + pp[t + 1]) * delta_t / 2.0);
+
+            if max_euphotic_depth.abs() < euphotic_depth[t].abs() {
+                max_euphotic_depth = euphotic_depth[t].abs();
+            }
         }
-    }
+
+        pp_day += pp_half_day;
+        pp_low_sun += pp_prestart;
+
+    } // half day loop
 
 
     // // calculate final mean for spectral i star
@@ -211,8 +538,23 @@ pub fn calc_production(input: &ModelInputs, settings: &ModelSettings) -> Result<
         spectral_i_star_mean = spectral_i_star_sum / spectral_i_star_count;
     }
 
-    // mutliply by two because we have only integrated over half of the day
-    pp_day = pp_day * 2.0;
+    // mutliply by two if we have only integrated over half of the day
+    let half_day_factor = 2.0 / halves.len() as f64;
+    pp_day = pp_day * half_day_factor;
+    for pp_z in daily_pp_profile.iter_mut() {
+        *pp_z *= half_day_factor;
+    }
+    for pp_layer in pp_layers.iter_mut() {
+        *pp_layer *= half_day_factor;
+    }
+    if let Some(outputs) = size_classes.as_mut() {
+        for k in 0..3 {
+            outputs.pp_day[k] *= half_day_factor;
+            for pp_z in outputs.pp_profile[k].iter_mut() {
+                *pp_z *= half_day_factor;
+            }
+        }
+    }
 
     // Calculate mean (along time) prochlorococcus for every depth
     if pro_total_profile.is_some() {
@@ -230,16 +572,125 @@ pub fn calc_production(input: &ModelInputs, settings: &ModelSettings) -> Result<
         Ok(
             ModelOutputs {
                 pp_day: Some(pp_day),
+                // both the morning and evening low sun periods
+                pp_low_sun: Some(pp_low_sun * half_day_factor),
+                pp_profile: Some(daily_pp_profile),
                 euphotic_depth: Some(max_euphotic_depth),
+                integration_depth: Some(match settings.integration_limit {
+                    IntegrationLimit::EuphoticDepth => max_euphotic_depth,
+                    _ => max_integration_depth
+                }),
+                pp_above_mld: Some(pp_layers[0]),
+                pp_below_mld: Some(pp_layers[1]),
                 spectral_i_star: Some(spectral_i_star_mean),
                 par_noon_max: Some(iom),
+                par: Some(par),
+                sunrise: sunrise_clock,
+                solar_noon: Some(solar_noon),
+                sunset: sunset_clock,
+                daylight_regime: Some(daylight_regime),
                 pro_1_profile,
                 pro_2_profile,
                 pro_total_profile,
-                pp_prochloro_profile
+                pp_prochloro_profile,
+                size_classes
             }
         )
     }
 
 
 }
+
+// integrate a production profile from the surface down to the integration limit
+fn integrate_pp_profile(pp_profile: &mut PpProfile, integration_limit: IntegrationLimit) -> f64 {
+    if pp_profile.euph_index == 0 { pp_profile.euph_index = 1; }
+
+    integrate_to_limit(&pp_profile.pp_profile, pp_profile, integration_limit)
+}
+
+// integrate a production profile (the total or a size class) down to the integration depth of
+// pp_profile, with the step of the original model at the euphotic depth
+fn integrate_to_limit(profile: &[f64; DEPTH_PROFILE_COUNT], pp_profile: &PpProfile, integration_limit: IntegrationLimit) -> f64 {
+    match integration_limit {
+        IntegrationLimit::EuphoticDepth => {
+            integrate_to_euphotic_depth(profile, pp_profile.euph_index, pp_profile.euphotic_depth)
+        },
+        _ => integrate_to_depth(profile, pp_profile.integration_depth)
+    }
+}
+
+fn integrate_to_euphotic_depth(pp_profile: &[f64; DEPTH_PROFILE_COUNT], euph_index: usize, euphotic_depth: f64) -> f64 {
+    let mut pp: f64 = 0.0;
+
+    for z in 0..euph_index {
+        pp += DEPTH_PROFILE_STEP * (pp_profile[z] + pp_profile[z + 1]) / 2.0;
+    }
+
+    pp + pp_profile[euph_index]
+        * (euphotic_depth
+        - (euph_index as f64 - 1.0) * DEPTH_PROFILE_STEP)
+}
+
+// trapezoidal integral of a production profile from the surface to a depth on or above the
+// bottom of the depth grid, interpolating linearly between the grid depths
+fn integrate_to_depth(pp_profile: &[f64; DEPTH_PROFILE_COUNT], depth: f64) -> f64 {
+    let mut pp: f64 = 0.0;
+    let mut top = 0.0;
+
+    for z in 0..DEPTH_PROFILE_COUNT - 1 {
+        let bottom = top + DEPTH_PROFILE_STEP;
+
+        if bottom >= depth {
+            let fraction = (depth - top) / DEPTH_PROFILE_STEP;
+            let pp_at_depth = pp_profile[z] + fraction * (pp_profile[z + 1] - pp_profile[z]);
+            return pp + (depth - top) * (pp_profile[z] + pp_at_depth) / 2.0;
+        }
+
+        pp += DEPTH_PROFILE_STEP * (pp_profile[z] + pp_profile[z + 1]) / 2.0;
+        top = bottom;
+    }
+
+    pp
+}
+
+// production at a time step above and below the mixed layer depth, all of it above when the mixed
+// layer is as deep as the integration limit
+fn split_at_mld(pp_profile: &PpProfile, pp: f64, mld: f64) -> [f64; 2] {
+    if mld >= pp_profile.integration_depth {
+        return [pp, 0.0];
+    }
+
+    let pp_above_mld = integrate_to_depth(&pp_profile.pp_profile, mld.max(0.0));
+    [pp_above_mld, pp - pp_above_mld]
+}
+
+fn accumulate_layers(pp_layers: &mut [f64; 2], layers: [f64; 2], weight: f64) {
+    for (pp_layer, layer) in pp_layers.iter_mut().zip(layers) {
+        *pp_layer += layer * weight;
+    }
+}
+
+// add the weighted production of each size class at a time step to the running time integrals
+fn accumulate_size_classes(
+    outputs: &mut SizeClassOutputs,
+    production: &([f64; 3], [[f64; DEPTH_PROFILE_COUNT]; 3]),
+    weight: f64
+) {
+    let (pp, pp_profiles) = production;
+
+    for k in 0..3 {
+        outputs.pp_day[k] += pp[k] * weight;
+        accumulate_profile(&mut outputs.pp_profile[k], &pp_profiles[k], weight);
+    }
+}
+
+// add a weighted production profile to a running time integral
+fn accumulate_profile(
+    integrated_profile: &mut [f64; DEPTH_PROFILE_COUNT],
+    pp_profile: &[f64; DEPTH_PROFILE_COUNT],
+    weight: f64
+) {
+    for (integrated, pp) in integrated_profile.iter_mut().zip(pp_profile.iter()) {
+        *integrated += pp * weight;
+    }
+}
@@ -0,0 +1,267 @@
+use crate::dwcpn::dwcpn::calc_production;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::dwcpn::modules::random::SeededRng;
+use crate::{ModelInputs, ModelSettings, PPErrors};
+
+// maximum number of redraws when a sample falls outside the physical range of a parameter
+const MAX_REDRAWS: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    // additive normal error with the given standard deviation
+    Normal { std_dev: f64 },
+    // multiplicative error, the natural log of the value is normal with the given
+    // standard deviation and the input value is the median (suits chlorophyll)
+    LogNormal { std_dev_ln: f64 },
+}
+
+pub struct InputUncertainty {
+    pub parameter: ModelParameter,
+    pub distribution: Distribution,
+}
+
+pub struct EnsembleSettings {
+    pub realisations: usize,
+    pub seed: u64,
+    // percentiles (0-100) to report for each output
+    pub percentiles: Vec<f64>,
+}
+
+pub struct EnsembleStatistics {
+    pub mean: f64,
+    pub std_dev: f64,
+    // (percentile, value) pairs in the order requested
+    pub percentiles: Vec<(f64, f64)>,
+}
+
+pub struct EnsembleOutputs {
+    pub pp_day: EnsembleStatistics,
+    pub euphotic_depth: EnsembleStatistics,
+    pub successful_realisations: usize,
+    pub failed_realisations: usize,
+    // realisations that ran but gave no daily production or euphotic depth (polar night,
+    // iom_only), left out of the statistics
+    pub skipped_realisations: usize,
+    // draws of a parameter that kept the input value after MAX_REDRAWS samples outside its
+    // physical range, which narrow the spread of the ensemble
+    pub redraw_fallbacks: usize,
+}
+
+impl Distribution {
+    fn sample(&self, value: f64, rng: &mut SeededRng) -> f64 {
+        match self {
+            Distribution::Normal { std_dev } => value + std_dev * rng.next_standard_normal(),
+            Distribution::LogNormal { std_dev_ln } => value * (std_dev_ln * rng.next_standard_normal()).exp(),
+        }
+    }
+}
+
+// draw a perturbed set of inputs, redrawing any value that is not physically possible. Also
+// returns the number of parameters left at their input value because no valid sample was drawn.
+fn perturb_inputs(
+    input: &ModelInputs,
+    uncertainties: &[InputUncertainty],
+    rng: &mut SeededRng
+) -> (ModelInputs, usize) {
+    let mut perturbed = input.clone();
+    let mut fallbacks: usize = 0;
+
+    for uncertainty in uncertainties {
+        let value = uncertainty.parameter.get(input);
+
+        let sample = (0..MAX_REDRAWS)
+            .map(|_| uncertainty.distribution.sample(value, rng))
+            .find(|sample| uncertainty.parameter.is_valid(*sample));
+
+        match sample {
+            Some(sample) => uncertainty.parameter.set(&mut perturbed, sample),
+            None => fallbacks += 1
+        }
+    }
+
+    (perturbed, fallbacks)
+}
+
+// run the model for an ensemble of inputs drawn from the given uncertainty distributions and
+// summarise the spread of daily production and euphotic depth. The same seed always gives
+// the same ensemble, and realisations in which the model fails or gives no production are
+// counted but left out.
+pub fn calc_production_ensemble(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    uncertainties: &[InputUncertainty],
+    ensemble_settings: &EnsembleSettings
+) -> Result<EnsembleOutputs, PPErrors> {
+    let mut rng = SeededRng::new(ensemble_settings.seed);
+
+    let mut pp_day: Vec<f64> = Vec::with_capacity(ensemble_settings.realisations);
+    let mut euphotic_depth: Vec<f64> = Vec::with_capacity(ensemble_settings.realisations);
+    let mut failed_realisations: usize = 0;
+    let mut skipped_realisations: usize = 0;
+    let mut redraw_fallbacks: usize = 0;
+
+    for _ in 0..ensemble_settings.realisations {
+        let (perturbed, fallbacks) = perturb_inputs(input, uncertainties, &mut rng);
+        redraw_fallbacks += fallbacks;
+
+        match calc_production(&perturbed, settings) {
+            Ok(outputs) => match (outputs.pp_day, outputs.euphotic_depth) {
+                (Some(member_pp_day), Some(member_euphotic_depth)) => {
+                    pp_day.push(member_pp_day);
+                    euphotic_depth.push(member_euphotic_depth);
+                },
+                _ => skipped_realisations += 1
+            },
+            Err(_) => failed_realisations += 1
+        }
+    }
+
+    if pp_day.is_empty() {
+        return Err(PPErrors::DWCPNError);
+    }
+
+    Ok(
+        EnsembleOutputs {
+            pp_day: summarise(&mut pp_day, &ensemble_settings.percentiles),
+            euphotic_depth: summarise(&mut euphotic_depth, &ensemble_settings.percentiles),
+            successful_realisations: pp_day.len(),
+            failed_realisations,
+            skipped_realisations,
+            redraw_fallbacks
+        }
+    )
+}
+
+fn summarise(values: &mut [f64], percentiles: &[f64]) -> EnsembleStatistics {
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+
+    let std_dev = if values.len() > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+
+    values.sort_by(|a, b| a.total_cmp(b));
+
+    EnsembleStatistics {
+        mean,
+        std_dev,
+        percentiles: percentiles.iter().map(|p| (*p, percentile(values, *p))).collect(),
+    }
+}
+
+// linear interpolation between the closest ranks of sorted values
+pub fn percentile(sorted_values: &[f64], percentile: f64) -> f64 {
+    let rank = (percentile / 100.0).clamp(0.0, 1.0) * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_fixtures::model_inputs;
+
+    fn test_inputs() -> ModelInputs {
+        ModelInputs {
+            lat: 43.2,
+            lon: 33.7,
+            z_bottom: 2198.8,
+            iday: 121,
+            alpha_b: 0.0578,
+            pmb: 3.294,
+            z_m: 49.44,
+            mld: 11.9296,
+            chl: 0.474,
+            rho: 0.87,
+            sigma: 14.62,
+            par: Some(50.35),
+            ..model_inputs()
+        }
+    }
+
+    fn test_settings() -> ModelSettings {
+        ModelSettings::default()
+    }
+
+    fn test_uncertainties() -> Vec<InputUncertainty> {
+        vec![
+            InputUncertainty { parameter: ModelParameter::Chl, distribution: Distribution::LogNormal { std_dev_ln: 0.3 } },
+            InputUncertainty { parameter: ModelParameter::Par, distribution: Distribution::Normal { std_dev: 3.0 } },
+            InputUncertainty { parameter: ModelParameter::Pmb, distribution: Distribution::Normal { std_dev: 0.5 } },
+        ]
+    }
+
+    #[test]
+    fn test_ensemble_is_reproducible() {
+        let ensemble_settings = EnsembleSettings { realisations: 10, seed: 42, percentiles: vec![5.0, 50.0, 95.0] };
+
+        let first = calc_production_ensemble(&test_inputs(), &test_settings(), &test_uncertainties(), &ensemble_settings).unwrap();
+        let second = calc_production_ensemble(&test_inputs(), &test_settings(), &test_uncertainties(), &ensemble_settings).unwrap();
+
+        assert_eq!(first.pp_day.mean, second.pp_day.mean);
+        assert_eq!(first.pp_day.std_dev, second.pp_day.std_dev);
+        assert!(first.pp_day.std_dev > 0.0);
+        assert!(first.pp_day.percentiles[0].1 <= first.pp_day.percentiles[1].1);
+        assert!(first.pp_day.percentiles[1].1 <= first.pp_day.percentiles[2].1);
+    }
+
+    #[test]
+    fn test_ensemble_without_uncertainty() {
+        let ensemble_settings = EnsembleSettings { realisations: 3, seed: 1, percentiles: vec![50.0] };
+
+        let outputs = calc_production_ensemble(&test_inputs(), &test_settings(), &[], &ensemble_settings).unwrap();
+        let deterministic = calc_production(&test_inputs(), &test_settings()).unwrap();
+
+        assert_eq!(outputs.pp_day.mean, deterministic.pp_day.unwrap());
+        assert_eq!(outputs.pp_day.std_dev, 0.0);
+    }
+
+    #[test]
+    fn test_redraw_fallbacks_are_counted() {
+        let ensemble_settings = EnsembleSettings { realisations: 5, seed: 7, percentiles: vec![50.0] };
+
+        // chlorophyll so far below zero that a normal error never makes it valid again
+        let inputs = ModelInputs { chl: -1000.0, ..test_inputs() };
+        let uncertainties = [
+            InputUncertainty { parameter: ModelParameter::Chl, distribution: Distribution::Normal { std_dev: 0.1 } },
+            InputUncertainty { parameter: ModelParameter::Pmb, distribution: Distribution::Normal { std_dev: 0.5 } },
+        ];
+
+        let mut rng = SeededRng::new(ensemble_settings.seed);
+        let (perturbed, fallbacks) = perturb_inputs(&inputs, &uncertainties, &mut rng);
+        assert_eq!(fallbacks, 1);
+        assert_eq!(perturbed.chl, inputs.chl);
+        assert_ne!(perturbed.pmb, inputs.pmb);
+
+        let outputs = calc_production_ensemble(&test_inputs(), &test_settings(), &uncertainties[1..], &ensemble_settings).unwrap();
+        assert_eq!(outputs.redraw_fallbacks, 0);
+    }
+
+    #[test]
+    fn test_members_without_production_are_skipped() {
+        let ensemble_settings = EnsembleSettings { realisations: 4, seed: 3, percentiles: vec![50.0] };
+
+        // only the noon PAR is computed, so no member has a production to summarise
+        let settings = ModelSettings { iom_only: true, ..Default::default() };
+        assert!(calc_production_ensemble(&test_inputs(), &settings, &test_uncertainties(), &ensemble_settings).is_err());
+
+        let outputs = calc_production_ensemble(&test_inputs(), &test_settings(), &test_uncertainties(), &ensemble_settings).unwrap();
+        assert_eq!(outputs.skipped_realisations, 0);
+        assert_eq!(outputs.successful_realisations + outputs.failed_realisations, 4);
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&values, 50.0), 3.0);
+        assert_eq!(percentile(&values, 62.5), 3.5);
+        assert_eq!(percentile(&values, 100.0), 5.0);
+    }
+}
@@ -0,0 +1,268 @@
+// Runs of the model over a 2D image, e.g. a global lat/lon grid of satellite inputs for one day.
+//
+// Each input field that varies over the image is a grid of rows by columns; inputs that do not
+// (the date, the water spectra, and often yel_sub or cloud) are taken from a template. Input
+// values equal to the input fill value, or NaN, are missing. Pixels outside the mask (land) are
+// not run, pixels with a missing input are flagged and not run, except that a missing par is
+// estimated from the clear sky irradiance by the model, and a missing sst leaves pmb as given.
+// Output grids hold the output fill value
+// wherever the model was not run or failed.
+
+use crate::dwcpn::dwcpn::calc_production_batch;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::{ModelInputs, ModelSettings};
+
+// row major values of a rows by columns image
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub rows: usize,
+    pub cols: usize,
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillValues {
+    // marks missing input values as well as NaN
+    pub input: f64,
+    pub output: f64,
+}
+
+pub struct GridInputs {
+    // inputs common to every pixel, the fields are set on a copy for each pixel
+    pub template: ModelInputs,
+    // must include lat and lon
+    pub fields: Vec<(ModelParameter, Grid)>,
+    // true for the pixels to run (ocean), every pixel is run when None
+    pub mask: Option<Vec<bool>>,
+    pub fill_values: FillValues,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelStatus {
+    Processed,
+    // outside the mask
+    Masked,
+    // an input was missing
+    Flagged,
+    // the model failed, or production was implausibly high
+    Failed,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GridStatistics {
+    pub pixels: usize,
+    pub masked: usize,
+    pub processed: usize,
+    pub failed: usize,
+    pub flagged: usize,
+}
+
+pub struct GridOutputs {
+    pub pp_day: Grid,
+    pub euphotic_depth: Grid,
+    pub par: Grid,
+    pub status: Vec<PixelStatus>,
+    pub statistics: GridStatistics,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    MissingField(ModelParameter),
+    // a field or the mask is not the shape of the lat field
+    ShapeMismatch(String),
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize, values: Vec<f64>) -> Grid {
+        Grid { rows, cols, values }
+    }
+
+    pub fn filled(rows: usize, cols: usize, value: f64) -> Grid {
+        Grid::new(rows, cols, vec![value; rows * cols])
+    }
+
+    // latitude of each pixel of a regular grid from the latitude of each row
+    pub fn from_latitudes(latitudes: &[f64], cols: usize) -> Grid {
+        let values = latitudes.iter().flat_map(|lat| std::iter::repeat_n(*lat, cols)).collect();
+        Grid::new(latitudes.len(), cols, values)
+    }
+
+    // longitude of each pixel of a regular grid from the longitude of each column
+    pub fn from_longitudes(longitudes: &[f64], rows: usize) -> Grid {
+        let values = (0..rows).flat_map(|_| longitudes.iter().copied()).collect();
+        Grid::new(rows, longitudes.len(), values)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.values[row * self.cols + col]
+    }
+}
+
+// run the model for every ocean pixel of the image
+pub fn run_grid(inputs: &GridInputs, settings: &ModelSettings) -> Result<GridOutputs, GridError> {
+    let shape_of = |parameter: ModelParameter| inputs.fields.iter()
+        .find(|(field_parameter, _)| *field_parameter == parameter)
+        .map(|(_, grid)| (grid.rows, grid.cols))
+        .ok_or(GridError::MissingField(parameter));
+
+    let (rows, cols) = shape_of(ModelParameter::Lat)?;
+    shape_of(ModelParameter::Lon)?;
+    let pixel_count = rows * cols;
+
+    for (parameter, grid) in inputs.fields.iter() {
+        if (grid.rows, grid.cols) != (rows, cols) || grid.values.len() != pixel_count {
+            return Err(GridError::ShapeMismatch(parameter.name().to_string()));
+        }
+    }
+    if inputs.mask.as_ref().is_some_and(|mask| mask.len() != pixel_count) {
+        return Err(GridError::ShapeMismatch("mask".to_string()));
+    }
+
+    let is_missing = |value: f64| value.is_nan() || value == inputs.fill_values.input;
+
+    let mut status = vec![PixelStatus::Processed; pixel_count];
+    let mut pixels: Vec<usize> = Vec::with_capacity(pixel_count);
+    let mut pixel_inputs: Vec<ModelInputs> = Vec::with_capacity(pixel_count);
+
+    for (pixel, pixel_status) in status.iter_mut().enumerate() {
+        if inputs.mask.as_ref().is_some_and(|mask| !mask[pixel]) {
+            *pixel_status = PixelStatus::Masked;
+            continue;
+        }
+
+        let missing = inputs.fields.iter()
+            .any(|(parameter, grid)| {
+                *parameter != ModelParameter::Par && !parameter.is_optional() && is_missing(grid.values[pixel])
+            });
+        if missing {
+            *pixel_status = PixelStatus::Flagged;
+            continue;
+        }
+
+        let mut pixel_input = inputs.template.clone();
+        for (parameter, grid) in inputs.fields.iter() {
+            let value = grid.values[pixel];
+            parameter.set(&mut pixel_input, if is_missing(value) { f64::NAN } else { value });
+        }
+
+        pixels.push(pixel);
+        pixel_inputs.push(pixel_input);
+    }
+
+    let fill = inputs.fill_values.output;
+    let mut outputs = GridOutputs {
+        pp_day: Grid::filled(rows, cols, fill),
+        euphotic_depth: Grid::filled(rows, cols, fill),
+        par: Grid::filled(rows, cols, fill),
+        status,
+        statistics: GridStatistics::default(),
+    };
+
+    for (pixel, result) in pixels.into_iter().zip(calc_production_batch(&pixel_inputs, settings)) {
+        match result {
+            Ok(model_outputs) => {
+                outputs.pp_day.values[pixel] = model_outputs.pp_day.unwrap_or(fill);
+                outputs.euphotic_depth.values[pixel] = model_outputs.euphotic_depth.unwrap_or(fill);
+                outputs.par.values[pixel] = model_outputs.par.unwrap_or(fill);
+            },
+            Err(_) => outputs.status[pixel] = PixelStatus::Failed
+        }
+    }
+
+    outputs.statistics.pixels = pixel_count;
+    for pixel_status in outputs.status.iter() {
+        match pixel_status {
+            PixelStatus::Processed => outputs.statistics.processed += 1,
+            PixelStatus::Masked => outputs.statistics.masked += 1,
+            PixelStatus::Flagged => outputs.statistics.flagged += 1,
+            PixelStatus::Failed => outputs.statistics.failed += 1,
+        }
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::dwcpn::dwcpn::calc_production;
+    use crate::test_fixtures::model_inputs;
+
+    #[test]
+    fn test_run_grid() {
+        let template = ModelInputs {
+            lat: 0.0,
+            lon: 0.0,
+            z_bottom: 2198.8,
+            iday: 121,
+            alpha_b: 0.0578,
+            pmb: 3.294,
+            z_m: 49.44,
+            mld: 11.9296,
+            chl: 0.0,
+            rho: 0.87,
+            sigma: 14.62,
+            par: None,
+            ..model_inputs()
+        };
+        let settings = ModelSettings::default();
+
+        // two rows of three pixels: land, a missing chl, a missing par, and three ocean pixels
+        let fill = -999.0;
+        let inputs = GridInputs {
+            template: template.clone(),
+            fields: vec![
+                (ModelParameter::Lat, Grid::from_latitudes(&[43.0, 42.0], 3)),
+                (ModelParameter::Lon, Grid::from_longitudes(&[33.0, 34.0, 35.0], 2)),
+                (ModelParameter::Chl, Grid::new(2, 3, vec![0.5, fill, 0.5, 0.5, 1.0, f64::NAN])),
+                (ModelParameter::Par, Grid::new(2, 3, vec![50.0, 50.0, fill, 50.0, 50.0, 50.0])),
+            ],
+            mask: Some(vec![false, true, true, true, true, true]),
+            fill_values: FillValues { input: fill, output: -1.0 },
+        };
+
+        let outputs = run_grid(&inputs, &settings).unwrap();
+        assert_eq!(outputs.statistics, GridStatistics { pixels: 6, masked: 1, processed: 3, failed: 0, flagged: 2 });
+        assert_eq!(outputs.status[1], PixelStatus::Flagged);
+        assert_eq!(outputs.pp_day.get(0, 0), -1.0);
+        assert_eq!(outputs.pp_day.get(1, 2), -1.0);
+
+        // the same as a single pixel run, with the clear sky PAR where it is missing
+        let mut pixel = template;
+        pixel.lat = 42.0;
+        pixel.lon = 34.0;
+        pixel.chl = 1.0;
+        pixel.par = Some(50.0);
+        assert_eq!(Some(outputs.pp_day.get(1, 1)), calc_production(&pixel, &settings).unwrap().pp_day);
+        assert!(outputs.par.get(0, 2) > 0.0 && outputs.par.get(0, 2) != 50.0);
+
+        let mismatched = GridInputs { mask: Some(vec![true; 4]), ..inputs };
+        assert_eq!(run_grid(&mismatched, &settings).err(), Some(GridError::ShapeMismatch("mask".to_string())));
+    }
+
+    #[test]
+    fn test_run_grid_missing_sst() {
+        let template = ModelInputs { par: Some(45.0), ..model_inputs() };
+        let settings = ModelSettings::default();
+
+        // a blank and a fill value sst are run with pmb as given
+        let fill = -999.0;
+        let inputs = GridInputs {
+            template: template.clone(),
+            fields: vec![
+                (ModelParameter::Lat, Grid::from_latitudes(&[45.0], 3)),
+                (ModelParameter::Lon, Grid::from_longitudes(&[14.0, 15.0, 16.0], 1)),
+                (ModelParameter::Sst, Grid::new(1, 3, vec![18.0, f64::NAN, fill])),
+            ],
+            mask: None,
+            fill_values: FillValues { input: fill, output: -1.0 },
+        };
+
+        let outputs = run_grid(&inputs, &settings).unwrap();
+        assert_eq!(outputs.statistics, GridStatistics { pixels: 3, masked: 0, processed: 3, failed: 0, flagged: 0 });
+
+        let pixel = ModelInputs { lon: 16.0, sst: None, ..template };
+        assert_eq!(Some(outputs.pp_day.get(0, 2)), calc_production(&pixel, &settings).unwrap().pp_day);
+    }
+}
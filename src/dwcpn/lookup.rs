@@ -0,0 +1,594 @@
+// Lookup table mode for fast daily production over large images.
+//
+// Daily production depends on alpha_b, pmb and the surface light only through
+// P = pmb * f(alpha_b * I / pmb), so the production normalised by pmb and day length,
+//
+//     pp_norm = pp_day / (pmb * day_length)
+//
+// is, to a good approximation, a function of the dimensionless noon irradiance
+// i_star = alpha_b * iom / pmb, the day length and the shape of the chlorophyll profile (chl, z_m,
+// sigma, rho). The table holds pp_norm on a grid over those six dimensions, each node computed
+// with the full spectral calc_production, and ln(pp_norm) is interpolated multilinearly (with
+// the i_star and chl axes also in log space).
+//
+// Things the table does not resolve: the path of the sun for a given day length (nodes are
+// computed on the northern solstices), yellow substance and the water optics, which are all taken
+// from the reference inputs used to generate the table. The table also ignores cloud. Cloud only
+// reaches it through the daily PAR (the clear sky estimate reduced by cloud when inputs.par is
+// None), while the model also shifts light from the direct to the diffuse beam under cloud; the
+// nodes have the clear sky split of the reference inputs. check_accuracy compares the table with
+// the full model for a set of pixels, and dwcpn-lut check reports it for a match-up file.
+//
+// The standard table (LookupAxes::standard, standard_reference, default settings) against the full
+// model for 336 clear and cloudy pixels from 45S to 45N through the year, with the clear sky PAR
+// and chl from 0.05 to 6 (test_standard_table_accuracy, ignored by default as generating the table
+// takes several minutes):
+//
+//     log10 bias -0.012 (the table is about 3% low), log10 rmsd 0.015, r 0.9998
+
+use crate::dwcpn::dwcpn::calc_production;
+use crate::dwcpn::modules::irradiance::compute_iom;
+use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
+use crate::dwcpn::modules::temperature::temperature_adjusted_inputs;
+use crate::dwcpn::modules::time::{compute_daylight_regime, compute_sunrise, resolve_day_of_year, DaylightRegime};
+use crate::dwcpn::modules::zenith::compute_zenith_time;
+use crate::dwcpn::surface_irradiance::SurfaceIrradianceDay;
+use crate::dwcpn::validation::{compute_statistics, ValidationStatistics};
+use crate::{IntegrationLimit, LowSunMode, ModelInputs, ModelSettings, PPErrors, ProductionMode, SolarSpectrum};
+
+// order of the table dimensions, with the last varying fastest in the stored values
+pub const LOOKUP_AXIS_NAMES: [&str; 6] = ["i_star", "day_length", "chl", "z_m", "sigma", "rho"];
+
+// axes interpolated in log space
+const LOG_AXES: [bool; 6] = [true, false, true, false, false, false];
+
+// reference photosynthetic parameters used to generate the table; only their ratio to the surface
+// light matters, and keeping pmb small keeps pp_day well clear of the PPTooHigh check
+const REFERENCE_ALPHA_B: f64 = 0.1;
+const REFERENCE_PMB: f64 = 1.0;
+
+// days on which nodes are computed, long days on the northern summer solstice and short days on
+// the winter solstice
+const LONG_DAY: u16 = 172;
+const SHORT_DAY: u16 = 355;
+
+#[derive(Debug)]
+pub enum LookupError {
+    Io(String),
+    Parse { line: usize, message: String },
+    // the table only covers the spectral model with the Gaussian chlorophyll profile (not
+    // mld_only) and the legacy solar spectrum, and the zenith cutoff and low sun mode it was
+    // generated with, for inputs with a daily PAR rather than a PAR time series
+    UnsupportedSettings,
+    OutOfRange { axis: &'static str, value: f64 },
+    Model(PPErrors),
+}
+
+pub struct LookupAxes {
+    pub i_star: Vec<f64>,
+    pub day_length: Vec<f64>,
+    pub chl: Vec<f64>,
+    pub z_m: Vec<f64>,
+    pub sigma: Vec<f64>,
+    pub rho: Vec<f64>,
+}
+
+impl LookupAxes {
+    // axes covering most open ocean conditions, 10 * 7 * 10 * 7 * 6 * 5 = 147000 nodes. Days
+    // shorter than 8 hours are left out because on the winter solstice the sun then stays below
+    // the usual 80 degree zenith cutoff, and with LowSunMode::Triangle those nodes would be zero.
+    pub fn standard() -> LookupAxes {
+        LookupAxes {
+            i_star: vec![0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0],
+            day_length: vec![8.0, 10.0, 12.0, 14.0, 16.0, 18.0, 20.0],
+            chl: vec![0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0],
+            z_m: vec![0.0, 10.0, 20.0, 30.0, 50.0, 75.0, 100.0],
+            sigma: vec![5.0, 10.0, 20.0, 30.0, 45.0, 60.0],
+            rho: vec![0.1, 0.3, 0.5, 0.7, 0.9],
+        }
+    }
+
+    fn as_array(&self) -> [&Vec<f64>; 6] {
+        [&self.i_star, &self.day_length, &self.chl, &self.z_m, &self.sigma, &self.rho]
+    }
+
+    fn node_count(&self) -> usize {
+        self.as_array().iter().map(|axis| axis.len()).product()
+    }
+}
+
+// clear sky open ocean reference optics the standard table is generated with, the other inputs are
+// set for each node
+pub fn standard_reference() -> ModelInputs {
+    ModelInputs {
+        lat: 0.0,
+        lon: 0.0,
+        z_bottom: 4000.0,
+        iday: 1,
+        date: None,
+        utc_offset: None,
+        alpha_b: REFERENCE_ALPHA_B,
+        pmb: REFERENCE_PMB,
+        z_m: 0.0,
+        mld: 0.0,
+        chl: 1.0,
+        rho: 0.5,
+        sigma: 10.0,
+        cloud: 0.0,
+        yel_sub: 0.3,
+        par: Some(0.0),
+        par_series: None,
+        sst: None,
+        bw: calculate_bw(),
+        bbr: calculate_bbr(),
+        ay: calculate_ay()
+    }
+}
+
+pub struct LookupTable {
+    pub axes: LookupAxes,
+    pub zenith_cutoff: f64,
+    pub low_sun_mode: LowSunMode,
+    // pp_day / (pmb * day_length) at each node
+    pub values: Vec<f64>,
+}
+
+// the table coordinates of a pixel: (i_star, day_length, chl, z_m, sigma, rho), or None if there
+// is no daylight
+pub fn lookup_coordinates(input: &ModelInputs) -> Option<[f64; 6]> {
+    let (day_of_year, year_length) = resolve_day_of_year(input);
+    let (sunrise, delta, phi) = compute_sunrise(day_of_year, year_length, input.lat);
+    let daylight_regime = compute_daylight_regime(delta, phi);
+
+    if daylight_regime == DaylightRegime::PolarNight {
+        return None;
+    }
+
+    let day_length = 2.0 * (12.0 - sunrise);
+    // tables are only generated with the legacy solar spectrum (see check_settings)
+    let par = input.par.unwrap_or_else(|| {
+        SurfaceIrradianceDay::new(day_of_year, year_length, input.lat, SolarSpectrum::Legacy).daily_par(input.cloud)
+    });
+    let iom = compute_iom(par, daylight_regime, day_length, delta, phi);
+
+    Some([input.alpha_b * iom / input.pmb, day_length, input.chl, input.z_m, input.sigma, input.rho])
+}
+
+// latitude at which the reference day has the given day length
+fn latitude_for_day_length(day: u16, day_length: f64) -> f64 {
+    let day_length_at = |lat: f64| 2.0 * (12.0 - compute_sunrise(day, 365.0, lat).0);
+    let (mut lower, mut upper) = (0.0, 89.0);
+
+    for _ in 0..60 {
+        let lat = 0.5 * (lower + upper);
+        let longer = day_length_at(lat) >= day_length;
+        // days lengthen towards the pole in northern summer and shorten in northern winter
+        if longer == (day == LONG_DAY) { upper = lat } else { lower = lat }
+    }
+
+    0.5 * (lower + upper)
+}
+
+fn check_settings(settings: &ModelSettings) -> Result<(), LookupError> {
+    if settings.mld_only
+        || settings.iom_only
+        || settings.prochloro_inputs.is_some()
+        || settings.size_classes.is_some()
+        || settings.integration_limit != IntegrationLimit::EuphoticDepth
+        || settings.production_mode != ProductionMode::Spectral
+        || settings.solar_spectrum != SolarSpectrum::Legacy {
+        return Err(LookupError::UnsupportedSettings);
+    }
+    Ok(())
+}
+
+// compute the table with the full model. Cloud, yellow substance, the water optics and z_bottom
+// are taken from reference, the other inputs are set for each node.
+pub fn generate_lookup_table(
+    axes: LookupAxes,
+    reference: &ModelInputs,
+    settings: &ModelSettings
+) -> Result<LookupTable, LookupError> {
+    check_settings(settings)?;
+
+    let mut values: Vec<f64> = Vec::with_capacity(axes.node_count());
+
+    for day_length in axes.day_length.iter() {
+        let day = if *day_length >= 12.0 { LONG_DAY } else { SHORT_DAY };
+        let lat = latitude_for_day_length(day, *day_length);
+
+        for i_star in axes.i_star.iter() {
+            let mut input = reference.clone();
+            input.lat = lat;
+            input.iday = day;
+            input.date = None;
+            input.alpha_b = REFERENCE_ALPHA_B;
+            input.pmb = REFERENCE_PMB;
+            // invert iom = par * pi / (2 * day_length) for the daily PAR giving this i_star
+            input.par = Some(i_star * REFERENCE_PMB / REFERENCE_ALPHA_B * 2.0 * day_length / std::f64::consts::PI);
+
+            for chl in axes.chl.iter() {
+                for z_m in axes.z_m.iter() {
+                    for sigma in axes.sigma.iter() {
+                        for rho in axes.rho.iter() {
+                            input.chl = *chl;
+                            input.z_m = *z_m;
+                            input.sigma = *sigma;
+                            input.rho = *rho;
+
+                            let outputs = calc_production(&input, settings).map_err(LookupError::Model)?;
+                            values.push(outputs.pp_day.unwrap_or(0.0) / (REFERENCE_PMB * day_length));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // values were generated with day_length outermost, reorder them to the stored axis order
+    let mut table = LookupTable {
+        axes,
+        zenith_cutoff: settings.zenith_cutoff,
+        low_sun_mode: settings.low_sun_mode,
+        values: vec![0.0; values.len()],
+    };
+    let (n_i_star, n_day_length) = (table.axes.i_star.len(), table.axes.day_length.len());
+    let block = values.len() / (n_i_star * n_day_length);
+    for d in 0..n_day_length {
+        for i in 0..n_i_star {
+            let from = (d * n_i_star + i) * block;
+            let to = (i * n_day_length + d) * block;
+            table.values[to..to + block].copy_from_slice(&values[from..from + block]);
+        }
+    }
+
+    Ok(table)
+}
+
+impl LookupTable {
+    // daily production for a pixel, interpolated from the table
+    pub fn calc_pp_day(&self, input: &ModelInputs, settings: &ModelSettings) -> Result<f64, LookupError> {
+        check_settings(settings)?;
+        if settings.zenith_cutoff != self.zenith_cutoff
+            || settings.low_sun_mode != self.low_sun_mode
+            || input.par_series.is_some() {
+            return Err(LookupError::UnsupportedSettings);
+        }
+
+        // the table is normalised by pmb, so it can be scaled to pmb at the sea surface temperature
+        let adjusted_input = temperature_adjusted_inputs(input, settings.temperature_dependence);
+        let input = adjusted_input.as_ref().unwrap_or(input);
+
+        let coordinates = match lookup_coordinates(input) {
+            Some(coordinates) => coordinates,
+            None => return Ok(0.0)
+        };
+
+        // as in the full model, with the triangle approximation there is no production if the sun
+        // never climbs above the zenith cutoff
+        if self.low_sun_mode == LowSunMode::Triangle {
+            let (day_of_year, year_length) = resolve_day_of_year(input);
+            let (_, delta, phi) = compute_sunrise(day_of_year, year_length, input.lat);
+            if compute_zenith_time(delta, phi, self.zenith_cutoff) < 0.0 {
+                return Ok(0.0);
+            }
+        }
+
+        Ok(self.interpolate(coordinates)? * input.pmb * coordinates[1])
+    }
+
+    // multilinear interpolation of the log of the normalised production at the given coordinates
+    pub fn interpolate(&self, coordinates: [f64; 6]) -> Result<f64, LookupError> {
+        let axes = self.axes.as_array();
+
+        // lower node index and weight of the upper node along each axis
+        let mut lower: [usize; 6] = [0; 6];
+        let mut weight: [f64; 6] = [0.0; 6];
+
+        for d in 0..6 {
+            let axis = axes[d];
+            let value = coordinates[d];
+
+            if !(value >= axis[0] && value <= axis[axis.len() - 1]) {
+                return Err(LookupError::OutOfRange { axis: LOOKUP_AXIS_NAMES[d], value });
+            }
+            if axis.len() == 1 {
+                continue;
+            }
+
+            let i = axis.partition_point(|node| *node <= value).clamp(1, axis.len() - 1) - 1;
+            let (x0, x1, x) = if LOG_AXES[d] {
+                (axis[i].ln(), axis[i + 1].ln(), value.ln())
+            } else {
+                (axis[i], axis[i + 1], value)
+            };
+
+            lower[d] = i;
+            weight[d] = (x - x0) / (x1 - x0);
+        }
+
+        let mut strides: [usize; 6] = [1; 6];
+        for d in (0..5).rev() {
+            strides[d] = strides[d + 1] * axes[d + 1].len();
+        }
+
+        let mut result = 0.0;
+        for corner in 0..(1 << 6) {
+            let mut index = 0;
+            let mut corner_weight = 1.0;
+
+            for d in 0..6 {
+                let upper = (corner >> d) & 1 == 1;
+                if upper && axes[d].len() == 1 {
+                    corner_weight = 0.0;
+                    break;
+                }
+                index += (lower[d] + upper as usize) * strides[d];
+                corner_weight *= if upper { weight[d] } else { 1.0 - weight[d] };
+            }
+
+            if corner_weight != 0.0 {
+                result += corner_weight * self.values[index].max(f64::MIN_POSITIVE).ln();
+            }
+        }
+
+        Ok(result.exp())
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), LookupError> {
+        std::fs::write(path, self.to_text()).map_err(|e| LookupError::Io(e.to_string()))
+    }
+
+    pub fn read(path: &str) -> Result<LookupTable, LookupError> {
+        let text = std::fs::read_to_string(path).map_err(|e| LookupError::Io(e.to_string()))?;
+        LookupTable::from_text(&text)
+    }
+
+    // plain text, one "name value..." line per axis and setting followed by a values line with
+    // one value per node. f64 Display round trips exactly.
+    pub fn to_text(&self) -> String {
+        let join = |values: &[f64]| values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" ");
+
+        let mut text = String::from("# dwcpn lookup table of pp_day / (pmb * day_length)\n");
+        for (name, axis) in LOOKUP_AXIS_NAMES.iter().zip(self.axes.as_array().iter()) {
+            text.push_str(&format!("{} {}\n", name, join(axis)));
+        }
+        text.push_str(&format!("zenith_cutoff {}\n", self.zenith_cutoff));
+        text.push_str(&format!("low_sun_mode {}\n", match self.low_sun_mode {
+            LowSunMode::Triangle => "triangle",
+            LowSunMode::Spectral => "spectral"
+        }));
+        text.push_str(&format!("values {}\n", join(&self.values)));
+
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<LookupTable, LookupError> {
+        let mut axes: [Option<Vec<f64>>; 6] = Default::default();
+        let mut zenith_cutoff: Option<f64> = None;
+        let mut low_sun_mode: Option<LowSunMode> = None;
+        let mut values: Option<Vec<f64>> = None;
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let parse_error = |message: String| LookupError::Parse { line: line_number, message };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or("");
+            let rest: Vec<&str> = fields.collect();
+            let numbers = || -> Result<Vec<f64>, LookupError> {
+                rest.iter()
+                    .map(|field| field.parse::<f64>().map_err(|_| parse_error(format!("could not read {} value '{}'", name, field))))
+                    .collect()
+            };
+
+            if let Some(d) = LOOKUP_AXIS_NAMES.iter().position(|axis| *axis == name) {
+                let axis = numbers()?;
+                if axis.is_empty() || axis.windows(2).any(|pair| pair[1] <= pair[0]) {
+                    return Err(parse_error(format!("{} axis must be non-empty and increasing", name)));
+                }
+                axes[d] = Some(axis);
+            } else {
+                match name {
+                    "zenith_cutoff" => zenith_cutoff = numbers()?.first().copied(),
+                    "low_sun_mode" => low_sun_mode = match rest.first().copied() {
+                        Some("triangle") => Some(LowSunMode::Triangle),
+                        Some("spectral") => Some(LowSunMode::Spectral),
+                        _ => return Err(parse_error("unknown low_sun_mode".to_string()))
+                    },
+                    "values" => values = Some(numbers()?),
+                    _ => return Err(parse_error(format!("unknown entry '{}'", name)))
+                }
+            }
+        }
+
+        let missing = |name: &str| LookupError::Parse { line: 0, message: format!("missing {}", name) };
+        let [i_star, day_length, chl, z_m, sigma, rho] = axes;
+
+        let table = LookupTable {
+            axes: LookupAxes {
+                i_star: i_star.ok_or_else(|| missing("i_star"))?,
+                day_length: day_length.ok_or_else(|| missing("day_length"))?,
+                chl: chl.ok_or_else(|| missing("chl"))?,
+                z_m: z_m.ok_or_else(|| missing("z_m"))?,
+                sigma: sigma.ok_or_else(|| missing("sigma"))?,
+                rho: rho.ok_or_else(|| missing("rho"))?,
+            },
+            zenith_cutoff: zenith_cutoff.ok_or_else(|| missing("zenith_cutoff"))?,
+            low_sun_mode: low_sun_mode.ok_or_else(|| missing("low_sun_mode"))?,
+            values: values.ok_or_else(|| missing("values"))?,
+        };
+
+        if table.values.len() != table.axes.node_count() {
+            return Err(LookupError::Parse {
+                line: 0,
+                message: format!("expected {} values, found {}", table.axes.node_count(), table.values.len())
+            });
+        }
+
+        Ok(table)
+    }
+}
+
+// compare the table with the full model for a set of pixels. Statistics are of table (modelled)
+// against full model (observed); pixels outside the table or for which the model fails are left
+// out of the statistics.
+pub fn check_accuracy(table: &LookupTable, inputs: &[ModelInputs], settings: &ModelSettings) -> ValidationStatistics {
+    let (full, interpolated): (Vec<f64>, Vec<f64>) = inputs.iter()
+        .filter_map(|input| {
+            let interpolated = table.calc_pp_day(input, settings).ok()?;
+            let full = calc_production(input, settings).ok()?.pp_day?;
+            Some((full, interpolated))
+        })
+        .unzip();
+
+    compute_statistics(&full, &interpolated)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_fixtures::model_inputs;
+
+    fn inputs(lat: f64, iday: u16, chl: f64, z_m: f64, sigma: f64, rho: f64) -> ModelInputs {
+        ModelInputs {
+            lat,
+            lon: 0.0,
+            z_bottom: 4000.0,
+            iday,
+            alpha_b: 0.08,
+            pmb: 3.5,
+            z_m,
+            mld: 20.0,
+            chl,
+            rho,
+            sigma,
+            par: Some(45.0),
+            ..model_inputs()
+        }
+    }
+
+    fn settings() -> ModelSettings {
+        ModelSettings::default()
+    }
+
+    fn small_axes() -> LookupAxes {
+        LookupAxes {
+            i_star: vec![0.05, 0.15, 0.45],
+            day_length: vec![10.0, 12.0, 14.0],
+            chl: vec![0.1, 0.3, 1.0],
+            z_m: vec![20.0, 40.0],
+            sigma: vec![10.0, 30.0],
+            rho: vec![0.3, 0.7],
+        }
+    }
+
+    #[test]
+    fn test_nodes_reproduce_model() {
+        let table = generate_lookup_table(small_axes(), &inputs(0.0, 1, 1.0, 0.0, 1.0, 0.5), &settings()).unwrap();
+
+        // a pixel placed exactly on a node (on the day and latitude used to generate it)
+        let mut pixel = inputs(latitude_for_day_length(LONG_DAY, 14.0), LONG_DAY, 0.3, 40.0, 10.0, 0.7);
+        let coordinates = lookup_coordinates(&pixel).unwrap();
+        pixel.par = pixel.par.map(|par| par * 0.15 / coordinates[0]);
+
+        let full = calc_production(&pixel, &settings()).unwrap().pp_day.unwrap();
+        let interpolated = table.calc_pp_day(&pixel, &settings()).unwrap();
+        assert!((interpolated / full - 1.0).abs() < 1e-6);
+
+        // round trip through the text format
+        let read = LookupTable::from_text(&table.to_text()).unwrap();
+        assert_eq!(read.values, table.values);
+        assert_eq!(read.axes.i_star, table.axes.i_star);
+
+        let outside = inputs(30.0, LONG_DAY, 5.0, 30.0, 20.0, 0.5);
+        assert!(matches!(table.calc_pp_day(&outside, &settings()), Err(LookupError::OutOfRange { axis: "chl", .. })));
+    }
+
+    #[test]
+    fn test_accuracy_between_nodes() {
+        let table = generate_lookup_table(small_axes(), &inputs(0.0, 1, 1.0, 0.0, 1.0, 0.5), &settings()).unwrap();
+
+        // pixels away from the nodes and from the solstices the table was generated on
+        let pixels: Vec<ModelInputs> = [
+            (10.0, 100, 0.2, 25.0, 15.0, 0.4),
+            (35.0, 120, 0.5, 30.0, 20.0, 0.5),
+            (-30.0, 240, 0.15, 35.0, 25.0, 0.6),
+            (40.0, 300, 0.7, 22.0, 12.0, 0.35),
+        ].iter()
+            .map(|(lat, iday, chl, z_m, sigma, rho)| inputs(*lat, *iday, *chl, *z_m, *sigma, *rho))
+            .collect();
+
+        let statistics = check_accuracy(&table, &pixels, &settings());
+
+        // a few percent on this deliberately coarse grid, most of it from the profile shape axes
+        assert_eq!(statistics.count, pixels.len());
+        assert!(statistics.log_rmsd < 0.03);
+    }
+
+    #[test]
+    fn test_cloud_only_through_par() {
+        let table = generate_lookup_table(small_axes(), &inputs(0.0, 1, 1.0, 0.0, 1.0, 0.5), &settings()).unwrap();
+
+        let clear = inputs(35.0, 120, 0.5, 30.0, 20.0, 0.5);
+        let cloudy = ModelInputs { cloud: 80.0, ..inputs(35.0, 120, 0.5, 30.0, 20.0, 0.5) };
+
+        // with the daily PAR given, cloud makes no difference to the table
+        assert_eq!(table.calc_pp_day(&cloudy, &settings()).unwrap(), table.calc_pp_day(&clear, &settings()).unwrap());
+
+        // without it, the clear sky PAR is reduced by cloud
+        let estimated = ModelInputs { par: None, ..clear };
+        let estimated_cloudy = ModelInputs { par: None, ..cloudy };
+        assert!(table.calc_pp_day(&estimated_cloudy, &settings()).unwrap() < table.calc_pp_day(&estimated, &settings()).unwrap());
+    }
+
+    // the figures in the module header, generating the standard table takes several minutes:
+    // cargo test --release standard_table_accuracy -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn test_standard_table_accuracy() {
+        let table = generate_lookup_table(LookupAxes::standard(), &standard_reference(), &settings()).unwrap();
+
+        // clear and cloudy days through the year from 45S to 45N, with the clear sky PAR, for
+        // oligotrophic to eutrophic profiles
+        let profiles = [
+            (0.05, 90.0, 40.0, 0.6, 0.05, 6.0),
+            (0.2, 50.0, 25.0, 0.4, 0.08, 3.5),
+            (1.5, 15.0, 12.0, 0.2, 0.1, 2.5),
+            (6.0, 0.0, 8.0, 0.15, 0.12, 2.0),
+        ];
+        let mut pixels: Vec<ModelInputs> = Vec::new();
+        for lat in [-45.0, -30.0, -15.0, 0.0, 15.0, 30.0, 45.0] {
+            for iday in [15, 74, 135, 196, 258, 319] {
+                for (chl, z_m, sigma, rho, alpha_b, pmb) in profiles {
+                    for cloud in [0.0, 50.0] {
+                        pixels.push(ModelInputs { alpha_b, pmb, cloud, par: None, ..inputs(lat, iday, chl, z_m, sigma, rho) });
+                    }
+                }
+            }
+        }
+
+        let statistics = check_accuracy(&table, &pixels, &settings());
+        println!(
+            "pixels {} of {}, bias {:.3} rmsd {:.3} r {:.5}, log bias {:.4} log rmsd {:.4}",
+            statistics.count,
+            pixels.len(),
+            statistics.bias,
+            statistics.rmsd,
+            statistics.pearson_r,
+            statistics.log_bias,
+            statistics.log_rmsd
+        );
+
+        assert_eq!(statistics.count, pixels.len());
+        assert!(statistics.pearson_r > 0.999);
+        assert!(statistics.log_rmsd < 0.02);
+        assert!(statistics.log_bias.abs() < 0.015);
+    }
+}
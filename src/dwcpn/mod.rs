@@ -1,2 +1,13 @@
+pub mod calibration;
 pub mod dwcpn;
+pub mod ensemble;
+pub mod grid;
+pub mod lookup;
 pub mod modules;
+pub mod pi_fit;
+pub mod sensitivity;
+pub mod solar;
+pub mod solar_spectrum;
+pub mod station;
+pub mod surface_irradiance;
+pub mod validation;
@@ -76,34 +76,35 @@ const MICRO: [f64; WL_COUNT] = [
     0.01311538, 0.01067647, 0.00711765, 0.00355882, 0.,
 ];
 
-pub fn calc_ac(chl: f64) -> ([f64; WL_COUNT], f64) {
-    let mut chlorophyll_absorption: [f64; WL_COUNT] = [0.0; WL_COUNT];
-    let mut absorption_sum: f64 = 0.0;
-
+// chlorophyll of the pico, nano and microphytoplankton size classes (in that order) from the total,
+// after Brewin et al 2011 and 2015
+pub fn calc_size_class_chl(chl: f64) -> [f64; 3] {
     // coefficients
-    // taken from Brewin et al 2011 and 2015
     let cm_pn: f64 = 0.77;
     let s_pn: f64 = 0.94 / cm_pn;
     let cm_p: f64 = 0.13;
     let s_p: f64 = 0.80 / cm_p;
 
-    // compute fractions
-    for i in 0..WL_COUNT {
-        let mut pico_absorption: f64 = cm_p * (1.0 - (-s_p * chl).exp());
-        let mut nano_absorption: f64 = cm_pn * (1.0 - (-s_pn * chl).exp()) - pico_absorption;
-        let mut micro_absorption: f64 = chl - (cm_pn * (1.0 - (-s_pn * chl).exp()));
-
-        // guarantee that no negative absorption gets through as it is phsyically impossible
-        if pico_absorption < 0.0 {
-            pico_absorption = 0.0;
-        };
-        if nano_absorption < 0.0 {
-            nano_absorption = 0.0;
-        };
-        if micro_absorption < 0.0 {
-            micro_absorption = 0.0;
-        };
+    let pico: f64 = cm_p * (1.0 - (-s_p * chl).exp());
+    let nano: f64 = cm_pn * (1.0 - (-s_pn * chl).exp()) - pico;
+    let micro: f64 = chl - (cm_pn * (1.0 - (-s_pn * chl).exp()));
+
+    // guarantee that no negative fraction gets through as it is phsyically impossible
+    [pico, nano, micro].map(|fraction| if fraction < 0.0 { 0.0 } else { fraction })
+}
+
+// chlorophyll specific absorption spectra of the size classes, ordered as calc_size_class_chl
+pub fn size_class_absorption_spectra() -> [&'static [f64; WL_COUNT]; 3] {
+    [&PICO, &NANO, &MICRO]
+}
+
+pub fn calc_ac(chl: f64) -> ([f64; WL_COUNT], f64) {
+    let mut chlorophyll_absorption: [f64; WL_COUNT] = [0.0; WL_COUNT];
+    let mut absorption_sum: f64 = 0.0;
 
+    let [pico_absorption, nano_absorption, micro_absorption] = calc_size_class_chl(chl);
+
+    for i in 0..WL_COUNT {
         chlorophyll_absorption[i] = (PICO[i] * pico_absorption)
             + (NANO[i] * nano_absorption)
             + (MICRO[i] * micro_absorption);
@@ -115,3 +116,22 @@ pub fn calc_ac(chl: f64) -> ([f64; WL_COUNT], f64) {
 
     return (chlorophyll_absorption, absorption_mean);
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_calc_size_class_chl() {
+        // picoplankton dominate at low chlorophyll and microplankton at high
+        for chl in [0.05, 0.3, 1.0, 5.0] {
+            let size_class_chl = calc_size_class_chl(chl);
+            assert!((size_class_chl.iter().sum::<f64>() - chl).abs() < 1e-12);
+        }
+        let low = calc_size_class_chl(0.05);
+        assert!(low[0] > low[1] && low[0] > low[2]);
+        let high = calc_size_class_chl(5.0);
+        assert!(high[2] > high[0] + high[1]);
+    }
+}
@@ -0,0 +1,249 @@
+// Broadband (non-spectral) model of daily water column production. Light is attenuated with a
+// single PAR attenuation coefficient K for each layer of the model depth grid, from its chlorophyll
+// by Riley's relation, instead of the spectral absorption and scattering of the full model.
+//
+// Within a layer of uniform biomass B the exponential P-I curve used by the spectral model
+// integrates over depth in closed form (Platt, Sathyendranath and Ravindran 1990, Proc. R. Soc.
+// Lond. B 241):
+//
+//     P_layer(t) = (B pmb / K) (Ein(I*_top(t)) - Ein(I*_bottom(t))),    I*(t) = alpha_b I(t) / pmb
+//
+// where Ein(x) = int_0^x (1 - exp(-u)) / u du. The layers are summed down to the euphotic (1%
+// light) depth. The integral over the day is numerical, by Simpson's rule over the surface light
+// curve, so the sine curve, the polar day cosine zenith curve and PAR time series are treated
+// alike. This is not the closed form daily solution of Platt and Sathyendranath (1993, JGR 98), in
+// which the time integral for uniform biomass is approximated by a polynomial in the noon value
+// I*_m; for uniform biomass and a sine curve this mode computes the same daily integral directly.
+//
+// Besides the broadband K, it differs from the spectral model in ignoring the spectral shape of
+// phytoplankton absorption, the mean cosine of the light field and the zenith cutoff, and in
+// taking the PAR above the surface as the light at the top of the water column, without
+// reflection at the surface.
+
+use crate::dwcpn::modules::config::{BROADBAND_TIMESTEPS, DEPTH_PROFILE_COUNT, DEPTH_PROFILE_STEP};
+use crate::dwcpn::modules::pp_profile::compute_pi_response;
+use crate::ModelInputs;
+
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+// converts alpha_b in mgC mgChl^-1 h^-1 (W m^-2)^-1 for use with irradiance in einsteins m^-2 h^-1,
+// as for the spectral model in calc_depth_optics
+const ALPHA_CONVERSION: f64 = 6022.0 / (2.77 * 36.0);
+
+pub struct BroadbandProduction {
+    pub pp_day: f64,
+    // daily production at each depth of the model depth grid
+    pub pp_profile: [f64; DEPTH_PROFILE_COUNT],
+    pub euphotic_depth: f64,
+}
+
+// diffuse attenuation coefficient for PAR (m^-1) from chlorophyll, after Riley (1956)
+pub fn riley_attenuation(chl: f64) -> f64 {
+    0.04 + 0.0088 * chl + 0.054 * chl.max(0.0).powf(2.0 / 3.0)
+}
+
+// exponential integral E1(x) for x > 1 by its continued fraction (modified Lentz)
+fn exponential_integral_e1(x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut b = x + 1.0;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+
+    for i in 1..200 {
+        let a = -((i * i) as f64);
+        b += 2.0;
+        d = 1.0 / (a * d + b);
+        c = b + a / c;
+        let delta = c * d;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+
+    h * (-x).exp()
+}
+
+// Ein(x) = int_0^x (1 - exp(-u)) / u du, the depth integral of the exponential P-I curve
+pub fn ein(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    if x <= 1.0 {
+        // alternating series sum (-1)^(k+1) x^k / (k k!)
+        let mut sum = 0.0;
+        let mut power_over_factorial = 1.0;
+        for k in 1..40 {
+            power_over_factorial *= x / k as f64;
+            let term = power_over_factorial / k as f64;
+            sum += if k % 2 == 1 { term } else { -term };
+            if term < 1e-17 {
+                break;
+            }
+        }
+        sum
+    } else {
+        EULER_GAMMA + x.ln() + exponential_integral_e1(x)
+    }
+}
+
+impl BroadbandProduction {
+    // mean of two estimates of the daily production, e.g. from the morning and the afternoon
+    pub fn mean_with(&self, other: &BroadbandProduction) -> BroadbandProduction {
+        let mut pp_profile: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
+        for (z, pp_z) in pp_profile.iter_mut().enumerate() {
+            *pp_z = (self.pp_profile[z] + other.pp_profile[z]) / 2.0;
+        }
+
+        BroadbandProduction {
+            pp_day: (self.pp_day + other.pp_day) / 2.0,
+            pp_profile,
+            euphotic_depth: self.euphotic_depth.max(other.euphotic_depth),
+        }
+    }
+}
+
+// daily production for the chlorophyll profile, with surface_par giving the PAR (einsteins
+// m^-2 h^-1) above the surface at each time between sunrise and noon, which is used without a
+// surface transmission
+pub fn calc_broadband_production<F>(
+    chl_profile: &[f64; DEPTH_PROFILE_COUNT],
+    input: &ModelInputs,
+    sunrise: f64,
+    surface_par: F
+) -> BroadbandProduction
+where
+    F: Fn(f64) -> f64,
+{
+    let euphotic_optical_depth = 100.0f64.ln();
+
+    // optical depth at the top of each layer, the layers below the euphotic depth are left out
+    let mut layer_count = 0;
+    let mut optical_depth_top: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
+    let mut layer_thickness: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
+    let mut optical_depth = 0.0;
+    let mut euphotic_depth = DEPTH_PROFILE_STEP * DEPTH_PROFILE_COUNT as f64;
+
+    for (z, chl) in chl_profile.iter().enumerate() {
+        let k = riley_attenuation(*chl);
+        let top = z as f64 * DEPTH_PROFILE_STEP;
+
+        optical_depth_top[z] = optical_depth;
+        layer_count = z + 1;
+
+        if optical_depth + k * DEPTH_PROFILE_STEP >= euphotic_optical_depth {
+            layer_thickness[z] = (euphotic_optical_depth - optical_depth) / k;
+            euphotic_depth = top + layer_thickness[z];
+            break;
+        }
+
+        layer_thickness[z] = DEPTH_PROFILE_STEP;
+        optical_depth += k * DEPTH_PROFILE_STEP;
+    }
+
+    // clamp to the physical depth of the ocean, as for the spectral model
+    if euphotic_depth > input.z_bottom.abs() {
+        euphotic_depth = input.z_bottom.abs();
+        layer_count = 0;
+        for (z, thickness) in layer_thickness.iter_mut().enumerate() {
+            let top = z as f64 * DEPTH_PROFILE_STEP;
+            if top >= euphotic_depth {
+                break;
+            }
+            *thickness = (euphotic_depth - top).min(DEPTH_PROFILE_STEP);
+            layer_count = z + 1;
+        }
+    }
+
+    // water column production and the production at each depth at a given time
+    let production_at = |time: f64, pp_profile: &mut [f64; DEPTH_PROFILE_COUNT], weight: f64| -> f64 {
+        let i_star = input.alpha_b * ALPHA_CONVERSION * surface_par(time) / input.pmb;
+        let mut pp_z = 0.0;
+
+        for z in 0..layer_count {
+            let chl = chl_profile[z];
+            let k = riley_attenuation(chl);
+            let i_star_top = i_star * (-optical_depth_top[z]).exp();
+            let i_star_bottom = i_star_top * (-k * layer_thickness[z]).exp();
+
+            pp_z += chl * input.pmb / k * (ein(i_star_top) - ein(i_star_bottom));
+            pp_profile[z] += weight * chl * compute_pi_response(i_star_top * input.pmb, input.pmb);
+        }
+
+        pp_z
+    };
+
+    // Simpson's rule from sunrise to noon
+    let mut pp_profile: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
+    let delta_t = (12.0 - sunrise) / BROADBAND_TIMESTEPS as f64;
+    let mut pp_day = 0.0;
+
+    for t in 0..=BROADBAND_TIMESTEPS {
+        let weight = delta_t / 3.0 * match t {
+            0 => 1.0,
+            _ if t == BROADBAND_TIMESTEPS => 1.0,
+            _ if t % 2 == 1 => 4.0,
+            _ => 2.0
+        };
+        pp_day += weight * production_at(sunrise + t as f64 * delta_t, &mut pp_profile, weight);
+    }
+
+    // multiply by two because we have only integrated over half of the day
+    for pp_z in pp_profile.iter_mut() {
+        *pp_z *= 2.0;
+    }
+
+    BroadbandProduction {
+        pp_day: pp_day * 2.0,
+        pp_profile,
+        euphotic_depth,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_ein() {
+        // Ein(x) = gamma + ln(x) + E1(x), reference values of E1 from Abramowitz and Stegun table 5.1
+        assert!((ein(0.5) - (EULER_GAMMA + 0.5f64.ln() + 0.559_773_594_8)).abs() < 1e-9);
+        assert!((ein(1.0) - 0.796_599_599_297_053_1).abs() < 1e-12);
+        assert!((ein(2.0) - (EULER_GAMMA + 2.0f64.ln() + 0.048_900_510_7)).abs() < 1e-9);
+        assert!((ein(5.0) - (EULER_GAMMA + 5.0f64.ln() + 0.001_148_295_6)).abs() < 1e-9);
+
+        // continuous where the method changes
+        assert!((ein(1.0 - 1e-12) - ein(1.0 + 1e-12)).abs() < 1e-10);
+    }
+
+    // uniform biomass under a sine curve from 6:00 to 18:00, in the light limited and light
+    // saturated limits where the daily integral has a closed form
+    #[test]
+    fn test_uniform_biomass_limits() {
+        use crate::test_fixtures::model_inputs;
+
+        let input = ModelInputs { alpha_b: 0.05, pmb: 2.5, chl: 0.4, ..model_inputs() };
+        let k = riley_attenuation(input.chl);
+        let day_length = 12.0;
+        let sine_curve = |iom: f64| move |time: f64| iom * (std::f64::consts::PI * (time - 6.0) / 12.0).sin();
+
+        // light limited, P = alpha_b B I, so the water column down to the 1% light level gives
+        // alpha_b B (0.99 / K) times the daily light 2 D iom / pi
+        let iom = 1e-6;
+        let production = calc_broadband_production(&[input.chl; DEPTH_PROFILE_COUNT], &input, 6.0, sine_curve(iom));
+        let expected = input.alpha_b * ALPHA_CONVERSION * input.chl * 0.99 / k * 2.0 * day_length * iom / std::f64::consts::PI;
+        assert!((production.pp_day / expected - 1.0).abs() < 1e-5);
+
+        // light saturated, Ein(I*) - Ein(I* / 100) tends to ln(100), so P = B pmb ln(100) D / K,
+        // less part of the first Simpson interval where the light jumps from darkness to saturation
+        let iom = 1e9;
+        let production = calc_broadband_production(&[input.chl; DEPTH_PROFILE_COUNT], &input, 6.0, sine_curve(iom));
+        let expected = input.chl * input.pmb * 100.0f64.ln() * day_length / k;
+        assert!((production.pp_day / expected - 1.0).abs() < 1.0 / BROADBAND_TIMESTEPS as f64);
+
+        assert!((production.euphotic_depth - 100.0f64.ln() / k).abs() < 1e-9);
+    }
+}
@@ -62,7 +62,7 @@ fn gen_chl_mld_profile(
     return (depth_array, chl_profile);
 }
 
-fn gen_depth_array() -> [f64; DEPTH_PROFILE_COUNT] {
+pub fn gen_depth_array() -> [f64; DEPTH_PROFILE_COUNT] {
     let mut depth_array: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
 
     for i in 0..DEPTH_PROFILE_COUNT {
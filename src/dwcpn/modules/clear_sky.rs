@@ -0,0 +1,180 @@
+use crate::dwcpn::modules::config::{LOW_SUN_TIMESTEPS, TIMESTEPS, WL_COUNT};
+use crate::dwcpn::modules::irradiance::{compute_irradiance_components, compute_solar_correction};
+use crate::dwcpn::modules::time::{compute_daylight_regime, compute_sunrise, generate_time_array, resolve_day_of_year, DaylightRegime};
+use crate::dwcpn::modules::zenith::{compute_zenith, compute_zenith_time, generate_zenith_array};
+use crate::{LowSunMode, ModelInputs, ModelSettings, SolarSpectrum};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// Clear sky direct and diffuse spectral irradiance at sea level over the half day from sunrise to
+// noon. None of this depends on anything but the day, latitude and zenith cutoff, so for a global
+// image it can be computed once per latitude row and shared by every pixel on that row. The cloud
+// and PAR scaling is done per pixel with correct_and_recompute_irradiance_components.
+pub struct ClearSkyIrradiance {
+    pub day_of_year: u16,
+    pub year_length: f64,
+    pub lat: f64,
+    pub zenith_cutoff: f64,
+    pub solar_spectrum: SolarSpectrum,
+    pub sunrise: f64,
+    pub delta: f64,
+    pub phi: f64,
+    pub daylight_regime: DaylightRegime,
+    // time the zenith angle drops below the cutoff (negative if it never does)
+    pub start_time: f64,
+    pub integration_window: bool,
+    // Earth-Sun distance correction, see compute_solar_correction
+    pub solar_correction: f64,
+    pub time_array: [f64; TIMESTEPS],
+    pub delta_t: f64,
+    pub zenith_array: [f64; TIMESTEPS],
+    pub zenith_d_array: [f64; TIMESTEPS],
+    pub direct: [[f64; WL_COUNT]; TIMESTEPS],
+    pub diffuse: [[f64; WL_COUNT]; TIMESTEPS],
+    // extra time steps between sunrise and start_time, only computed for LowSunMode::Spectral and
+    // during polar day
+    pub low_sun: Option<LowSunIrradiance>,
+}
+
+pub struct LowSunIrradiance {
+    pub time_array: [f64; LOW_SUN_TIMESTEPS + 1],
+    pub delta_t: f64,
+    pub zenith_array: [f64; LOW_SUN_TIMESTEPS + 1],
+    pub zenith_d_array: [f64; LOW_SUN_TIMESTEPS + 1],
+    pub direct: [[f64; WL_COUNT]; LOW_SUN_TIMESTEPS + 1],
+    pub diffuse: [[f64; WL_COUNT]; LOW_SUN_TIMESTEPS + 1],
+}
+
+impl ClearSkyIrradiance {
+    pub fn new(day_of_year: u16, year_length: f64, lat: f64, settings: &ModelSettings) -> ClearSkyIrradiance {
+        // all times in the model are local solar time, with noon at 12.0
+        let (sunrise, delta, phi) = compute_sunrise(day_of_year, year_length, lat);
+        let daylight_regime = compute_daylight_regime(delta, phi);
+
+        // start_time is calculation start time, when the zenith angle drops below the cutoff
+        // (midnight if the sun stays above that all day, negative if it never does)
+        let start_time = compute_zenith_time(delta, phi, settings.zenith_cutoff);
+
+        // the sun never climbs above the cutoff, so there is no window to integrate over
+        let integration_window = start_time >= 0.0 && daylight_regime != DaylightRegime::PolarNight;
+
+        let (time_array, delta_t) = generate_time_array(start_time.max(0.0));
+        let (zenith_array, zenith_d_array) = generate_zenith_array(time_array, delta, phi);
+
+        let mut direct = [[0.0; WL_COUNT]; TIMESTEPS];
+        let mut diffuse = [[0.0; WL_COUNT]; TIMESTEPS];
+
+        if integration_window {
+            for t in 0..TIMESTEPS {
+                if zenith_d_array[t] >= settings.zenith_cutoff + 0.00005 {
+                    continue;
+                }
+                (direct[t], diffuse[t]) = compute_irradiance_components(zenith_array[t], zenith_d_array[t], &settings.solar_spectrum);
+            }
+        }
+
+        let low_sun_spectral = settings.low_sun_mode == LowSunMode::Spectral || daylight_regime == DaylightRegime::PolarDay;
+        let low_sun = if low_sun_spectral && daylight_regime != DaylightRegime::PolarNight {
+            // without an integration window the whole morning is spent below the cutoff
+            let low_sun_end = if integration_window { start_time } else { 12.0 };
+            let low_sun_delta_t = (low_sun_end - sunrise) / LOW_SUN_TIMESTEPS as f64;
+
+            let mut low_sun_time_array = [0.0; LOW_SUN_TIMESTEPS + 1];
+            let mut low_sun_zenith_array = [0.0; LOW_SUN_TIMESTEPS + 1];
+            let mut low_sun_zenith_d_array = [0.0; LOW_SUN_TIMESTEPS + 1];
+            let mut low_sun_direct = [[0.0; WL_COUNT]; LOW_SUN_TIMESTEPS + 1];
+            let mut low_sun_diffuse = [[0.0; WL_COUNT]; LOW_SUN_TIMESTEPS + 1];
+
+            for i in 0..LOW_SUN_TIMESTEPS + 1 {
+                low_sun_time_array[i] = sunrise + low_sun_delta_t * i as f64;
+                low_sun_zenith_array[i] = compute_zenith(low_sun_time_array[i], delta, phi);
+                low_sun_zenith_d_array[i] = low_sun_zenith_array[i] * (180.0 / PI);
+
+                // the sun is on (or below) the horizon so there is no light
+                if low_sun_zenith_d_array[i] >= 90.0 {
+                    continue;
+                }
+                (low_sun_direct[i], low_sun_diffuse[i]) =
+                    compute_irradiance_components(low_sun_zenith_array[i], low_sun_zenith_d_array[i], &settings.solar_spectrum);
+            }
+
+            Some(
+                LowSunIrradiance {
+                    time_array: low_sun_time_array,
+                    delta_t: low_sun_delta_t,
+                    zenith_array: low_sun_zenith_array,
+                    zenith_d_array: low_sun_zenith_d_array,
+                    direct: low_sun_direct,
+                    diffuse: low_sun_diffuse,
+                }
+            )
+        } else {
+            None
+        };
+
+        ClearSkyIrradiance {
+            day_of_year,
+            year_length,
+            lat,
+            zenith_cutoff: settings.zenith_cutoff,
+            solar_spectrum: settings.solar_spectrum.clone(),
+            sunrise,
+            delta,
+            phi,
+            daylight_regime,
+            start_time,
+            integration_window,
+            solar_correction: compute_solar_correction(&settings.solar_spectrum, day_of_year, year_length),
+            time_array,
+            delta_t,
+            zenith_array,
+            zenith_d_array,
+            direct,
+            diffuse,
+            low_sun,
+        }
+    }
+
+    pub fn for_inputs(input: &ModelInputs, settings: &ModelSettings) -> ClearSkyIrradiance {
+        let (day_of_year, year_length) = resolve_day_of_year(input);
+        ClearSkyIrradiance::new(day_of_year, year_length, input.lat, settings)
+    }
+
+    // whether this irradiance was computed for the day and latitude of the inputs and with settings
+    // that need the same time steps
+    pub fn matches(&self, input: &ModelInputs, settings: &ModelSettings) -> bool {
+        let (day_of_year, year_length) = resolve_day_of_year(input);
+
+        day_of_year == self.day_of_year
+            && year_length == self.year_length
+            && input.lat == self.lat
+            && settings.zenith_cutoff == self.zenith_cutoff
+            && settings.solar_spectrum == self.solar_spectrum
+            && (settings.low_sun_mode == LowSunMode::Triangle
+                || self.low_sun.is_some()
+                || self.daylight_regime == DaylightRegime::PolarNight)
+    }
+}
+
+// clear sky irradiance for each distinct day and latitude seen in a run over many pixels, all with
+// the same settings (e.g. once per row of a global image)
+#[derive(Default)]
+pub struct ClearSkyCache {
+    irradiance: HashMap<(u16, u64, u64), ClearSkyIrradiance>
+}
+
+impl ClearSkyCache {
+    // irradiance for the day and latitude of the inputs, only computed the first time they are seen
+    pub fn for_inputs(&mut self, input: &ModelInputs, settings: &ModelSettings) -> &ClearSkyIrradiance {
+        let (day_of_year, year_length) = resolve_day_of_year(input);
+
+        self.irradiance
+            .entry((day_of_year, year_length.to_bits(), input.lat.to_bits()))
+            .or_insert_with(|| ClearSkyIrradiance::new(day_of_year, year_length, input.lat, settings))
+    }
+
+    // number of days and latitudes the irradiance has been computed for
+    pub fn computed(&self) -> usize {
+        self.irradiance.len()
+    }
+}
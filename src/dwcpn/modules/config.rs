@@ -1,5 +1,11 @@
 pub const TIMESTEPS: usize =  12;
 
+// time steps between sunrise and the zenith cutoff when the low sun period is computed spectrally
+pub const LOW_SUN_TIMESTEPS: usize = 6;
+
+// Simpson's rule intervals between sunrise and noon for the broadband model, must be even
+pub const BROADBAND_TIMESTEPS: usize = 24;
+
 pub const DEPTH_PROFILE_COUNT: usize = 500;
 pub const DEPTH_PROFILE_STEP: f64 = 0.5;
 
@@ -0,0 +1,43 @@
+// Floating point type for the spectral light field, which holds the largest array of the model
+// (an optical profile of DEPTH_PROFILE_COUNT x WL_COUNT x 2 values, swept at every time step).
+// The optics are computed in f64 and stored as F, and the light decay with depth is computed in
+// F; everything else stays in f64.
+//
+// Precision::F32 halves the memory taken by the optical profile, for memory bound batch runs.
+// The daily production stays within 1e-6 relative of the f64 result (see f32_precision_test).
+
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub};
+
+pub trait Float:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + MulAssign
+{
+    const ZERO: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn exp(self) -> Self;
+}
+
+impl Float for f64 {
+    const ZERO: f64 = 0.0;
+
+    fn from_f64(value: f64) -> f64 { value }
+    fn to_f64(self) -> f64 { self }
+    fn exp(self) -> f64 { f64::exp(self) }
+}
+
+impl Float for f32 {
+    const ZERO: f32 = 0.0;
+
+    fn from_f64(value: f64) -> f32 { value as f32 }
+    fn to_f64(self) -> f64 { self as f64 }
+    fn exp(self) -> f32 { f32::exp(self) }
+}
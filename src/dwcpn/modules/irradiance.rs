@@ -1,5 +1,8 @@
 use crate::dwcpn::modules::config::{WL_ARRAY, WL_COUNT, DELTA_LAMBDA};
 use crate::dwcpn::modules::linear_interp::linear_interp;
+use crate::dwcpn::modules::time::DaylightRegime;
+use crate::dwcpn::modules::zenith::compute_zenith;
+use crate::SolarSpectrum;
 
 use std::f64::consts::PI;
 
@@ -51,6 +54,9 @@ const ET_SPECTRAL_IRRADIANCE: [f64; TRANSMITTANCE_WL_COUNT] = [
     1831.0, 1891.0, 1898.0, 1892.0, 1840.0, 1768.0, 1728.0, 1658.0, 1524.0, 1531.0, 1420.0, 1399.0,
 ];
 
+// mean extra-terrestrial irradiance the legacy Earth-Sun distance table is relative to
+const LEGACY_SOLAR_CONSTANT: f64 = 1353.0;
+
 pub fn lookup_thekaekara_correction(julian_day: u16) -> f64 {
     let day_points: [f64; 25] = [
         0., 3., 31., 42., 59., 78., 90., 93., 120., 133., 151., 170., 181., 183., 206., 212., 243.,
@@ -62,6 +68,9 @@ pub fn lookup_thekaekara_correction(julian_day: u16) -> f64 {
         1309., 1312., 1313., 1329., 1344., 1350., 1353., 1347., 1375., 1392., 1398., 1399.,
     ];
 
+    // as in the original model the day of the year is compared with the table index rather than
+    // the tabulated days, so from day 24 on the correction is extrapolated from the last two
+    // points; kept for backward compatibility, SolarSpectrum::Reference uses Spencer (1971)
     let mut idx: usize = 0;
     for i in 0..25 {
         idx = i;
@@ -80,6 +89,26 @@ pub fn lookup_thekaekara_correction(julian_day: u16) -> f64 {
     }
 }
 
+// ratio of the extra-terrestrial irradiance on a day of the year to its mean, (r0 / r)^2 from the
+// Fourier series of Spencer (1971) in the day angle 2 pi (day - 1) / year_length
+pub fn compute_earth_sun_distance_correction(day_of_year: u16, year_length: f64) -> f64 {
+    let day_angle = 2.0 * PI * (day_of_year as f64 - 1.0) / year_length;
+
+    1.000110
+        + 0.034221 * day_angle.cos()
+        + 0.001280 * day_angle.sin()
+        + 0.000719 * (2.0 * day_angle).cos()
+        + 0.000077 * (2.0 * day_angle).sin()
+}
+
+// factor applied to the mean extra-terrestrial spectrum for the Earth-Sun distance on the day
+pub fn compute_solar_correction(solar_spectrum: &SolarSpectrum, day_of_year: u16, year_length: f64) -> f64 {
+    match solar_spectrum {
+        SolarSpectrum::Legacy => lookup_thekaekara_correction(day_of_year) / LEGACY_SOLAR_CONSTANT,
+        SolarSpectrum::Reference(_) => compute_earth_sun_distance_correction(day_of_year, year_length)
+    }
+}
+
 fn compute_airmass(zen_r: f64, zen_d: f64) -> f64 {
     let mut airmass: f64;
 
@@ -177,6 +206,8 @@ fn find_lut_index(zen_d: f64) -> usize {
 // take the correction factors for 5 wavelengths from the LUT and interpolate to the
 // number of wavelengths we are using in the transmittance calculations (24 at time of writing)
 fn interpolate_correction_factor(zen_d: f64) -> [f64; TRANSMITTANCE_WL_COUNT] {
+    // hold the last correction factors for a low sun rather than extrapolating past the table
+    let zen_d = zen_d.min(CORRECTION_ZEN_LOOKUP[CORRECTION_ZEN_LOOKUP.len() - 1]);
     let lut_index: usize = find_lut_index(zen_d);
 
     let c: [f64; 5] = CORRECTION[lut_index];
@@ -221,7 +252,9 @@ fn interpolate_correction_factor(zen_d: f64) -> [f64; TRANSMITTANCE_WL_COUNT] {
     return correction_interpolated;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn compute_diffuse_irradiance(
+    et_irradiance: [f64; TRANSMITTANCE_WL_COUNT],
     zen_r: f64,
     zen_d: f64,
     direct_irradiance: [f64; TRANSMITTANCE_WL_COUNT],
@@ -237,7 +270,7 @@ fn compute_diffuse_irradiance(
     let mut diffuse: [f64; TRANSMITTANCE_WL_COUNT] = [0.0; TRANSMITTANCE_WL_COUNT];
 
     for l in 0..24 {
-        let xx = ET_SPECTRAL_IRRADIANCE[l] * zen_r.cos() * t_ozone[l] * t_water_vapour[l];
+        let xx = et_irradiance[l] * zen_r.cos() * t_ozone[l] * t_water_vapour[l];
         let mut r = xx * t_aerosol[l] * (1.0 - t_rayleigh[l]) * 0.5;
         let mut a = xx * t_rayleigh[l] * (1.0 - t_aerosol[l] * 0.928 * 0.82);
 
@@ -258,6 +291,7 @@ fn compute_diffuse_irradiance(
 }
 
 fn compute_direct_irradiance(
+    et_irradiance: [f64; TRANSMITTANCE_WL_COUNT],
     t_aerosol: [f64; TRANSMITTANCE_WL_COUNT],
     t_ozone: [f64; TRANSMITTANCE_WL_COUNT],
     t_rayleigh: [f64; TRANSMITTANCE_WL_COUNT],
@@ -267,7 +301,7 @@ fn compute_direct_irradiance(
     let mut direct: [f64; TRANSMITTANCE_WL_COUNT] = [0.0; TRANSMITTANCE_WL_COUNT];
 
     for i in 0..TRANSMITTANCE_WL_COUNT {
-        direct[i] = ET_SPECTRAL_IRRADIANCE[i]
+        direct[i] = et_irradiance[i]
             * t_rayleigh[i]
             * t_aerosol[i]
             * t_water_vapour[i]
@@ -278,10 +312,20 @@ fn compute_direct_irradiance(
     direct
 }
 
+// clear sky direct and diffuse irradiance at sea level on WL_ARRAY (W m^-2 um^-1, the direct
+// component normal to the beam) at the mean Earth-Sun distance. The transmittances are computed on
+// TRANSMITTANCE_WAVELENGTHS, where the legacy spectrum is applied before interpolating to WL_ARRAY
+// and a reference spectrum after (everything here is linear in the extra-terrestrial irradiance)
 pub fn compute_irradiance_components(
     zenith_r: f64,
     zenith_d: f64,
+    solar_spectrum: &SolarSpectrum
 ) -> ([f64; WL_COUNT], [f64; WL_COUNT]) {
+    let et_irradiance = match solar_spectrum {
+        SolarSpectrum::Legacy => ET_SPECTRAL_IRRADIANCE,
+        SolarSpectrum::Reference(_) => [1.0; TRANSMITTANCE_WL_COUNT]
+    };
+
     // use airmass estimate initially until we calculate air albedo and then we recalculate transmittances
     let airmass = 1.90;
 
@@ -300,9 +344,10 @@ pub fn compute_irradiance_components(
     let t_ozone = compute_ozone_transmittance(zenith_r);
     let t_u = compute_tu(airmass);
 
-    let direct = compute_direct_irradiance(t_aerosol, t_ozone, t_rayleigh, t_u, t_water_vapour);
+    let direct = compute_direct_irradiance(et_irradiance, t_aerosol, t_ozone, t_rayleigh, t_u, t_water_vapour);
 
     let diffuse = compute_diffuse_irradiance(
+        et_irradiance,
         zenith_r,
         zenith_d,
         direct,
@@ -314,10 +359,17 @@ pub fn compute_irradiance_components(
         t_water_vapour,
     );
 
-    let direct_interpolated = interpolate_irradiances(TRANSMITTANCE_WAVELENGTHS, WL_ARRAY, direct);
-    let diffuse_interpolated =
+    let mut direct_interpolated = interpolate_irradiances(TRANSMITTANCE_WAVELENGTHS, WL_ARRAY, direct);
+    let mut diffuse_interpolated =
         interpolate_irradiances(TRANSMITTANCE_WAVELENGTHS, WL_ARRAY, diffuse);
 
+    if let SolarSpectrum::Reference(reference) = solar_spectrum {
+        for l in 0..WL_COUNT {
+            direct_interpolated[l] *= reference[l];
+            diffuse_interpolated[l] *= reference[l];
+        }
+    }
+
     return (direct_interpolated, diffuse_interpolated);
 }
 
@@ -339,17 +391,76 @@ fn interpolate_irradiances(
     output_irradiances
 }
 
+// compute surface irradiance from total daily surface irradiance (e.g. satellite par)
+// assuming a sinusoidal variation between sunrise and sunset
+pub fn compute_par_surface_irradiance(iom: f64, time: f64, sunrise: f64, day_length: f64) -> f64 {
+    iom * (PI * (time - sunrise) / day_length).sin()
+}
+
+// during continuous daylight the sine curve above would fall to zero at midnight, so the daily
+// total is instead distributed in proportion to the cosine of the zenith angle, which integrates
+// to 24 * sin(delta) * sin(phi) over a day in which the sun never sets
+pub fn compute_polar_day_par_surface_irradiance(par: f64, zenith_r: f64, delta: f64, phi: f64) -> f64 {
+    par * zenith_r.cos() / (24.0 * delta.sin() * phi.sin())
+}
+
+// noon surface PAR from the daily total, for the sine (or during polar day cosine zenith) shape
+// of the daily cycle
+pub fn compute_iom(par: f64, daylight_regime: DaylightRegime, day_length: f64, delta: f64, phi: f64) -> f64 {
+    match daylight_regime {
+        DaylightRegime::PolarDay => compute_polar_day_par_surface_irradiance(
+            par,
+            compute_zenith(12.0, delta, phi),
+            delta,
+            phi
+        ),
+        DaylightRegime::PolarNight => 0.0,
+        DaylightRegime::Normal => par * PI / (2.0 * day_length)
+    }
+}
+
 pub fn correct_and_recompute_irradiance_components(
     direct: [f64;WL_COUNT],
     diffuse: [f64;WL_COUNT],
     solar_correction: f64,
-    iom: f64,
-    day_length: f64,
-    sunrise: f64,
+    par_surface_irradiance: f64,
     zenith_r: f64,
-    time: f64,
     cloud_cover: f64
 ) -> ([f64; WL_COUNT], [f64; WL_COUNT]) {
+    let (mut direct_corrected, mut diffuse_corrected, surface_irradiance) = correct_irradiance_components(
+        direct,
+        diffuse,
+        solar_correction,
+        zenith_r,
+        cloud_cover
+    );
+
+    // Adjustment to the difuse and direct component: from use of measured total daily surface irradiance (
+    // e.g. satellite PAR) to compute the surface irradiance at all time. SSP
+    let adjustment: f64 = par_surface_irradiance / surface_irradiance;
+
+    //compute the adjusted irradiance surface value
+    // i_z[0] = 0.0;
+    for l in 0..WL_COUNT {
+        direct_corrected[l] = direct_corrected[l] * adjustment;
+        diffuse_corrected[l] = diffuse_corrected[l] * adjustment;
+
+        // i_z[0] = i_z[0] + (direct[l] + diffuse[l]) * DELTA_LAMBDA;
+    }
+
+    (direct_corrected, diffuse_corrected)
+}
+
+// direct and diffuse irradiance just below the surface (einsteins m^-2 h^-1 nm^-1) from the clear
+// sky components, corrected for the Earth-Sun distance, cloud and surface reflection, along with
+// the PAR just above the surface that correct_and_recompute_irradiance_components scales to
+pub fn correct_irradiance_components(
+    direct: [f64;WL_COUNT],
+    diffuse: [f64;WL_COUNT],
+    solar_correction: f64,
+    zenith_r: f64,
+    cloud_cover: f64
+) -> ([f64; WL_COUNT], [f64; WL_COUNT], f64) {
     let mut direct_integrated: f64 = 0.0;
     let mut diffuse_integrated: f64 = 0.0;
 
@@ -359,10 +470,10 @@ pub fn correct_and_recompute_irradiance_components(
     let mut surface_irradiance: f64 = 0.0;
 
     for l in 0..WL_COUNT {
-        // apply fractional correction to diffuse and direct components of irradiance
-        // the max correction value is 1353.0, so this converts it to as though we were applying a percentage correction
-        direct_corrected[l] = direct[l] * solar_correction / 1353.0;
-        diffuse_corrected[l] = diffuse[l] * solar_correction / 1353.0;
+        // apply the Earth-Sun distance correction (see compute_solar_correction) to diffuse and
+        // direct components of irradiance
+        direct_corrected[l] = direct[l] * solar_correction;
+        diffuse_corrected[l] = diffuse[l] * solar_correction;
 
         // add this value to the integrated direct/diffuse components
         direct_integrated = direct_integrated + (direct[l] * zenith_r.cos());
@@ -410,23 +521,7 @@ pub fn correct_and_recompute_irradiance_components(
         diffuse_corrected[l] = diffuse_corrected[l] * 0.945;
     }
 
-    // compute surface irradiance from total daily surface irradiance (e.g. satellite par)
-    let par_surface_irradiance: f64 = iom * (PI * (time - (sunrise)) / day_length).sin();
     surface_irradiance = surface_irradiance * DELTA_LAMBDA;
 
-
-    // Adjustment to the difuse and direct component: from use of measured total daily surface irradiance (
-    // e.g. satellite PAR) to compute the surface irradiance at all time. SSP
-    let adjustment: f64 = par_surface_irradiance / surface_irradiance;
-
-    //compute the adjusted irradiance surface value
-    // i_z[0] = 0.0;
-    for l in 0..WL_COUNT {
-        direct_corrected[l] = direct_corrected[l] * adjustment;
-        diffuse_corrected[l] = diffuse_corrected[l] * adjustment;
-
-        // i_z[0] = i_z[0] + (direct[l] + diffuse[l]) * DELTA_LAMBDA;
-    }
-
-    (direct_corrected, diffuse_corrected)
+    (direct_corrected, diffuse_corrected, surface_irradiance)
 }
@@ -1,44 +1,52 @@
-use crate::dwcpn::modules::absorption::calc_ac;
+use crate::dwcpn::modules::absorption::{calc_ac, size_class_absorption_spectra};
 use crate::dwcpn::modules::config::{AW, DELTA_LAMBDA, DEPTH_PROFILE_COUNT, DEPTH_PROFILE_STEP, WL_ARRAY, WL_COUNT};
+use crate::dwcpn::modules::float::Float;
 use crate::dwcpn::modules::linear_interp::linear_interp;
-use crate::ModelInputs;
+use crate::{ModelInputs, Precision};
 
 pub struct LightProfile {
     pub par_profile: [f64; DEPTH_PROFILE_COUNT],
     pub i_alpha_profile: [f64; DEPTH_PROFILE_COUNT]
 }
 
-pub fn init_mu_d_and_i_z (
+pub fn init_mu_d_and_i_z<F: Float> (
     direct_irradiance: [f64; WL_COUNT],
     diffuse_irradiance: [f64; WL_COUNT],
     zenith_r: f64
-) -> ([f64; WL_COUNT], [f64; WL_COUNT]) {
+) -> ([F; WL_COUNT], [F; WL_COUNT]) {
     let mut i_zero: [f64; WL_COUNT] = [0.0; WL_COUNT];
-    let mut mu_d: [f64; WL_COUNT] = [0.0; WL_COUNT];
-    let mut i_z: [f64; WL_COUNT] = [0.0; WL_COUNT];
+    let mut mu_d: [F; WL_COUNT] = [F::ZERO; WL_COUNT];
+    let mut i_z: [F; WL_COUNT] = [F::ZERO; WL_COUNT];
     let zenith_w: f64 = (zenith_r.sin() / 1.333).asin();
 
     for l in 0..WL_COUNT {
         i_zero[l] = direct_irradiance[l] + diffuse_irradiance[l];
-        mu_d[l] =
-            (direct_irradiance[l] * zenith_w.cos() + diffuse_irradiance[l] * 0.831000) / i_zero[l];
-        i_z[l] = i_zero[l];
+        mu_d[l] = F::from_f64(
+            (direct_irradiance[l] * zenith_w.cos() + diffuse_irradiance[l] * 0.831000) / i_zero[l]
+        );
+        i_z[l] = F::from_f64(i_zero[l]);
     }
 
     return (mu_d, i_z)
 }
 
-pub fn calc_i_z_decay(
-    ac: [f64; WL_COUNT],
-    mu_d: [f64; WL_COUNT],
-    i_z: [f64; WL_COUNT],
-    chl: f64,
-    inputs: &ModelInputs,
-    ac_mean: f64
-) -> (f64, [f64; WL_COUNT], f64) {
-    let mut i_z= i_z.clone();
-    let mut i_alpha = 0.0;
-    let mut k: [f64; WL_COUNT] = [0.0; WL_COUNT];
+// optical properties at one depth that depend only on the chlorophyll concentration and the
+// inputs, so they can be computed once per run rather than at every time step
+pub struct DepthOptics<F: Float = f64> {
+    // total absorption plus backscattering, divided by mu_d to give the attenuation coefficient
+    pub a_plus_bb: [F; WL_COUNT],
+    // spectral weighting of irradiance by the phytoplankton absorption for i_alpha
+    pub alpha_coefficient: [F; WL_COUNT],
+}
+
+// computed in f64 whatever the precision of the stored values
+pub fn calc_depth_optics<F: Float>(chl: f64, inputs: &ModelInputs) -> Option<DepthOptics<F>> {
+    let (ac, ac_mean) = calc_ac(chl);
+
+    if ac_mean == 0.0 { return None; }
+
+    let mut a_plus_bb: [f64; WL_COUNT] = [0.0; WL_COUNT];
+    let mut alpha_coefficient: [f64; WL_COUNT] = [0.0; WL_COUNT];
 
     let ac440 = linear_interp(&WL_ARRAY, &ac, 440.0);
 
@@ -54,12 +62,6 @@ pub fn calc_i_z_decay(
         bbtilda = 0.01;
     }
 
-    let mut par = 0.0;
-
-    for l in 0..WL_COUNT {
-        par = par + i_z[l] * DELTA_LAMBDA;
-    }
-
     for l in 0..WL_COUNT {
         let wl = WL_ARRAY[l];
         let a = AW[l] + ac[l] + ay440 * inputs.ay[l] + 2.0 * inputs.bbr[l];
@@ -71,61 +73,163 @@ pub fn calc_i_z_decay(
 
         let bb = bc * bbtilda + inputs.bw[l] * 0.50;
 
-        k[l] = (a + bb) / mu_d[l];
-
-        // par_profile[z] = par_profile[z] + i_z[l] * DELTA_LAMBDA;
+        a_plus_bb[l] = a + bb;
 
         // this conversion expects pi_alpha to be in units of
         // mgC mgChl^-1 h^-1 (W m^-2)^-1
         // a.ka. (mgC per mgChl per Hour) / (Watts per m^2)
         // the line below converts irradiance (light units) to einsteins per m^2 per hour
         // this makes it compatible with the par units
-        let x = inputs.alpha_b * ac[l] * 6022.0 / (2.77 * 36.0 * ac_mean);
+        alpha_coefficient[l] = inputs.alpha_b * ac[l] * 6022.0 / (2.77 * 36.0 * ac_mean);
+    }
 
+    Some(DepthOptics { a_plus_bb: a_plus_bb.map(F::from_f64), alpha_coefficient: alpha_coefficient.map(F::from_f64) })
+}
 
+// alpha_coefficient of each size class, from the absorption spectrum of the class alone. As the
+// spectrum is normalised by its mean this does not depend on the chlorophyll of the class.
+pub fn calc_size_class_alpha_coefficients(alpha_b: [f64; 3]) -> [[f64; WL_COUNT]; 3] {
+    let spectra = size_class_absorption_spectra();
+    let mut alpha_coefficients: [[f64; WL_COUNT]; 3] = [[0.0; WL_COUNT]; 3];
 
-        i_alpha = i_alpha + x * DELTA_LAMBDA * i_z[l] / mu_d[l];
-        i_z[l] = i_z[l] * (-k[l] * DEPTH_PROFILE_STEP).exp();
+    for (k, spectrum) in spectra.iter().enumerate() {
+        let spectrum_mean = spectrum.iter().sum::<f64>() / WL_COUNT as f64;
+
+        for l in 0..WL_COUNT {
+            alpha_coefficients[k][l] = alpha_b[k] * spectrum[l] * 6022.0 / (2.77 * 36.0 * spectrum_mean);
+        }
+    }
+
+    alpha_coefficients
+}
+
+// optics for each depth of the chlorophyll profile, stopping at the first depth with no
+// chlorophyll absorption (the light profile is not computed below that)
+pub fn calc_optical_profile<F: Float>(chl_profile: &[f64; DEPTH_PROFILE_COUNT], inputs: &ModelInputs) -> Vec<DepthOptics<F>> {
+    chl_profile.iter()
+        .map_while(|chl| calc_depth_optics(*chl, inputs))
+        .collect()
+}
+
+pub fn calc_i_z_decay<F: Float>(
+    optics: &DepthOptics<F>,
+    mu_d: [F; WL_COUNT],
+    mut i_z: [F; WL_COUNT]
+) -> (F, [F; WL_COUNT], F) {
+    let delta_lambda = F::from_f64(DELTA_LAMBDA);
+    let depth_step = F::from_f64(DEPTH_PROFILE_STEP);
+
+    let mut par = F::ZERO;
+    for i in i_z.iter() {
+        par += *i * delta_lambda;
+    }
+
+    let mut i_alpha = F::ZERO;
+    let spectra = optics.a_plus_bb.iter().zip(optics.alpha_coefficient.iter()).zip(mu_d.iter());
+    for (i, ((a_plus_bb, alpha_coefficient), mu)) in i_z.iter_mut().zip(spectra) {
+        let k = *a_plus_bb / *mu;
+
+        i_alpha += *alpha_coefficient * delta_lambda * *i / *mu;
+        *i *= (-k * depth_step).exp();
     }
 
     (i_alpha, i_z, par)
 }
 
-pub fn calc_light_decay_profile(
-    chl_profile: [f64; DEPTH_PROFILE_COUNT],
+// i_alpha and par with depth, plus the i_alpha of each size class in the same light field when
+// their alpha coefficients are given. The light field is computed down to the first depth where PAR
+// falls below par_cutoff of the surface value (0.001 in the original model), or through the whole
+// profile for a par_cutoff of zero.
+pub fn calc_light_decay_profile<F: Float>(
+    optical_profile: &[DepthOptics<F>],
     direct_irradiance: [f64; WL_COUNT],
     diffuse_irradiance: [f64; WL_COUNT],
     zenith_r: f64,
-    inputs: &ModelInputs,
-) -> ([f64; DEPTH_PROFILE_COUNT], [f64; DEPTH_PROFILE_COUNT]) {
-    let mut i_alpha_profile = [0.0; DEPTH_PROFILE_COUNT];
-    let mut par_profile = [0.0; DEPTH_PROFILE_COUNT];
+    size_class_alpha_coefficients: Option<&[[f64; WL_COUNT]; 3]>,
+    par_cutoff: f64
+) -> ([F; DEPTH_PROFILE_COUNT], [F; DEPTH_PROFILE_COUNT], Option<[[F; DEPTH_PROFILE_COUNT]; 3]>) {
+    let mut i_alpha_profile = [F::ZERO; DEPTH_PROFILE_COUNT];
+    let mut par_profile = [F::ZERO; DEPTH_PROFILE_COUNT];
+    let par_cutoff = F::from_f64(par_cutoff);
+    let delta_lambda = F::from_f64(DELTA_LAMBDA);
+
+    let size_class_alpha_coefficients = size_class_alpha_coefficients
+        .map(|coefficients| coefficients.map(|class_coefficients| class_coefficients.map(F::from_f64)));
+    let mut size_class_i_alpha_profiles = size_class_alpha_coefficients.map(|_| [[F::ZERO; DEPTH_PROFILE_COUNT]; 3]);
 
     let (mu_d, mut i_z) = init_mu_d_and_i_z(direct_irradiance, diffuse_irradiance, zenith_r);
 
-    for z in 0..DEPTH_PROFILE_COUNT {
-        let chl = chl_profile[z];
-        let (ac, ac_mean) = calc_ac(chl);
-
-        if ac_mean == 0.0 { break; }
+    for (z, optics) in optical_profile.iter().enumerate() {
+        // from the irradiance at the top of the layer, as i_alpha in calc_i_z_decay
+        if let (Some(coefficients), Some(profiles)) = (&size_class_alpha_coefficients, &mut size_class_i_alpha_profiles) {
+            for (class_coefficients, profile) in coefficients.iter().zip(profiles.iter_mut()) {
+                for l in 0..WL_COUNT {
+                    profile[z] += class_coefficients[l] * delta_lambda * i_z[l] / mu_d[l];
+                }
+            }
+        }
 
-        let (i_alpha_z, i_z_temp, par_z) = calc_i_z_decay(
-            ac,
-            mu_d,
-            i_z,
-            chl,
-            inputs,
-            ac_mean
-        );
+        let (i_alpha_z, i_z_temp, par_z) = calc_i_z_decay(optics, mu_d, i_z);
         i_alpha_profile[z] = i_alpha_z;
         i_z = i_z_temp;
         par_profile[z] = par_z;
 
-        if par_profile[z] < (0.001 * par_profile[0]) {
+        if par_profile[z] < (par_cutoff * par_profile[0]) {
             break;
         }
 
     }
 
-    (i_alpha_profile,par_profile)
+    (i_alpha_profile, par_profile, size_class_i_alpha_profiles)
+}
+
+// optical profile stored at the precision of the light field calculation
+pub enum OpticalProfile {
+    F64(Vec<DepthOptics<f64>>),
+    F32(Vec<DepthOptics<f32>>),
+}
+
+impl OpticalProfile {
+    pub fn new(chl_profile: &[f64; DEPTH_PROFILE_COUNT], inputs: &ModelInputs, precision: Precision) -> OpticalProfile {
+        match precision {
+            Precision::F64 => OpticalProfile::F64(calc_optical_profile(chl_profile, inputs)),
+            Precision::F32 => OpticalProfile::F32(calc_optical_profile(chl_profile, inputs)),
+        }
+    }
+
+    // i_alpha, par and size class i_alpha profiles as from calc_light_decay_profile, returned as f64
+    pub fn light_decay_profile(
+        &self,
+        direct_irradiance: [f64; WL_COUNT],
+        diffuse_irradiance: [f64; WL_COUNT],
+        zenith_r: f64,
+        size_class_alpha_coefficients: Option<&[[f64; WL_COUNT]; 3]>,
+        par_cutoff: f64
+    ) -> ([f64; DEPTH_PROFILE_COUNT], [f64; DEPTH_PROFILE_COUNT], Option<[[f64; DEPTH_PROFILE_COUNT]; 3]>) {
+        match self {
+            OpticalProfile::F64(optical_profile) => calc_light_decay_profile(
+                optical_profile,
+                direct_irradiance,
+                diffuse_irradiance,
+                zenith_r,
+                size_class_alpha_coefficients,
+                par_cutoff
+            ),
+            OpticalProfile::F32(optical_profile) => {
+                let (i_alpha_profile, par_profile, size_class_i_alpha_profiles) = calc_light_decay_profile(
+                    optical_profile,
+                    direct_irradiance,
+                    diffuse_irradiance,
+                    zenith_r,
+                    size_class_alpha_coefficients,
+                    par_cutoff
+                );
+                (
+                    i_alpha_profile.map(f32::to_f64),
+                    par_profile.map(f32::to_f64),
+                    size_class_i_alpha_profiles.map(|profiles| profiles.map(|profile| profile.map(f32::to_f64)))
+                )
+            },
+        }
+    }
 }
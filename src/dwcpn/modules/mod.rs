@@ -1,9 +1,17 @@
 pub mod absorption;
+pub mod broadband;
 pub mod chl_profile;
+pub mod clear_sky;
 pub mod config;
+pub mod float;
 pub mod irradiance;
 pub mod linear_interp;
+pub mod optimise;
+pub mod par_series;
+pub mod parameters;
 pub mod pp_profile;
+pub mod random;
+pub mod temperature;
 pub mod time;
 pub mod zenith;
 pub mod light_profile;
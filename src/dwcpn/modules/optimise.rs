@@ -0,0 +1,134 @@
+// Derivative free minimisation with the Nelder-Mead simplex method. The model is discretised
+// in depth and time so its response to the inputs is not smooth enough for gradient methods.
+
+pub struct NelderMeadResult {
+    pub point: Vec<f64>,
+    pub value: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+// minimise f starting from start, with the initial simplex spanning step along each axis.
+// Converges when the spread of function values across the simplex falls below tolerance.
+pub fn nelder_mead<F>(
+    mut f: F,
+    start: &[f64],
+    step: &[f64],
+    max_iterations: usize,
+    tolerance: f64
+) -> NelderMeadResult
+where
+    F: FnMut(&[f64]) -> f64,
+{
+    const REFLECTION: f64 = 1.0;
+    const EXPANSION: f64 = 2.0;
+    const CONTRACTION: f64 = 0.5;
+    const SHRINK: f64 = 0.5;
+
+    let n = start.len();
+
+    let mut simplex: Vec<Vec<f64>> = Vec::with_capacity(n + 1);
+    simplex.push(start.to_vec());
+    for i in 0..n {
+        let mut vertex = start.to_vec();
+        vertex[i] += step[i];
+        simplex.push(vertex);
+    }
+    let mut values: Vec<f64> = simplex.iter().map(|vertex| f(vertex)).collect();
+
+    let mut iterations: usize = 0;
+    let mut converged = false;
+
+    while iterations < max_iterations {
+        iterations += 1;
+
+        // order the vertices from best to worst
+        let mut order: Vec<usize> = (0..=n).collect();
+        order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+        simplex = order.iter().map(|i| simplex[*i].clone()).collect();
+        values = order.iter().map(|i| values[*i]).collect();
+
+        if (values[n] - values[0]).abs() <= tolerance * (values[0].abs() + tolerance) {
+            converged = true;
+            break;
+        }
+
+        // centroid of all but the worst vertex
+        let mut centroid = vec![0.0; n];
+        for vertex in simplex.iter().take(n) {
+            for (c, v) in centroid.iter_mut().zip(vertex.iter()) {
+                *c += v / n as f64;
+            }
+        }
+
+        let towards = |coefficient: f64, vertex: &[f64]| -> Vec<f64> {
+            centroid.iter().zip(vertex.iter()).map(|(c, v)| c + coefficient * (v - c)).collect()
+        };
+
+        let reflected = towards(-REFLECTION, &simplex[n]);
+        let reflected_value = f(&reflected);
+
+        if reflected_value < values[0] {
+            let expanded = towards(-EXPANSION, &simplex[n]);
+            let expanded_value = f(&expanded);
+
+            if expanded_value < reflected_value {
+                simplex[n] = expanded;
+                values[n] = expanded_value;
+            } else {
+                simplex[n] = reflected;
+                values[n] = reflected_value;
+            }
+        } else if reflected_value < values[n - 1] {
+            simplex[n] = reflected;
+            values[n] = reflected_value;
+        } else {
+            let contracted = if reflected_value < values[n] {
+                towards(-CONTRACTION, &simplex[n])
+            } else {
+                towards(CONTRACTION, &simplex[n])
+            };
+            let contracted_value = f(&contracted);
+
+            if contracted_value < values[n].min(reflected_value) {
+                simplex[n] = contracted;
+                values[n] = contracted_value;
+            } else {
+                // shrink everything towards the best vertex
+                for i in 1..=n {
+                    let shrunk: Vec<f64> = simplex[0].iter().zip(simplex[i].iter())
+                        .map(|(best, v)| best + SHRINK * (v - best))
+                        .collect();
+                    values[i] = f(&shrunk);
+                    simplex[i] = shrunk;
+                }
+            }
+        }
+    }
+
+    let best = (0..=n).min_by(|a, b| values[*a].total_cmp(&values[*b])).unwrap_or(0);
+
+    NelderMeadResult {
+        point: simplex[best].clone(),
+        value: values[best],
+        iterations,
+        converged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rosenbrock() {
+        let rosenbrock = |x: &[f64]| (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0].powi(2)).powi(2);
+
+        let result = nelder_mead(rosenbrock, &[-1.2, 1.0], &[0.5, 0.5], 2000, 1e-14);
+
+        assert!(result.converged);
+        assert!((result.point[0] - 1.0).abs() < 1e-3);
+        assert!((result.point[1] - 1.0).abs() < 1e-3);
+    }
+}
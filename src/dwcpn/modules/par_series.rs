@@ -0,0 +1,70 @@
+// Sub-daily surface PAR, e.g. hourly from a buoy or a reanalysis, used to scale the spectral
+// irradiance at each time step in place of the sine curve reconstructed from the daily total.
+// The samples are linearly interpolated in time and looked up at the clock time of each model
+// time step (UTC plus utc_offset, as for the reported clock times). The series need not be
+// symmetric about noon, so the model integrates the morning and the afternoon separately.
+
+use crate::dwcpn::modules::linear_interp::linear_interp;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParTimeSeries {
+    // clock time in hours, increasing, and may run past midnight either side (e.g. -1.0 or 25.0)
+    pub times: Vec<f64>,
+    // instantaneous surface PAR in einsteins m^-2 h^-1 (umol m^-2 s^-1 times 0.0036)
+    pub values: Vec<f64>,
+}
+
+impl ParTimeSeries {
+    pub fn new(times: Vec<f64>, values: Vec<f64>) -> ParTimeSeries {
+        ParTimeSeries { times, values }
+    }
+
+    // at least two samples, at increasing times, with finite values
+    pub fn is_valid(&self) -> bool {
+        self.times.len() >= 2
+            && self.times.len() == self.values.len()
+            && self.times.windows(2).all(|pair| pair[0] < pair[1])
+            && self.times.iter().chain(self.values.iter()).all(|value| value.is_finite())
+    }
+
+    pub fn covers(&self, start: f64, end: f64) -> bool {
+        self.times[0] <= start && self.times[self.times.len() - 1] >= end
+    }
+
+    // surface PAR at a clock time inside the series, negative samples are taken as zero
+    pub fn at(&self, clock_time: f64) -> f64 {
+        linear_interp(&self.times, &self.values, clock_time).max(0.0)
+    }
+
+    // PAR integrated between two clock times inside the series (einsteins m^-2)
+    pub fn integrate(&self, start: f64, end: f64) -> f64 {
+        let mut points: Vec<f64> = vec![start];
+        points.extend(self.times.iter().filter(|time| **time > start && **time < end));
+        points.push(end);
+
+        points.windows(2)
+            .map(|pair| (self.at(pair[0]) + self.at(pair[1])) * (pair[1] - pair[0]) / 2.0)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_par_time_series() {
+        let series = ParTimeSeries::new(vec![0.0, 6.0, 12.0, 18.0, 24.0], vec![-0.1, 0.0, 6.0, 0.0, 0.0]);
+        assert!(series.is_valid());
+        assert!(series.covers(5.5, 18.5) && !series.covers(-1.0, 12.0));
+
+        assert_eq!(series.at(0.0), 0.0);
+        assert_eq!(series.at(9.0), 3.0);
+        assert!((series.integrate(6.0, 18.0) - 36.0).abs() < 1e-12);
+        assert!((series.integrate(9.0, 12.0) - 13.5).abs() < 1e-12);
+
+        assert!(!ParTimeSeries::new(vec![0.0, 0.0], vec![1.0, 1.0]).is_valid());
+        assert!(!ParTimeSeries::new(vec![0.0, 1.0], vec![1.0, f64::NAN]).is_valid());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::ModelInputs;
+
+// The scalar fields of ModelInputs that can be perturbed, fitted or analysed by name
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModelParameter {
+    Lat,
+    Lon,
+    ZBottom,
+    AlphaB,
+    Pmb,
+    ZM,
+    Mld,
+    Chl,
+    Rho,
+    Sigma,
+    Cloud,
+    YelSub,
+    Par,
+    Sst,
+}
+
+impl ModelParameter {
+    pub const ALL: [ModelParameter; 14] = [
+        ModelParameter::Lat,
+        ModelParameter::Lon,
+        ModelParameter::ZBottom,
+        ModelParameter::AlphaB,
+        ModelParameter::Pmb,
+        ModelParameter::ZM,
+        ModelParameter::Mld,
+        ModelParameter::Chl,
+        ModelParameter::Rho,
+        ModelParameter::Sigma,
+        ModelParameter::Cloud,
+        ModelParameter::YelSub,
+        ModelParameter::Par,
+        ModelParameter::Sst,
+    ];
+
+    // field name as it appears in ModelInputs
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModelParameter::Lat => "lat",
+            ModelParameter::Lon => "lon",
+            ModelParameter::ZBottom => "z_bottom",
+            ModelParameter::AlphaB => "alpha_b",
+            ModelParameter::Pmb => "pmb",
+            ModelParameter::ZM => "z_m",
+            ModelParameter::Mld => "mld",
+            ModelParameter::Chl => "chl",
+            ModelParameter::Rho => "rho",
+            ModelParameter::Sigma => "sigma",
+            ModelParameter::Cloud => "cloud",
+            ModelParameter::YelSub => "yel_sub",
+            ModelParameter::Par => "par",
+            ModelParameter::Sst => "sst",
+        }
+    }
+
+    // whether input files may leave out the column, sst is only needed for a temperature dependence
+    pub fn is_optional(&self) -> bool {
+        *self == ModelParameter::Sst
+    }
+
+    pub fn from_name(name: &str) -> Option<ModelParameter> {
+        ModelParameter::ALL.iter().copied().find(|parameter| parameter.name() == name)
+    }
+
+    pub fn get(&self, inputs: &ModelInputs) -> f64 {
+        match self {
+            ModelParameter::Lat => inputs.lat,
+            ModelParameter::Lon => inputs.lon,
+            ModelParameter::ZBottom => inputs.z_bottom,
+            ModelParameter::AlphaB => inputs.alpha_b,
+            ModelParameter::Pmb => inputs.pmb,
+            ModelParameter::ZM => inputs.z_m,
+            ModelParameter::Mld => inputs.mld,
+            ModelParameter::Chl => inputs.chl,
+            ModelParameter::Rho => inputs.rho,
+            ModelParameter::Sigma => inputs.sigma,
+            ModelParameter::Cloud => inputs.cloud,
+            ModelParameter::YelSub => inputs.yel_sub,
+            // NaN when the model estimates it
+            ModelParameter::Par => inputs.par.unwrap_or(f64::NAN),
+            ModelParameter::Sst => inputs.sst.unwrap_or(f64::NAN),
+        }
+    }
+
+    pub fn set(&self, inputs: &mut ModelInputs, value: f64) {
+        match self {
+            ModelParameter::Lat => inputs.lat = value,
+            ModelParameter::Lon => inputs.lon = value,
+            ModelParameter::ZBottom => inputs.z_bottom = value,
+            ModelParameter::AlphaB => inputs.alpha_b = value,
+            ModelParameter::Pmb => inputs.pmb = value,
+            ModelParameter::ZM => inputs.z_m = value,
+            ModelParameter::Mld => inputs.mld = value,
+            ModelParameter::Chl => inputs.chl = value,
+            ModelParameter::Rho => inputs.rho = value,
+            ModelParameter::Sigma => inputs.sigma = value,
+            ModelParameter::Cloud => inputs.cloud = value,
+            ModelParameter::YelSub => inputs.yel_sub = value,
+            // NaN, as from get, leaves it to the model
+            ModelParameter::Par => inputs.par = Some(value).filter(|par| !par.is_nan()),
+            ModelParameter::Sst => inputs.sst = Some(value).filter(|sst| !sst.is_nan()),
+        }
+    }
+
+    // whether a value is physically meaningful for this parameter
+    pub fn is_valid(&self, value: f64) -> bool {
+        if !value.is_finite() {
+            return false;
+        }
+
+        match self {
+            ModelParameter::Lat => (-90.0..=90.0).contains(&value),
+            ModelParameter::Lon => (-180.0..=360.0).contains(&value),
+            ModelParameter::Rho => (0.0..1.0).contains(&value),
+            ModelParameter::Cloud => (0.0..=100.0).contains(&value),
+            // seawater freezes at about -1.9 degrees C
+            ModelParameter::Sst => (-2.0..=40.0).contains(&value),
+            ModelParameter::ZM | ModelParameter::YelSub | ModelParameter::Par => value >= 0.0,
+            _ => value > 0.0,
+        }
+    }
+}
@@ -1,5 +1,6 @@
+use crate::dwcpn::modules::absorption::calc_size_class_chl;
 use crate::dwcpn::modules::config::{DEPTH_PROFILE_COUNT, DEPTH_PROFILE_STEP, WL_ARRAY, WL_COUNT};
-use crate::{ModelInputs, PPErrors};
+use crate::{IntegrationLimit, ModelInputs, PPErrors};
 
 
 pub struct PpProfile {
@@ -7,6 +8,8 @@ pub struct PpProfile {
     pub par_profile: [f64; DEPTH_PROFILE_COUNT],
     pub euphotic_depth: f64,
     pub euph_index: usize,
+    // depth the profile is integrated to, see IntegrationLimit
+    pub integration_depth: f64,
     pub spectral_i_star: f64,
     pub success: bool,
 }
@@ -58,19 +61,26 @@ pub fn calculate_ay() -> [f64; WL_COUNT] {
     ay
 }
 
+// chlorophyll normalised production from the photosynthesis-irradiance (P-I) curve,
+// where i_alpha is the product of the initial slope alpha_b and irradiance
+pub fn compute_pi_response(i_alpha: f64, pmb: f64) -> f64 {
+    pmb * (1.0 - (-i_alpha / pmb).exp())
+}
+
 pub fn compute_pp_depth_profile(
     chl_profile: &[f64; DEPTH_PROFILE_COUNT],
     depth_profile: &[f64; DEPTH_PROFILE_COUNT],
     i_alpha_profile: &[f64; DEPTH_PROFILE_COUNT],
     par_profile: &[f64; DEPTH_PROFILE_COUNT],
-    model_inputs: &ModelInputs
+    model_inputs: &ModelInputs,
+    integration_limit: IntegrationLimit
 ) -> Result<PpProfile, PPErrors> {
     let mut pp_profile: [f64; DEPTH_PROFILE_COUNT] = [0.0; DEPTH_PROFILE_COUNT];
 
     let mut i_alpha_sum: f64 = 0.0;
 
     for z in 0..DEPTH_PROFILE_COUNT {
-        pp_profile[z] = chl_profile[z] * model_inputs.pmb * (1.0 - (-i_alpha_profile[z] / model_inputs.pmb).exp());
+        pp_profile[z] = chl_profile[z] * compute_pi_response(i_alpha_profile[z], model_inputs.pmb);
         i_alpha_sum = i_alpha_sum + i_alpha_profile[z];
 
         if z > 0 && par_profile[z] < (0.01 * par_profile[0]) {
@@ -82,12 +92,26 @@ pub fn compute_pp_depth_profile(
                 euphotic_depth = model_inputs.z_bottom.abs();
             }
 
+            // the production below the euphotic depth is only needed for a deeper limit
+            if integration_limit != IntegrationLimit::EuphoticDepth {
+                for deeper_z in z + 1..DEPTH_PROFILE_COUNT {
+                    pp_profile[deeper_z] = chl_profile[deeper_z]
+                        * compute_pi_response(i_alpha_profile[deeper_z], model_inputs.pmb);
+                }
+            }
 
             return Ok(PpProfile {
                 pp_profile,
                 par_profile: par_profile.clone(),
                 euphotic_depth,
                 euph_index,
+                integration_depth: compute_integration_depth(
+                    integration_limit,
+                    euphotic_depth,
+                    depth_profile,
+                    par_profile,
+                    model_inputs
+                ),
                 spectral_i_star: i_alpha_sum / model_inputs.pmb,
                 success: true,
             });
@@ -97,6 +121,68 @@ pub fn compute_pp_depth_profile(
     return Err(PPErrors::DWCPNError);
 }
 
+// depth of the integration limit, clamped to the physical depth of the ocean and the depth grid
+fn compute_integration_depth(
+    integration_limit: IntegrationLimit,
+    euphotic_depth: f64,
+    depth_profile: &[f64; DEPTH_PROFILE_COUNT],
+    par_profile: &[f64; DEPTH_PROFILE_COUNT],
+    model_inputs: &ModelInputs
+) -> f64 {
+    let grid_bottom = depth_profile[DEPTH_PROFILE_COUNT - 1];
+
+    let integration_depth = match integration_limit {
+        // already clamped to z_bottom, and left as it is so that the original results are unchanged
+        IntegrationLimit::EuphoticDepth => return euphotic_depth,
+        IntegrationLimit::TenthPercentLight => (1..DEPTH_PROFILE_COUNT)
+            .find(|z| par_profile[*z] < 0.001 * par_profile[0])
+            .map_or(grid_bottom, |z| interpolate_light_depth(z, depth_profile, par_profile, 1000.0)),
+        IntegrationLimit::MixedLayer => model_inputs.mld,
+        IntegrationLimit::FixedDepth(depth) => depth,
+        IntegrationLimit::Seabed => model_inputs.z_bottom.abs(),
+    };
+
+    integration_depth.abs().min(model_inputs.z_bottom.abs()).min(grid_bottom)
+}
+
+// chlorophyll of the pico, nano and microphytoplankton with depth, splitting the chlorophyll at
+// each depth as calc_size_class_chl
+pub fn compute_size_class_chl_profiles(chl_profile: &[f64; DEPTH_PROFILE_COUNT]) -> [[f64; DEPTH_PROFILE_COUNT]; 3] {
+    let mut size_class_chl_profiles: [[f64; DEPTH_PROFILE_COUNT]; 3] = [[0.0; DEPTH_PROFILE_COUNT]; 3];
+
+    for z in 0..DEPTH_PROFILE_COUNT {
+        for (k, chl) in calc_size_class_chl(chl_profile[z]).into_iter().enumerate() {
+            size_class_chl_profiles[k][z] = chl;
+        }
+    }
+
+    size_class_chl_profiles
+}
+
+// production of each size class with depth from its own chlorophyll, i_alpha and pmb, computed at
+// the depths where the total profile of compute_pp_depth_profile has production. The total is
+// computed with the absorption and parameters of the whole population, not as the sum of the
+// classes, so the classes do not add up to it exactly
+pub fn compute_size_class_pp_profiles(
+    size_class_chl_profiles: &[[f64; DEPTH_PROFILE_COUNT]; 3],
+    size_class_i_alpha_profiles: &[[f64; DEPTH_PROFILE_COUNT]; 3],
+    size_class_pmb: [f64; 3],
+    pp_profile: &[f64; DEPTH_PROFILE_COUNT]
+) -> [[f64; DEPTH_PROFILE_COUNT]; 3] {
+    let mut size_class_pp_profiles: [[f64; DEPTH_PROFILE_COUNT]; 3] = [[0.0; DEPTH_PROFILE_COUNT]; 3];
+
+    for k in 0..3 {
+        for z in 0..DEPTH_PROFILE_COUNT {
+            if pp_profile[z] > 0.0 {
+                size_class_pp_profiles[k][z] = size_class_chl_profiles[k][z]
+                    * compute_pi_response(size_class_i_alpha_profiles[k][z], size_class_pmb[k]);
+            }
+        }
+    }
+
+    size_class_pp_profiles
+}
+
 pub fn compute_prochloro_profile(
     chl_profile: &[f64; DEPTH_PROFILE_COUNT],
     depth_profile: &[f64; DEPTH_PROFILE_COUNT],
@@ -115,7 +201,7 @@ pub fn compute_prochloro_profile(
     let mut i_alpha_sum: f64 = 0.0;
 
     for z in 0..DEPTH_PROFILE_COUNT {
-        let production_coefficient = model_inputs.pmb * (1.0 - (-i_alpha_profile[z] / model_inputs.pmb).exp());
+        let production_coefficient = compute_pi_response(i_alpha_profile[z], model_inputs.pmb);
         pp_profile[z] = chl_profile[z] * production_coefficient;
         i_alpha_sum = i_alpha_sum + i_alpha_profile[z];
 
@@ -168,9 +254,22 @@ fn integrate_euphotic_depth(
     par_profile: &[f64; DEPTH_PROFILE_COUNT]
 ) -> (usize, f64) {
     let euph_index = depth_index - 1;
-    let euphotic_depth = depth_profile[euph_index]
-        + DEPTH_PROFILE_STEP * (100.0 * par_profile[euph_index] / par_profile[0]).ln()
-        / (par_profile[euph_index] / par_profile[depth_index]).ln();
+    let euphotic_depth = interpolate_light_depth(depth_index, depth_profile, par_profile, 100.0);
 
     (euph_index, euphotic_depth)
+}
+
+// depth at which PAR falls to 1 / attenuation of the surface value, between depth_index (the
+// first depth below it) and the depth above, assuming exponential decay
+fn interpolate_light_depth(
+    depth_index: usize,
+    depth_profile: &[f64; DEPTH_PROFILE_COUNT],
+    par_profile: &[f64; DEPTH_PROFILE_COUNT],
+    attenuation: f64
+) -> f64 {
+    let above_index = depth_index - 1;
+
+    depth_profile[above_index]
+        + DEPTH_PROFILE_STEP * (attenuation * par_profile[above_index] / par_profile[0]).ln()
+        / (par_profile[above_index] / par_profile[depth_index]).ln()
 }
\ No newline at end of file
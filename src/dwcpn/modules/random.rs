@@ -0,0 +1,40 @@
+// Small seeded random number generator so that ensemble runs are reproducible
+// without pulling in external crates. Uses the SplitMix64 generator, which is
+// plenty for Monte Carlo sampling of model inputs.
+
+pub struct SeededRng {
+    state: u64,
+    cached_normal: Option<f64>,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed, cached_normal: None }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform on (0, 1], never returns zero so it is safe to take the log
+    pub fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    // standard normal deviate using the Box-Muller transform
+    pub fn next_standard_normal(&mut self) -> f64 {
+        if let Some(normal) = self.cached_normal.take() {
+            return normal;
+        }
+
+        let radius = (-2.0 * self.next_f64().ln()).sqrt();
+        let angle = 2.0 * std::f64::consts::PI * self.next_f64();
+
+        self.cached_normal = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+}
@@ -0,0 +1,53 @@
+// Temperature dependence of the maximum photosynthetic rate. The pmb of the inputs is taken as the
+// rate at the reference temperature and is scaled to the sea surface temperature before the
+// production profiles are computed. Without an sst pmb is used as given.
+
+use crate::{ModelInputs, TemperatureDependence};
+
+// exponent of the Eppley (1972) curve, a factor of 1.066 per degree C
+pub const EPPLEY_RATE: f64 = 0.0633;
+
+pub fn adjust_pmb(pmb: f64, sst: f64, temperature_dependence: TemperatureDependence) -> f64 {
+    match temperature_dependence {
+        TemperatureDependence::None => pmb,
+        TemperatureDependence::Eppley { rate, reference_temperature } => {
+            pmb * (rate * (sst - reference_temperature)).exp()
+        },
+        TemperatureDependence::Q10 { q10, reference_temperature } => {
+            pmb * q10.powf((sst - reference_temperature) / 10.0)
+        }
+    }
+}
+
+// the inputs with pmb at their sea surface temperature, None when there is nothing to adjust
+pub fn temperature_adjusted_inputs(input: &ModelInputs, temperature_dependence: TemperatureDependence) -> Option<ModelInputs> {
+    match (input.sst, temperature_dependence) {
+        (_, TemperatureDependence::None) | (None, _) => None,
+        (Some(sst), _) => Some(
+            ModelInputs {
+                pmb: adjust_pmb(input.pmb, sst, temperature_dependence),
+                ..input.clone()
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_adjust_pmb() {
+        let eppley = TemperatureDependence::Eppley { rate: EPPLEY_RATE, reference_temperature: 20.0 };
+        assert_eq!(adjust_pmb(3.0, 20.0, eppley), 3.0);
+        assert!((adjust_pmb(3.0, 21.0, eppley) / 3.0 - 1.0653).abs() < 1e-4);
+
+        // a Q10 of 2 doubles pmb for every 10 degrees
+        let q10 = TemperatureDependence::Q10 { q10: 2.0, reference_temperature: 15.0 };
+        assert!((adjust_pmb(3.0, 25.0, q10) - 6.0).abs() < 1e-12);
+        assert!((adjust_pmb(3.0, 5.0, q10) - 1.5).abs() < 1e-12);
+
+        assert_eq!(adjust_pmb(3.0, 30.0, TemperatureDependence::None), 3.0);
+    }
+}
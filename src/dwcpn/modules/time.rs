@@ -1,4 +1,112 @@
 use crate::dwcpn::modules::config::TIMESTEPS;
+use crate::ModelInputs;
+
+// length of the year assumed when only a bare day of year (iday) is supplied
+pub const DEFAULT_YEAR_LENGTH: f64 = 365.0;
+
+// A calendar date, used in place of a bare day of year so that leap years are accounted for. Only
+// dates in the calendar can be constructed, through new or parse
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct CalendarDate {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl CalendarDate {
+    // None if the date is not in the calendar
+    pub fn new(year: i32, month: u8, day: u8) -> Option<CalendarDate> {
+        if month == 0 || month > 12 || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(CalendarDate { year, month, day })
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    pub fn is_leap_year(&self) -> bool {
+        is_leap_year(self.year)
+    }
+
+    pub fn days_in_year(&self) -> u16 {
+        if self.is_leap_year() { 366 } else { 365 }
+    }
+
+    pub fn days_in_month(&self) -> u8 {
+        days_in_month(self.year, self.month)
+    }
+
+    // day of year starting at 1 for the 1st of January
+    pub fn day_of_year(&self) -> u16 {
+        let mut day_of_year = self.day as u16;
+        for month in 1..self.month {
+            day_of_year += days_in_month(self.year, month) as u16;
+        }
+        day_of_year
+    }
+
+    pub fn next_day(&self) -> CalendarDate {
+        match (self.day < self.days_in_month(), self.month < 12) {
+            (true, _) => CalendarDate { day: self.day + 1, ..*self },
+            (false, true) => CalendarDate { year: self.year, month: self.month + 1, day: 1 },
+            (false, false) => CalendarDate { year: self.year + 1, month: 1, day: 1 },
+        }
+    }
+
+    // a yyyy-mm-dd date, None if it is malformed or not in the calendar
+    pub fn parse(text: &str) -> Option<CalendarDate> {
+        let mut parts = text.split('-');
+        let year = parts.next()?.parse::<i32>().ok()?;
+        let month = parts.next()?.parse::<u8>().ok()?;
+        let day = parts.next()?.parse::<u8>().ok()?;
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        CalendarDate::new(year, month, day)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DaylightRegime {
+    Normal,
+    // 24 hours of daylight (midnight sun)
+    PolarDay,
+    // 24 hours of darkness
+    PolarNight,
+}
+
+// returns the day of year and the length of that year, taken from the calendar date
+// if one has been given and falling back to iday in a 365 day year otherwise
+pub fn resolve_day_of_year(inputs: &ModelInputs) -> (u16, f64) {
+    match inputs.date {
+        Some(date) => (date.day_of_year(), date.days_in_year() as f64),
+        None => (inputs.iday, DEFAULT_YEAR_LENGTH),
+    }
+}
 
 pub fn generate_time_array(sunrise: f64) -> ([f64; TIMESTEPS], f64) {
     let mut time_array: [f64; TIMESTEPS] = [0.0; TIMESTEPS];
@@ -28,20 +136,27 @@ pub fn generate_time_array(sunrise: f64) -> ([f64; TIMESTEPS], f64) {
 
 
 
-pub fn compute_sunrise(jday: u16, lat: f64) -> (f64, f64, f64) {
-
-    // convert julian day to double for the equation
-    let dayfloat = jday as f64;
-
-    let tau: f64 = std::f64::consts::PI * 2.00f64;
-    let theta: f64 = tau * dayfloat / 365.0f64;
+// fractional year (radians) used by the declination and equation of time series
+fn compute_fractional_year(jday: u16, year_length: f64) -> f64 {
+    std::f64::consts::PI * 2.0 * jday as f64 / year_length
+}
 
-    let delta: f64 = 0.006918 - 0.399912 * theta.cos()
+// solar declination (radians) for the fractional year theta, Spencer (1971)
+pub fn compute_declination(theta: f64) -> f64 {
+    0.006918 - 0.399912 * theta.cos()
         + 0.070257 * theta.sin()
         - 0.006758 * (2.0 * theta).cos()
         + 0.000907 * (2.0 * theta).sin()
         - 0.002697 * (3.0 * theta).cos()
-        + 0.001480 * (3.0 * theta).sin();
+        + 0.001480 * (3.0 * theta).sin()
+}
+
+pub fn compute_sunrise(jday: u16, year_length: f64, lat: f64) -> (f64, f64, f64) {
+
+    let tau: f64 = std::f64::consts::PI * 2.00f64;
+    let theta: f64 = compute_fractional_year(jday, year_length);
+
+    let delta: f64 = compute_declination(theta);
 
     let phi: f64 = lat * (tau / 360.0);
 
@@ -58,3 +173,96 @@ pub fn compute_sunrise(jday: u16, lat: f64) -> (f64, f64, f64) {
 
     return (sunrise, delta, phi);
 }
+
+// the sun never sets when -tan(phi)tan(delta) < -1 and never rises when it is > 1
+pub fn compute_daylight_regime(delta: f64, phi: f64) -> DaylightRegime {
+    let phidel: f64 = -phi.tan() * delta.tan();
+
+    if phidel < -1.0 {
+        DaylightRegime::PolarDay
+    } else if phidel > 1.0 {
+        DaylightRegime::PolarNight
+    } else {
+        DaylightRegime::Normal
+    }
+}
+
+// equation of time in hours (Spencer 1971), i.e. apparent minus mean solar time
+pub fn compute_equation_of_time(jday: u16, year_length: f64) -> f64 {
+    compute_equation_of_time_at(compute_fractional_year(jday, year_length))
+}
+
+// equation of time in hours for the fractional year theta
+pub fn compute_equation_of_time_at(theta: f64) -> f64 {
+    let minutes = 229.18
+        * (0.000075 + 0.001868 * theta.cos()
+            - 0.032077 * theta.sin()
+            - 0.014615 * (2.0 * theta).cos()
+            - 0.040849 * (2.0 * theta).sin());
+
+    minutes / 60.0
+}
+
+// number of hours to add to clock time (UTC plus utc_offset hours) to obtain local solar time
+// at the given longitude (degrees east)
+pub fn compute_solar_time_offset(jday: u16, year_length: f64, lon: f64, utc_offset: f64) -> f64 {
+    lon / 15.0 - utc_offset + compute_equation_of_time(jday, year_length)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn date(year: i32, month: u8, day: u8) -> CalendarDate {
+        CalendarDate::new(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_day_of_year() {
+        assert_eq!(date(2021, 1, 1).day_of_year(), 1);
+        assert_eq!(date(2021, 3, 1).day_of_year(), 60);
+        assert_eq!(date(2020, 3, 1).day_of_year(), 61);
+        assert_eq!(date(2020, 12, 31).day_of_year(), 366);
+    }
+
+    #[test]
+    fn test_next_day() {
+        assert_eq!(date(2020, 2, 28).next_day(), date(2020, 2, 29));
+        assert_eq!(date(2021, 2, 28).next_day(), date(2021, 3, 1));
+        assert_eq!(date(2021, 12, 31).next_day(), date(2022, 1, 1));
+        assert_eq!(CalendarDate::parse("2021-02-29"), None);
+        assert_eq!(CalendarDate::parse("2020-02-29"), CalendarDate::new(2020, 2, 29));
+    }
+
+    #[test]
+    fn test_leap_years() {
+        assert!(date(2000, 1, 1).is_leap_year());
+        assert!(date(2024, 1, 1).is_leap_year());
+        assert!(!date(1900, 1, 1).is_leap_year());
+        assert!(!date(2023, 1, 1).is_leap_year());
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        assert_eq!(CalendarDate::new(2021, 13, 40), None);
+        assert_eq!(CalendarDate::new(2021, 0, 1), None);
+        assert_eq!(CalendarDate::new(2021, 4, 31), None);
+        assert_eq!(CalendarDate::new(2021, 2, 29), None);
+        assert!(CalendarDate::new(2020, 2, 29).is_some());
+    }
+
+    #[test]
+    fn test_equation_of_time() {
+        // early November is the annual maximum at roughly +16.4 minutes,
+        // mid February the minimum at roughly -14.2 minutes
+        let november = date(2021, 11, 3);
+        let february = date(2021, 2, 11);
+
+        let eot_nov = compute_equation_of_time(november.day_of_year(), 365.0) * 60.0;
+        let eot_feb = compute_equation_of_time(february.day_of_year(), 365.0) * 60.0;
+
+        assert!((eot_nov - 16.4).abs() < 0.5);
+        assert!((eot_feb + 14.2).abs() < 0.5);
+    }
+}
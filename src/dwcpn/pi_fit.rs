@@ -0,0 +1,291 @@
+// Estimation of the photosynthesis-irradiance parameters alpha_b and pmb from P-I incubation
+// experiments (e.g. photosynthetron runs), by non-linear least squares fitting of the same P-I
+// curve used by the model in compute_pp_depth_profile.
+//
+// The fitted alpha_b is in units of production per unit of the irradiance supplied. The model
+// expects alpha_b in mgC mgChl^-1 h^-1 (W m^-2)^-1 (see calc_i_z_decay), so irradiance should be
+// given as PAR in W m^-2 and production as mgC mgChl^-1 h^-1 for the fit to be used directly.
+
+use crate::dwcpn::modules::pp_profile::compute_pi_response;
+use crate::ModelInputs;
+
+const MAX_ITERATIONS: usize = 200;
+const CONVERGENCE_TOLERANCE: f64 = 1e-10;
+
+#[derive(Debug, PartialEq)]
+pub enum PiFitError {
+    MismatchedLengths,
+    // at least three observations are needed to fit two parameters and estimate their errors
+    TooFewObservations,
+    InvalidObservations,
+    NotConverged,
+}
+
+pub struct PiFit {
+    pub alpha_b: f64,
+    pub pmb: f64,
+    pub alpha_b_std_error: f64,
+    pub pmb_std_error: f64,
+    // light saturation parameter pmb / alpha_b
+    pub ik: f64,
+    // None when the observations are all the same, leaving no variance to explain
+    pub r_squared: Option<f64>,
+    pub rmse: f64,
+    pub observations: usize,
+    pub iterations: usize,
+    // the damping grew until no step reduced the misfit before the convergence tolerance was met,
+    // as when the observations do not saturate and pmb is poorly determined, so the parameters
+    // may not be at a minimum
+    pub stalled: bool,
+}
+
+impl PiFit {
+    pub fn apply_to(&self, inputs: &mut ModelInputs) {
+        inputs.alpha_b = self.alpha_b;
+        inputs.pmb = self.pmb;
+    }
+}
+
+// residuals and jacobian of the P-I curve for the current parameters
+fn evaluate(irradiance: &[f64], production: &[f64], alpha_b: f64, pmb: f64) -> (Vec<f64>, Vec<[f64; 2]>) {
+    let mut residuals: Vec<f64> = Vec::with_capacity(irradiance.len());
+    let mut jacobian: Vec<[f64; 2]> = Vec::with_capacity(irradiance.len());
+
+    for (i, p) in irradiance.iter().zip(production.iter()) {
+        let x = alpha_b * i / pmb;
+        let e = (-x).exp();
+
+        residuals.push(p - compute_pi_response(alpha_b * i, pmb));
+        jacobian.push([i * e, 1.0 - e - x * e]);
+    }
+
+    (residuals, jacobian)
+}
+
+fn sum_of_squares(values: &[f64]) -> f64 {
+    values.iter().map(|v| v * v).sum()
+}
+
+// J^T J and J^T r for the two parameter problem
+fn normal_equations(residuals: &[f64], jacobian: &[[f64; 2]]) -> ([[f64; 2]; 2], [f64; 2]) {
+    let mut jtj = [[0.0; 2]; 2];
+    let mut jtr = [0.0; 2];
+
+    for (r, j) in residuals.iter().zip(jacobian.iter()) {
+        for a in 0..2 {
+            jtr[a] += j[a] * r;
+            for b in 0..2 {
+                jtj[a][b] += j[a] * j[b];
+            }
+        }
+    }
+
+    (jtj, jtr)
+}
+
+fn invert(matrix: [[f64; 2]; 2]) -> Option<[[f64; 2]; 2]> {
+    let determinant = matrix[0][0] * matrix[1][1] - matrix[0][1] * matrix[1][0];
+
+    if determinant.abs() < f64::MIN_POSITIVE {
+        return None;
+    }
+
+    Some([
+        [matrix[1][1] / determinant, -matrix[0][1] / determinant],
+        [-matrix[1][0] / determinant, matrix[0][0] / determinant],
+    ])
+}
+
+// starting guesses: pmb from the highest observed rate and alpha_b from a regression through
+// the origin of the lower half of the irradiance range
+fn initial_guess(irradiance: &[f64], production: &[f64]) -> (f64, f64) {
+    let pmb = production.iter().cloned().fold(f64::MIN, f64::max);
+
+    let mut sorted_irradiance = irradiance.to_vec();
+    sorted_irradiance.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted_irradiance[sorted_irradiance.len() / 2];
+
+    let (mut sum_ip, mut sum_ii) = (0.0, 0.0);
+    for (i, p) in irradiance.iter().zip(production.iter()) {
+        if *i <= median {
+            sum_ip += i * p;
+            sum_ii += i * i;
+        }
+    }
+
+    let alpha_b = if sum_ii > 0.0 && sum_ip > 0.0 { sum_ip / sum_ii } else { pmb / median };
+
+    (alpha_b, pmb)
+}
+
+// fit P = pmb * (1 - exp(-alpha_b * I / pmb)) to (irradiance, chlorophyll normalised production)
+// pairs with the Levenberg-Marquardt method
+pub fn fit_pi_curve(irradiance: &[f64], production: &[f64]) -> Result<PiFit, PiFitError> {
+    if irradiance.len() != production.len() {
+        return Err(PiFitError::MismatchedLengths);
+    }
+    if irradiance.len() < 3 {
+        return Err(PiFitError::TooFewObservations);
+    }
+    if irradiance.iter().chain(production.iter()).any(|v| !v.is_finite())
+        || irradiance.iter().any(|i| *i < 0.0)
+        || production.iter().all(|p| *p <= 0.0) {
+        return Err(PiFitError::InvalidObservations);
+    }
+
+    let (mut alpha_b, mut pmb) = initial_guess(irradiance, production);
+    let (mut residuals, mut jacobian) = evaluate(irradiance, production, alpha_b, pmb);
+    let mut ssr = sum_of_squares(&residuals);
+    let mut lambda: f64 = 1e-3;
+    let mut iterations: usize = 0;
+    let mut converged = false;
+    let mut stalled = false;
+
+    while iterations < MAX_ITERATIONS {
+        iterations += 1;
+
+        let (jtj, jtr) = normal_equations(&residuals, &jacobian);
+
+        // damp the diagonal and solve for the step
+        let mut damped = jtj;
+        damped[0][0] *= 1.0 + lambda;
+        damped[1][1] *= 1.0 + lambda;
+
+        let inverse = invert(damped).ok_or(PiFitError::NotConverged)?;
+        let step = [
+            inverse[0][0] * jtr[0] + inverse[0][1] * jtr[1],
+            inverse[1][0] * jtr[0] + inverse[1][1] * jtr[1],
+        ];
+
+        let (trial_alpha_b, trial_pmb) = (alpha_b + step[0], pmb + step[1]);
+
+        if trial_alpha_b <= 0.0 || trial_pmb <= 0.0 {
+            lambda *= 10.0;
+            continue;
+        }
+
+        let (trial_residuals, trial_jacobian) = evaluate(irradiance, production, trial_alpha_b, trial_pmb);
+        let trial_ssr = sum_of_squares(&trial_residuals);
+
+        if trial_ssr <= ssr {
+            let improvement = ssr - trial_ssr;
+
+            alpha_b = trial_alpha_b;
+            pmb = trial_pmb;
+            residuals = trial_residuals;
+            jacobian = trial_jacobian;
+            ssr = trial_ssr;
+            lambda = (lambda / 10.0).max(1e-12);
+
+            if improvement <= CONVERGENCE_TOLERANCE * ssr.max(f64::MIN_POSITIVE)
+                && step[0].abs() <= CONVERGENCE_TOLERANCE.sqrt() * alpha_b
+                && step[1].abs() <= CONVERGENCE_TOLERANCE.sqrt() * pmb {
+                converged = true;
+                break;
+            }
+        } else {
+            lambda *= 10.0;
+        }
+
+        if lambda > 1e12 {
+            stalled = true;
+            break;
+        }
+    }
+
+    if !converged && !stalled {
+        return Err(PiFitError::NotConverged);
+    }
+
+    // standard errors from the covariance matrix s^2 (J^T J)^-1
+    let n = irradiance.len() as f64;
+    let (jtj, _) = normal_equations(&residuals, &jacobian);
+    let covariance = invert(jtj).ok_or(PiFitError::NotConverged)?;
+    let residual_variance = ssr / (n - 2.0);
+
+    let mean_production = production.iter().sum::<f64>() / n;
+    let total_sum_of_squares: f64 = production.iter().map(|p| (p - mean_production).powi(2)).sum();
+
+    Ok(
+        PiFit {
+            alpha_b,
+            pmb,
+            alpha_b_std_error: (residual_variance * covariance[0][0]).sqrt(),
+            pmb_std_error: (residual_variance * covariance[1][1]).sqrt(),
+            ik: pmb / alpha_b,
+            r_squared: (total_sum_of_squares > 0.0).then(|| 1.0 - ssr / total_sum_of_squares),
+            rmse: (ssr / n).sqrt(),
+            observations: irradiance.len(),
+            iterations,
+            stalled
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const IRRADIANCE: [f64; 12] = [0.0, 5.0, 10.0, 20.0, 35.0, 50.0, 75.0, 100.0, 150.0, 200.0, 300.0, 450.0];
+
+    #[test]
+    fn test_exact_recovery() {
+        let production: Vec<f64> = IRRADIANCE.iter().map(|i| compute_pi_response(0.08 * i, 4.5)).collect();
+
+        let fit = fit_pi_curve(&IRRADIANCE, &production).unwrap();
+
+        assert!((fit.alpha_b - 0.08).abs() < 1e-6);
+        assert!((fit.pmb - 4.5).abs() < 1e-6);
+        assert!((fit.r_squared.unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_noisy_fit() {
+        // fixed +/- 5% perturbations so that the test is deterministic
+        let noise = [1.0, 1.05, 0.95, 1.03, 0.97, 1.04, 0.96, 1.02, 0.98, 1.05, 0.95, 1.0];
+        let production: Vec<f64> = IRRADIANCE.iter().zip(noise.iter())
+            .map(|(i, n)| compute_pi_response(0.05 * i, 3.0) * n)
+            .collect();
+
+        let fit = fit_pi_curve(&IRRADIANCE, &production).unwrap();
+
+        assert!((fit.alpha_b - 0.05).abs() < 3.0 * fit.alpha_b_std_error + 0.005);
+        assert!((fit.pmb - 3.0).abs() < 3.0 * fit.pmb_std_error + 0.1);
+        assert!(fit.alpha_b_std_error > 0.0 && fit.pmb_std_error > 0.0);
+        assert!(fit.r_squared.unwrap() > 0.95);
+    }
+
+    #[test]
+    fn test_unsaturated_observations_stall() {
+        // production that keeps rising with light leaves pmb free to grow without bound
+        let production: Vec<f64> = IRRADIANCE.iter().map(|i| 0.01 * i).collect();
+
+        let fit = fit_pi_curve(&IRRADIANCE, &production).unwrap();
+
+        assert!(fit.stalled);
+        assert!((fit.alpha_b - 0.01).abs() < 1e-6);
+        assert!(fit.ik > 10.0 * IRRADIANCE[IRRADIANCE.len() - 1]);
+
+        let exact: Vec<f64> = IRRADIANCE.iter().map(|i| compute_pi_response(0.08 * i, 4.5)).collect();
+        assert!(!fit_pi_curve(&IRRADIANCE, &exact).unwrap().stalled);
+    }
+
+    #[test]
+    fn test_constant_observations() {
+        // production saturated at every irradiance
+        let irradiance = &IRRADIANCE[6..];
+        let production = vec![2.0; irradiance.len()];
+
+        let fit = fit_pi_curve(irradiance, &production).unwrap();
+
+        assert!((fit.pmb - 2.0).abs() < 1e-3);
+        assert_eq!(fit.r_squared, None);
+    }
+
+    #[test]
+    fn test_too_few_observations() {
+        assert_eq!(fit_pi_curve(&[10.0, 20.0], &[1.0, 2.0]).err(), Some(PiFitError::TooFewObservations));
+        assert_eq!(fit_pi_curve(&[10.0, 20.0, 30.0], &[1.0, 2.0]).err(), Some(PiFitError::MismatchedLengths));
+    }
+}
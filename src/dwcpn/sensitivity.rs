@@ -0,0 +1,178 @@
+use crate::dwcpn::dwcpn::calc_production;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::{ModelInputs, ModelSettings, PPErrors};
+
+pub struct SensitivitySettings {
+    pub parameters: Vec<ModelParameter>,
+    // finite difference step as a fraction of the parameter value. The model is discretised in
+    // depth and time, so steps much below 1e-3 pick up numerical noise rather than the response
+    pub relative_step: f64,
+    // step used instead when the parameter value is zero (e.g. cloud)
+    pub zero_step: f64,
+}
+
+pub struct ParameterSensitivity {
+    pub parameter: ModelParameter,
+    pub value: f64,
+    // partial derivative of pp_day with respect to the parameter
+    pub derivative: f64,
+    // normalised sensitivity (value / pp_day) * derivative, i.e. the % change in pp_day
+    // for a 1% change in the parameter
+    pub elasticity: f64,
+}
+
+pub struct SensitivityOutputs {
+    pub pp_day: f64,
+    pub sensitivities: Vec<ParameterSensitivity>,
+}
+
+// runs without a daily production (e.g. iom_only) have nothing to differentiate
+fn run_pp_day(input: &ModelInputs, settings: &ModelSettings) -> Result<f64, PPErrors> {
+    calc_production(input, settings)?.pp_day.ok_or(PPErrors::DWCPNError)
+}
+
+// finite difference sensitivity of pp_day to each requested input. Central differences are used
+// where both perturbed values are physically valid and one sided differences otherwise. Inputs
+// left to the model (par and sst when None) have no value to perturb and are left out.
+pub fn calc_sensitivity(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    sensitivity_settings: &SensitivitySettings
+) -> Result<SensitivityOutputs, PPErrors> {
+    let pp_day = run_pp_day(input, settings)?;
+
+    let mut sensitivities: Vec<ParameterSensitivity> = Vec::with_capacity(sensitivity_settings.parameters.len());
+
+    for parameter in sensitivity_settings.parameters.iter() {
+        let value = parameter.get(input);
+        if value.is_nan() {
+            continue;
+        }
+
+        let step = if value == 0.0 {
+            sensitivity_settings.zero_step
+        } else {
+            sensitivity_settings.relative_step * value.abs()
+        };
+
+        let (lower, upper) = (value - step, value + step);
+        let lower_valid = parameter.is_valid(lower);
+        let upper_valid = parameter.is_valid(upper);
+
+        let derivative = if lower_valid && upper_valid {
+            let pp_upper = run_perturbed(input, settings, parameter, upper)?;
+            let pp_lower = run_perturbed(input, settings, parameter, lower)?;
+            (pp_upper - pp_lower) / (2.0 * step)
+        } else if upper_valid {
+            (run_perturbed(input, settings, parameter, upper)? - pp_day) / step
+        } else if lower_valid {
+            (pp_day - run_perturbed(input, settings, parameter, lower)?) / step
+        } else {
+            return Err(PPErrors::DWCPNError);
+        };
+
+        let elasticity = if pp_day != 0.0 { derivative * value / pp_day } else { 0.0 };
+
+        sensitivities.push(
+            ParameterSensitivity {
+                parameter: *parameter,
+                value,
+                derivative,
+                elasticity
+            }
+        );
+    }
+
+    Ok(SensitivityOutputs { pp_day, sensitivities })
+}
+
+fn run_perturbed(
+    input: &ModelInputs,
+    settings: &ModelSettings,
+    parameter: &ModelParameter,
+    value: f64
+) -> Result<f64, PPErrors> {
+    let mut perturbed = input.clone();
+    parameter.set(&mut perturbed, value);
+    run_pp_day(&perturbed, settings)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_fixtures::model_inputs;
+
+    #[test]
+    fn test_sensitivity_signs() {
+        let inputs = ModelInputs {
+            lat: -5.792,
+            lon: -96.62,
+            z_bottom: 100.0,
+            iday: 1,
+            alpha_b: 0.0844,
+            pmb: 4.756,
+            z_m: 46.1,
+            mld: 19.35091019,
+            chl: 0.26096588,
+            rho: 0.878,
+            sigma: 34.6,
+            par: Some(49.1697464),
+            ..model_inputs()
+        };
+
+        let settings = ModelSettings::default();
+
+        let sensitivity_settings = SensitivitySettings {
+            parameters: vec![ModelParameter::AlphaB, ModelParameter::Pmb, ModelParameter::Par, ModelParameter::Cloud],
+            relative_step: 0.01,
+            zero_step: 1.0
+        };
+
+        let outputs = calc_sensitivity(&inputs, &settings, &sensitivity_settings).unwrap();
+
+        // production saturates with light, so each of alpha_b, pmb and par has an elasticity
+        // between zero and one
+        for sensitivity in outputs.sensitivities.iter().take(3) {
+            assert!(sensitivity.elasticity > 0.0 && sensitivity.elasticity < 1.0);
+        }
+
+        // the spectral light is rescaled to the satellite PAR, which already includes the
+        // effect of cloud, so cloud only shifts light between the direct and diffuse components
+        assert!(outputs.sensitivities[3].derivative.abs() * 100.0 < 0.1 * outputs.pp_day);
+    }
+
+    #[test]
+    fn test_sensitivity_without_par_and_sst() {
+        let inputs = ModelInputs { par: None, sst: None, ..model_inputs() };
+
+        let sensitivity_settings = SensitivitySettings {
+            parameters: ModelParameter::ALL.to_vec(),
+            relative_step: 0.01,
+            zero_step: 1.0
+        };
+
+        let outputs = calc_sensitivity(&inputs, &ModelSettings::default(), &sensitivity_settings).unwrap();
+
+        let parameters: Vec<ModelParameter> = outputs.sensitivities.iter().map(|sensitivity| sensitivity.parameter).collect();
+        assert_eq!(parameters.len(), ModelParameter::ALL.len() - 2);
+        assert!(!parameters.contains(&ModelParameter::Par) && !parameters.contains(&ModelParameter::Sst));
+        assert!(outputs.sensitivities.iter().all(|sensitivity| sensitivity.elasticity.is_finite()));
+
+        // cloud now reduces the clear sky PAR the model runs on
+        let cloud = outputs.sensitivities.iter().find(|sensitivity| sensitivity.parameter == ModelParameter::Cloud).unwrap();
+        assert!(cloud.derivative < 0.0);
+    }
+
+    #[test]
+    fn test_sensitivity_without_production() {
+        let sensitivity_settings = SensitivitySettings {
+            parameters: vec![ModelParameter::Chl],
+            relative_step: 0.01,
+            zero_step: 1.0
+        };
+        let settings = ModelSettings { iom_only: true, ..Default::default() };
+
+        assert!(matches!(calc_sensitivity(&model_inputs(), &settings, &sensitivity_settings), Err(PPErrors::DWCPNError)));
+    }
+}
@@ -0,0 +1,276 @@
+// Solar geometry for use outside the model: declination, equation of time, hour angle, solar
+// zenith and elevation, sunrise, sunset and day length, and the times at which the sun reaches a
+// given zenith angle.
+//
+// Angles are in degrees and times in hours. Times are local solar time (12 at solar noon) unless
+// converted with SolarDay::clock_time, which takes the longitude (degrees east) and the offset of
+// the clock from UTC (hours). Sunrise and sunset are for the centre of the sun on the horizon,
+// as in the model; use times_at_zenith with APPARENT_SUNRISE_ZENITH for the sunrise and sunset of
+// almanacs and the NOAA solar calculator, which allow for refraction and the radius of the sun.
+//
+// Declination and the equation of time come from the series of Spencer (1971) in modules::time,
+// evaluated at the same fractional year as in the model (2 pi iday / year_length at solar noon),
+// so SolarDay gives the model's sunrise. Against the NOAA solar calculator for 2020 to 2025 the
+// declination is within 0.15 degrees and the equation of time within 0.75 minutes.
+
+use crate::dwcpn::modules::time::{
+    compute_declination, compute_equation_of_time_at, CalendarDate, DaylightRegime
+};
+use std::f64::consts::PI;
+
+// zenith angle of the centre of the sun at sunrise and sunset allowing for atmospheric refraction
+// (34') and the radius of the sun (16'), as used by NOAA
+pub const APPARENT_SUNRISE_ZENITH: f64 = 90.833;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolarPosition {
+    // negative in the morning, 15 degrees per hour from solar noon
+    pub hour_angle: f64,
+    pub declination: f64,
+    pub zenith: f64,
+    pub elevation: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SolarDay {
+    pub day_of_year: u16,
+    pub year_length: f64,
+    pub latitude: f64,
+    // at solar noon
+    pub declination: f64,
+    // apparent minus mean solar time (hours) at solar noon
+    pub equation_of_time: f64,
+    pub daylight_regime: DaylightRegime,
+    // local solar time, None during polar day and night
+    pub sunrise: Option<f64>,
+    pub sunset: Option<f64>,
+    // hours with the centre of the sun above the horizon
+    pub day_length: f64,
+}
+
+// fractional year (radians) at a local solar time on a day of the year, as the model at noon
+pub fn fractional_year(day_of_year: u16, year_length: f64, solar_time: f64) -> f64 {
+    2.0 * PI * (day_of_year as f64 + (solar_time - 12.0) / 24.0) / year_length
+}
+
+pub fn hour_angle(solar_time: f64) -> f64 {
+    15.0 * (solar_time - 12.0)
+}
+
+// zenith angle for a declination and hour angle at a latitude, all in degrees
+pub fn solar_zenith(latitude: f64, declination: f64, hour_angle: f64) -> f64 {
+    let (phi, delta, h) = (latitude.to_radians(), declination.to_radians(), hour_angle.to_radians());
+    let cos_zenith = phi.sin() * delta.sin() + phi.cos() * delta.cos() * h.cos();
+
+    cos_zenith.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+// hour angle at which the sun reaches a zenith angle, None if it stays above or below it all day
+pub fn hour_angle_at_zenith(latitude: f64, declination: f64, zenith: f64) -> Option<f64> {
+    let (phi, delta) = (latitude.to_radians(), declination.to_radians());
+    let cos_h = (zenith.to_radians().cos() - phi.sin() * delta.sin()) / (phi.cos() * delta.cos());
+
+    if (-1.0..=1.0).contains(&cos_h) {
+        Some(cos_h.acos().to_degrees())
+    } else {
+        None
+    }
+}
+
+impl SolarDay {
+    pub fn new(day_of_year: u16, year_length: f64, latitude: f64) -> SolarDay {
+        let theta = fractional_year(day_of_year, year_length, 12.0);
+        let declination = compute_declination(theta).to_degrees();
+
+        let (daylight_regime, sunrise, sunset, day_length) = match hour_angle_at_zenith(latitude, declination, 90.0) {
+            Some(h) => (DaylightRegime::Normal, Some(12.0 - h / 15.0), Some(12.0 + h / 15.0), 2.0 * h / 15.0),
+            // the sun is above the horizon all day if it is at noon
+            None if solar_zenith(latitude, declination, 0.0) < 90.0 => (DaylightRegime::PolarDay, None, None, 24.0),
+            None => (DaylightRegime::PolarNight, None, None, 0.0),
+        };
+
+        SolarDay {
+            day_of_year,
+            year_length,
+            latitude,
+            declination,
+            equation_of_time: compute_equation_of_time_at(theta),
+            daylight_regime,
+            sunrise,
+            sunset,
+            day_length,
+        }
+    }
+
+    pub fn for_date(date: CalendarDate, latitude: f64) -> SolarDay {
+        SolarDay::new(date.day_of_year(), date.days_in_year() as f64, latitude)
+    }
+
+    // position of the sun at a local solar time, with the declination at that time
+    pub fn position_at(&self, solar_time: f64) -> SolarPosition {
+        let theta = fractional_year(self.day_of_year, self.year_length, solar_time);
+        let declination = compute_declination(theta).to_degrees();
+        let hour_angle = hour_angle(solar_time);
+        let zenith = solar_zenith(self.latitude, declination, hour_angle);
+
+        SolarPosition { hour_angle, declination, zenith, elevation: 90.0 - zenith }
+    }
+
+    pub fn zenith_at(&self, solar_time: f64) -> f64 {
+        self.position_at(solar_time).zenith
+    }
+
+    // morning and afternoon local solar times at which the sun is at a zenith angle, None if it
+    // stays above or below that angle all day
+    pub fn times_at_zenith(&self, zenith: f64) -> Option<(f64, f64)> {
+        hour_angle_at_zenith(self.latitude, self.declination, zenith)
+            .map(|h| (12.0 - h / 15.0, 12.0 + h / 15.0))
+    }
+
+    // hours to add to clock time to obtain local solar time, as compute_solar_time_offset
+    pub fn solar_time_offset(&self, longitude: f64, utc_offset: f64) -> f64 {
+        longitude / 15.0 - utc_offset + self.equation_of_time
+    }
+
+    pub fn clock_time(&self, solar_time: f64, longitude: f64, utc_offset: f64) -> f64 {
+        solar_time - self.solar_time_offset(longitude, utc_offset)
+    }
+
+    pub fn solar_time(&self, clock_time: f64, longitude: f64, utc_offset: f64) -> f64 {
+        clock_time + self.solar_time_offset(longitude, utc_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    struct NoaaReference {
+        date: CalendarDate,
+        latitude: f64,
+        longitude: f64,
+        utc_offset: f64,
+        // at solar noon
+        declination: f64,
+        // minutes
+        equation_of_time: f64,
+        // clock times, None during polar day or night
+        sunrise: Option<f64>,
+        sunset: Option<f64>,
+        // zenith at a clock time
+        clock_time: f64,
+        zenith: f64,
+    }
+
+    // values from the NOAA solar calculator (NOAA Global Monitoring Laboratory spreadsheet
+    // version), with sunrise and sunset at the apparent sunrise zenith
+    fn noaa_references() -> Vec<NoaaReference> {
+        vec![
+            // Plymouth, summer solstice
+            NoaaReference {
+                date: CalendarDate::new(2021, 6, 21).unwrap(), latitude: 50.366, longitude: -4.142, utc_offset: 1.0,
+                declination: 23.4370, equation_of_time: -1.8494, sunrise: Some(5.0906), sunset: Some(21.5233),
+                clock_time: 10.0, zenith: 46.7146
+            },
+            // equator, March equinox
+            NoaaReference {
+                date: CalendarDate::new(2021, 3, 20).unwrap(), latitude: 0.0, longitude: 0.0, utc_offset: 0.0,
+                declination: 0.0379, equation_of_time: -7.3739, sunrise: Some(6.0674), sunset: Some(18.1784),
+                clock_time: 9.0, zenith: 46.8527
+            },
+            // Hobart, southern summer solstice
+            NoaaReference {
+                date: CalendarDate::new(2021, 12, 21).unwrap(), latitude: -42.88, longitude: 147.33, utc_offset: 11.0,
+                declination: -23.4362, equation_of_time: 2.0334, sunrise: Some(5.4709), sunset: Some(20.8173),
+                clock_time: 15.0, zenith: 30.0891
+            },
+            // Mauritanian upwelling test pixel
+            NoaaReference {
+                date: CalendarDate::new(2021, 5, 1).unwrap(), latitude: 18.71, longitude: -18.625, utc_offset: 0.0,
+                declination: 15.2296, equation_of_time: 2.9253, sunrise: Some(6.7791), sunset: Some(19.6067),
+                clock_time: 8.5, zenith: 67.0026
+            },
+            // Tromsø, midnight sun and polar night
+            NoaaReference {
+                date: CalendarDate::new(2021, 6, 21).unwrap(), latitude: 69.65, longitude: 18.96, utc_offset: 2.0,
+                declination: 23.4371, equation_of_time: -1.8403, sunrise: None, sunset: None,
+                clock_time: 12.0, zenith: 46.7191
+            },
+            NoaaReference {
+                date: CalendarDate::new(2021, 12, 21).unwrap(), latitude: 69.65, longitude: 18.96, utc_offset: 1.0,
+                declination: -23.4375, equation_of_time: 1.8270, sunrise: None, sunset: None,
+                clock_time: 12.0, zenith: 93.1419
+            },
+            // Reykjavik, leap day
+            NoaaReference {
+                date: CalendarDate::new(2024, 2, 29).unwrap(), latitude: 64.15, longitude: -21.94, utc_offset: 0.0,
+                declination: -7.6709, equation_of_time: -12.4158, sunrise: Some(8.6125), sunset: Some(18.7267),
+                clock_time: 13.0, zenith: 72.2039
+            },
+        ]
+    }
+
+    #[test]
+    fn test_noaa_reference_values() {
+        for reference in noaa_references() {
+            let day = SolarDay::for_date(reference.date, reference.latitude);
+
+            assert!((day.declination - reference.declination).abs() < 0.15);
+            assert!((day.equation_of_time * 60.0 - reference.equation_of_time).abs() < 0.75);
+
+            let apparent = day.times_at_zenith(APPARENT_SUNRISE_ZENITH)
+                .map(|(sunrise, sunset)| (
+                    day.clock_time(sunrise, reference.longitude, reference.utc_offset),
+                    day.clock_time(sunset, reference.longitude, reference.utc_offset)
+                ));
+            match (apparent, reference.sunrise, reference.sunset) {
+                (Some((sunrise, sunset)), Some(expected_sunrise), Some(expected_sunset)) => {
+                    assert!((sunrise - expected_sunrise).abs() < 1.5 / 60.0);
+                    assert!((sunset - expected_sunset).abs() < 1.5 / 60.0);
+                },
+                (None, None, None) => {},
+                _ => panic!("sunrise mismatch for {:?}", reference.date)
+            }
+
+            let solar_time = day.solar_time(reference.clock_time, reference.longitude, reference.utc_offset);
+            assert!((day.zenith_at(solar_time) - reference.zenith).abs() < 0.15);
+        }
+    }
+
+    #[test]
+    fn test_daylight_regimes() {
+        let midnight_sun = SolarDay::for_date(CalendarDate::new(2021, 6, 21).unwrap(), 69.65);
+        assert_eq!(midnight_sun.daylight_regime, DaylightRegime::PolarDay);
+        assert_eq!(midnight_sun.day_length, 24.0);
+        assert!(midnight_sun.sunrise.is_none());
+
+        let polar_night = SolarDay::for_date(CalendarDate::new(2021, 12, 21).unwrap(), 69.65);
+        assert_eq!(polar_night.daylight_regime, DaylightRegime::PolarNight);
+        assert_eq!(polar_night.day_length, 0.0);
+
+        // the geometric sunrise and sunset are symmetric about noon and 12 hours apart at the equator
+        let equator = SolarDay::new(80, 365.0, 0.0);
+        let (sunrise, sunset) = (equator.sunrise.unwrap(), equator.sunset.unwrap());
+        assert!((sunrise + sunset - 24.0).abs() < 1e-12);
+        assert!((equator.day_length - 12.0).abs() < 1e-12);
+
+        let plymouth = SolarDay::new(172, 365.0, 50.366);
+        let (morning, afternoon) = plymouth.times_at_zenith(60.0).unwrap();
+        assert!((plymouth.zenith_at(morning) - 60.0).abs() < 0.05);
+        assert!((plymouth.zenith_at(afternoon) - 60.0).abs() < 0.05);
+
+        // the sun is on the horizon at sunrise
+        assert!((plymouth.position_at(plymouth.sunrise.unwrap()).elevation).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_model_sunrise() {
+        use crate::dwcpn::modules::time::compute_sunrise;
+
+        for (day_of_year, latitude) in [(1, 0.0), (121, 18.71), (172, 50.366), (355, -42.88)] {
+            let (sunrise, _, _) = compute_sunrise(day_of_year, 365.0, latitude);
+            assert!((SolarDay::new(day_of_year, 365.0, latitude).sunrise.unwrap() - sunrise).abs() < 1e-9);
+        }
+    }
+}
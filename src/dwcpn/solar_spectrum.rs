@@ -0,0 +1,145 @@
+// Reference extra-terrestrial solar spectra for SolarSpectrum::Reference. A published spectrum
+// (e.g. the Thuillier et al. (2003) ATLAS 3 composite) is read as two columns of wavelength (nm)
+// and irradiance at the mean Earth-Sun distance in W m^-2 um^-1 (the same as mW m^-2 nm^-1),
+// separated by whitespace or commas. Text header lines before the data and lines starting with #
+// are skipped. The spectrum is binned onto WL_ARRAY by averaging it over the DELTA_LAMBDA band
+// centred on each model wavelength, so it has to cover 397.5 to 702.5 nm.
+
+use crate::dwcpn::modules::config::{DELTA_LAMBDA, WL_ARRAY, WL_COUNT};
+use crate::dwcpn::modules::linear_interp::linear_interp;
+
+#[derive(Debug, PartialEq)]
+pub enum SpectrumError {
+    Io(String),
+    Parse { line: usize, message: String },
+    // the spectrum does not cover the band of a model wavelength
+    Coverage { wavelength: f64 },
+}
+
+pub fn read_reference_spectrum(path: &str) -> Result<[f64; WL_COUNT], SpectrumError> {
+    let text = std::fs::read_to_string(path).map_err(|e| SpectrumError::Io(e.to_string()))?;
+    let (wavelengths, irradiance) = parse_spectrum(&text)?;
+    bin_reference_spectrum(&wavelengths, &irradiance)
+}
+
+pub fn parse_spectrum(text: &str) -> Result<(Vec<f64>, Vec<f64>), SpectrumError> {
+    let mut wavelengths: Vec<f64> = Vec::new();
+    let mut irradiance: Vec<f64> = Vec::new();
+
+    let lines = text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    for (line_index, line) in lines {
+        let line_number = line_index + 1;
+        let fields: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        let values: Option<Vec<f64>> = fields.iter().take(2).map(|field| field.parse().ok()).collect();
+
+        match values {
+            Some(values) if values.len() == 2 => {
+                if wavelengths.last().is_some_and(|last| values[0] <= *last) {
+                    return Err(SpectrumError::Parse {
+                        line: line_number,
+                        message: format!("wavelength {} is not increasing", values[0])
+                    });
+                }
+                wavelengths.push(values[0]);
+                irradiance.push(values[1]);
+            },
+            // header text before the data
+            _ if wavelengths.is_empty() => continue,
+            _ => return Err(SpectrumError::Parse {
+                line: line_number,
+                message: format!("could not read wavelength and irradiance from '{}'", line.trim())
+            })
+        }
+    }
+
+    Ok((wavelengths, irradiance))
+}
+
+// mean of the linearly interpolated spectrum over the band of each model wavelength
+pub fn bin_reference_spectrum(wavelengths: &[f64], irradiance: &[f64]) -> Result<[f64; WL_COUNT], SpectrumError> {
+    let mut binned = [0.0; WL_COUNT];
+
+    for l in 0..WL_COUNT {
+        let lower = WL_ARRAY[l] - DELTA_LAMBDA / 2.0;
+        let upper = WL_ARRAY[l] + DELTA_LAMBDA / 2.0;
+
+        if wavelengths.len() < 2 || wavelengths[0] > lower || wavelengths[wavelengths.len() - 1] < upper {
+            return Err(SpectrumError::Coverage { wavelength: WL_ARRAY[l] });
+        }
+
+        // trapezoids between the band edges and the tabulated wavelengths inside the band
+        let mut points: Vec<f64> = vec![lower];
+        points.extend(wavelengths.iter().filter(|wavelength| **wavelength > lower && **wavelength < upper));
+        points.push(upper);
+
+        let mut integral = 0.0;
+        for pair in points.windows(2) {
+            let left = linear_interp(wavelengths, irradiance, pair[0]);
+            let right = linear_interp(wavelengths, irradiance, pair[1]);
+            integral += (left + right) * (pair[1] - pair[0]) / 2.0;
+        }
+        binned[l] = integral / DELTA_LAMBDA;
+    }
+
+    Ok(binned)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::dwcpn::modules::irradiance::{compute_earth_sun_distance_correction, compute_solar_correction, lookup_thekaekara_correction};
+    use crate::SolarSpectrum;
+
+    #[test]
+    fn test_earth_sun_distance_correction() {
+        // perihelion early in January and aphelion early in July
+        assert!((compute_earth_sun_distance_correction(3, 365.0) - 1.0351).abs() < 1e-4);
+        assert!((compute_earth_sun_distance_correction(185, 365.0) - 0.9666).abs() < 1e-4);
+
+        let reference = compute_solar_correction(&SolarSpectrum::Reference(Box::new([0.0; WL_COUNT])), 185, 365.0);
+        assert_eq!(reference, compute_earth_sun_distance_correction(185, 365.0));
+    }
+
+    #[test]
+    fn test_legacy_correction_unchanged() {
+        // the original model's lookup, extrapolated from the last two table points after day 23
+        assert_eq!(lookup_thekaekara_correction(1), 1399.0 - (1399.0 - 1399.0) * (1.0 / 3.0));
+        assert_eq!(lookup_thekaekara_correction(121), 1398.0 - (1398.0 - 1399.0) * ((121.0 - 355.0) / 10.0));
+        assert_eq!(
+            compute_solar_correction(&SolarSpectrum::Legacy, 121, 365.0),
+            lookup_thekaekara_correction(121) / 1353.0
+        );
+    }
+
+    #[test]
+    fn test_bin_reference_spectrum() {
+        // a linear spectrum bins to its value at each model wavelength, whatever the sampling
+        let text = "wavelength (nm)  irradiance (mW m-2 nm-1)\n# comment\n"
+            .to_string()
+            + &(0..=321).map(|i| {
+                let wavelength = 390.0 + i as f64 * 1.0;
+                format!("{:.1}, {:.6}\n", wavelength, 2000.0 - wavelength)
+            }).collect::<String>();
+
+        let (wavelengths, irradiance) = parse_spectrum(&text).unwrap();
+        assert_eq!(wavelengths.len(), 322);
+
+        let binned = bin_reference_spectrum(&wavelengths, &irradiance).unwrap();
+        for l in 0..WL_COUNT {
+            assert!((binned[l] - (2000.0 - WL_ARRAY[l])).abs() < 1e-9);
+        }
+
+        // a spectrum that stops short of the last band
+        assert_eq!(
+            bin_reference_spectrum(&wavelengths[..311], &irradiance[..311]),
+            Err(SpectrumError::Coverage { wavelength: 700.0 })
+        );
+        assert!(matches!(parse_spectrum("400 1.0\n399 1.0\n"), Err(SpectrumError::Parse { line: 2, .. })));
+    }
+}
@@ -0,0 +1,392 @@
+// Runs of the model through a daily time series at a fixed station (e.g. BATS, HOT or L4), with
+// monthly and annual totals of production.
+//
+// Station series are read from a comma separated file with a header row, a date column
+// (yyyy-mm-dd) and a column for every scalar field of ModelInputs as for validation match-ups.
+// Rows are in date order; days without a row, and blank or nan values, are missing. Missing
+// values are gap filled as configured, and a day that still has missing values is not run, except
+// that a missing par is estimated from the clear sky irradiance by the model, and a missing sst
+// leaves pmb as given.
+
+use crate::dwcpn::dwcpn::calc_production;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
+use crate::dwcpn::modules::time::CalendarDate;
+use crate::{ModelInputs, ModelSettings, PPErrors};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GapFilling {
+    // days with a missing value are not run
+    None,
+    // the last valid value is carried forward for up to max_days days
+    Persistence { max_days: usize },
+    // linear in time between the valid values either side of gaps of up to max_days days
+    Linear { max_days: usize },
+}
+
+pub struct StationSeries {
+    pub start: CalendarDate,
+    // inputs that do not vary (e.g. the water spectra), the daily values are set on a copy for each day
+    pub station: ModelInputs,
+    // one value per day from start for each parameter that varies, NaN for a missing day
+    pub daily: Vec<(ModelParameter, Vec<f64>)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StationError {
+    Io(String),
+    MissingColumn(String),
+    Parse { line: usize, message: String },
+    // the daily values of a parameter do not cover the same days as the others
+    LengthMismatch(ModelParameter),
+}
+
+pub struct StationDay {
+    pub date: CalendarDate,
+    // None if the day was not run or the model failed
+    pub pp_day: Option<f64>,
+    pub euphotic_depth: Option<f64>,
+    pub par: Option<f64>,
+    // why the model failed on a day that was run
+    pub error: Option<PPErrors>,
+    // parameters with a gap filled value on this day
+    pub filled: Vec<ModelParameter>,
+    // parameters still missing after gap filling, the day is not run if there are any
+    pub missing: Vec<ModelParameter>,
+}
+
+pub struct PeriodTotal {
+    pub year: i32,
+    // None for an annual total
+    pub month: Option<u8>,
+    // days of the period in the series, and of those the days with production
+    pub days: usize,
+    pub modelled_days: usize,
+    // production over the modelled days (mgC m^-2)
+    pub pp_total: f64,
+    // mean daily production times the days in the calendar month or year, NaN with no modelled days
+    pub pp_estimated_total: f64,
+}
+
+pub struct StationRun {
+    pub daily: Vec<StationDay>,
+    pub monthly: Vec<PeriodTotal>,
+    pub annual: Vec<PeriodTotal>,
+}
+
+impl StationSeries {
+    pub fn len(&self) -> usize {
+        self.daily.first().map_or(0, |(_, values)| values.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn dates(&self) -> Vec<CalendarDate> {
+        let mut dates: Vec<CalendarDate> = Vec::with_capacity(self.len());
+        let mut date = self.start;
+        for _ in 0..self.len() {
+            dates.push(date);
+            date = date.next_day();
+        }
+        dates
+    }
+}
+
+pub fn read_station_series(path: &str) -> Result<StationSeries, StationError> {
+    let text = std::fs::read_to_string(path).map_err(|e| StationError::Io(e.to_string()))?;
+    parse_station_series(&text)
+}
+
+pub fn parse_station_series(text: &str) -> Result<StationSeries, StationError> {
+    let mut lines = text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => line.split(',').map(|column| column.trim().to_string()).collect(),
+        None => return Err(StationError::MissingColumn("date".to_string()))
+    };
+
+    let column = |name: &str| header.iter().position(|column| column == name)
+        .ok_or(StationError::MissingColumn(name.to_string()));
+
+    let date_column = column("date")?;
+    let parameter_columns: Vec<(ModelParameter, usize)> = ModelParameter::ALL.iter()
+        .filter(|parameter| !parameter.is_optional() || column(parameter.name()).is_ok())
+        .map(|parameter| column(parameter.name()).map(|index| (*parameter, index)))
+        .collect::<Result<_, _>>()?;
+
+    let mut start: Option<CalendarDate> = None;
+    let mut next_date: Option<CalendarDate> = None;
+    let mut daily: Vec<(ModelParameter, Vec<f64>)> = parameter_columns.iter()
+        .map(|(parameter, _)| (*parameter, Vec::new()))
+        .collect();
+
+    for (line_index, line) in lines {
+        let line_number = line_index + 1;
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        let parse_error = |message: String| StationError::Parse { line: line_number, message };
+
+        let date_field = fields.get(date_column).copied().unwrap_or("");
+        let date = CalendarDate::parse(date_field)
+            .ok_or_else(|| parse_error(format!("could not read date '{}'", date_field)))?;
+
+        // days between the previous row and this one are missing
+        if let Some(mut expected) = next_date {
+            if date < expected {
+                return Err(parse_error(format!("date {} is not after the previous row", date_field)));
+            }
+            while expected != date {
+                for (_, values) in daily.iter_mut() {
+                    values.push(f64::NAN);
+                }
+                expected = expected.next_day();
+            }
+        }
+        start.get_or_insert(date);
+        next_date = Some(date.next_day());
+
+        for ((parameter, index), (_, values)) in parameter_columns.iter().zip(daily.iter_mut()) {
+            let value = match fields.get(*index).copied().unwrap_or("") {
+                "" => f64::NAN,
+                field => field.parse::<f64>()
+                    .map_err(|_| parse_error(format!("could not read {} value '{}'", parameter.name(), field)))?
+            };
+            values.push(value);
+        }
+    }
+
+    Ok(
+        StationSeries {
+            start: start.ok_or(StationError::MissingColumn("date".to_string()))?,
+            station: ModelInputs {
+                lat: 0.0,
+                lon: 0.0,
+                z_bottom: 0.0,
+                iday: 1,
+                date: None,
+                utc_offset: None,
+                alpha_b: 0.0,
+                pmb: 0.0,
+                z_m: 0.0,
+                mld: 0.0,
+                chl: 0.0,
+                rho: 0.0,
+                sigma: 0.0,
+                cloud: 0.0,
+                yel_sub: 0.0,
+                par: None,
+                par_series: None,
+                sst: None,
+                bw: calculate_bw(),
+                bbr: calculate_bbr(),
+                ay: calculate_ay()
+            },
+            daily
+        }
+    )
+}
+
+// fill the NaN values of a daily series, values that cannot be filled are left as NaN
+pub fn fill_gaps(values: &[f64], gap_filling: GapFilling) -> Vec<f64> {
+    let mut filled = values.to_vec();
+    let valid: Vec<usize> = (0..values.len()).filter(|i| !values[*i].is_nan()).collect();
+
+    match gap_filling {
+        GapFilling::None => {},
+        GapFilling::Persistence { max_days } => {
+            let mut last: Option<usize> = None;
+            for (i, value) in filled.iter_mut().enumerate() {
+                if !values[i].is_nan() {
+                    last = Some(i);
+                } else if let Some(last) = last.filter(|last| i - last <= max_days) {
+                    *value = values[last];
+                }
+            }
+        },
+        GapFilling::Linear { max_days } => {
+            for pair in valid.windows(2) {
+                let (before, after) = (pair[0], pair[1]);
+                if after - before - 1 > max_days {
+                    continue;
+                }
+                for (i, value) in filled.iter_mut().enumerate().take(after).skip(before + 1) {
+                    let weight = (i - before) as f64 / (after - before) as f64;
+                    *value = values[before] + (values[after] - values[before]) * weight;
+                }
+            }
+        }
+    }
+
+    filled
+}
+
+// run the model for each day of the series and total the production by month and year
+pub fn run_station(
+    series: &StationSeries,
+    settings: &ModelSettings,
+    gap_filling: GapFilling
+) -> Result<StationRun, StationError> {
+    let dates = series.dates();
+
+    let mut filled_series: Vec<(ModelParameter, Vec<f64>)> = Vec::with_capacity(series.daily.len());
+    for (parameter, values) in series.daily.iter() {
+        if values.len() != dates.len() {
+            return Err(StationError::LengthMismatch(*parameter));
+        }
+        filled_series.push((*parameter, fill_gaps(values, gap_filling)));
+    }
+
+    let daily: Vec<StationDay> = dates.iter()
+        .enumerate()
+        .map(|(i, date)| {
+            let mut inputs = series.station.clone();
+            inputs.date = Some(*date);
+            inputs.iday = date.day_of_year();
+
+            let mut filled: Vec<ModelParameter> = Vec::new();
+            let mut missing: Vec<ModelParameter> = Vec::new();
+            for ((parameter, values), (_, original)) in filled_series.iter().zip(series.daily.iter()) {
+                if values[i].is_nan() && *parameter != ModelParameter::Par && !parameter.is_optional() {
+                    missing.push(*parameter);
+                } else if original[i].is_nan() && !values[i].is_nan() {
+                    filled.push(*parameter);
+                }
+                parameter.set(&mut inputs, values[i]);
+            }
+
+            let (outputs, error) = if !missing.is_empty() {
+                (None, None)
+            } else {
+                match calc_production(&inputs, settings) {
+                    Ok(outputs) => (Some(outputs), None),
+                    Err(error) => (None, Some(error))
+                }
+            };
+
+            StationDay {
+                date: *date,
+                pp_day: outputs.as_ref().and_then(|outputs| outputs.pp_day),
+                euphotic_depth: outputs.as_ref().and_then(|outputs| outputs.euphotic_depth),
+                par: outputs.as_ref().and_then(|outputs| outputs.par),
+                error,
+                filled,
+                missing,
+            }
+        })
+        .collect();
+
+    let monthly = total_by_period(&daily, |date| (date.year(), Some(date.month())));
+    let annual = total_by_period(&daily, |date| (date.year(), None));
+
+    Ok(StationRun { daily, monthly, annual })
+}
+
+// totals over consecutive days in the same period, keyed by year and month
+fn total_by_period<F>(daily: &[StationDay], period: F) -> Vec<PeriodTotal>
+where
+    F: Fn(&CalendarDate) -> (i32, Option<u8>),
+{
+    let mut totals: Vec<PeriodTotal> = Vec::new();
+
+    for day in daily {
+        let (year, month) = period(&day.date);
+        let is_new_period = totals.last().is_none_or(|total| (total.year, total.month) != (year, month));
+        if is_new_period {
+            totals.push(PeriodTotal { year, month, days: 0, modelled_days: 0, pp_total: 0.0, pp_estimated_total: f64::NAN });
+        }
+
+        let total = totals.last_mut().unwrap();
+        total.days += 1;
+        if let Some(pp_day) = day.pp_day {
+            total.modelled_days += 1;
+            total.pp_total += pp_day;
+        }
+    }
+
+    for total in totals.iter_mut().filter(|total| total.modelled_days > 0) {
+        // the first of a month taken from a date of the series
+        let first_day = CalendarDate::new(total.year, total.month.unwrap_or(1), 1).unwrap();
+        let calendar_days = match total.month {
+            Some(_) => first_day.days_in_month() as usize,
+            None => first_day.days_in_year() as usize,
+        };
+        total.pp_estimated_total = total.pp_total / total.modelled_days as f64 * calendar_days as f64;
+    }
+
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fill_gaps() {
+        let values = [1.0, f64::NAN, f64::NAN, 4.0, f64::NAN, f64::NAN, f64::NAN, 8.0];
+
+        let linear = fill_gaps(&values, GapFilling::Linear { max_days: 2 });
+        assert_eq!(&linear[..4], &[1.0, 2.0, 3.0, 4.0]);
+        assert!(linear[4..7].iter().all(|value| value.is_nan()));
+
+        let persistence = fill_gaps(&values, GapFilling::Persistence { max_days: 2 });
+        assert_eq!(&persistence[..6], &[1.0, 1.0, 1.0, 4.0, 4.0, 4.0]);
+        assert!(persistence[6].is_nan());
+
+        assert!(fill_gaps(&values, GapFilling::None)[1].is_nan());
+    }
+
+    #[test]
+    fn test_run_station() {
+        let text = "\
+date,lat,lon,z_bottom,alpha_b,pmb,z_m,mld,chl,rho,sigma,cloud,yel_sub,par
+2021-01-30,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,0.474,0.87,14.62,0.0,0.3,20.0
+2021-01-31,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,,0.87,14.62,0.0,0.3,
+2021-02-02,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,0.6,0.87,14.62,0.0,0.3,22.0
+";
+        let series = parse_station_series(text).unwrap();
+        assert_eq!(series.len(), 4);
+        assert_eq!(series.dates()[3], CalendarDate::new(2021, 2, 2).unwrap());
+
+        let settings = ModelSettings::default();
+
+        // without gap filling the day without chl and the day without a row are not run, a
+        // missing par is estimated
+        let run = run_station(&series, &settings, GapFilling::None).unwrap();
+        assert!(run.daily[0].pp_day.is_some() && run.daily[3].pp_day.is_some());
+        assert_eq!(run.daily[1].missing, vec![ModelParameter::Chl]);
+        assert!(run.daily[2].pp_day.is_none());
+
+        assert_eq!(run.monthly.len(), 2);
+        assert_eq!((run.monthly[0].days, run.monthly[0].modelled_days), (2, 1));
+        assert_eq!(run.monthly[0].pp_estimated_total, run.daily[0].pp_day.unwrap() * 31.0);
+        assert_eq!(run.annual.len(), 1);
+        assert_eq!(run.annual[0].pp_total, run.daily[0].pp_day.unwrap() + run.daily[3].pp_day.unwrap());
+
+        let filled = run_station(&series, &settings, GapFilling::Linear { max_days: 2 }).unwrap();
+        assert!(filled.daily.iter().all(|day| day.pp_day.is_some()));
+        assert!(filled.daily[1].filled.contains(&ModelParameter::Chl));
+        assert!(filled.daily[2].filled.contains(&ModelParameter::Par));
+        assert_eq!(filled.annual[0].modelled_days, 4);
+        assert!(filled.daily.iter().all(|day| day.error.is_none()));
+    }
+
+    #[test]
+    fn test_blank_sst_and_model_errors() {
+        // a blank sst leaves pmb as given, a chl the model rejects is recorded as an error
+        let text = "\
+date,lat,lon,z_bottom,alpha_b,pmb,z_m,mld,chl,rho,sigma,cloud,yel_sub,par,sst
+2021-06-01,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,0.474,0.87,14.62,0.0,0.3,40.0,
+2021-06-02,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,1e9,0.87,14.62,0.0,0.3,40.0,18.0
+";
+        let series = parse_station_series(text).unwrap();
+        let run = run_station(&series, &ModelSettings::default(), GapFilling::None).unwrap();
+
+        assert!(run.daily[0].pp_day.is_some() && run.daily[0].missing.is_empty());
+        assert!(run.daily[1].pp_day.is_none() && run.daily[1].missing.is_empty());
+        assert!(run.daily[1].error.is_some());
+    }
+}
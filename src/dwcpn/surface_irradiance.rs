@@ -0,0 +1,223 @@
+// Spectral irradiance just below the sea surface through a day, for use without the production
+// model. The clear sky direct and diffuse components of compute_irradiance_components are
+// corrected for the Earth-Sun distance, cloud and surface reflection as in the model, and are
+// either left at their clear sky values or scaled to a daily total of surface PAR (e.g. from a
+// satellite) spread through the day as in the model.
+//
+// Times are local solar time in hours (see solar::SolarDay to convert clock times) and spectra
+// are on WL_ARRAY in einsteins m^-2 h^-1 nm^-1, so that PAR is the sum times DELTA_LAMBDA.
+
+use crate::dwcpn::modules::config::{DELTA_LAMBDA, WL_COUNT};
+use crate::dwcpn::modules::irradiance::{
+    compute_iom, compute_irradiance_components, compute_par_surface_irradiance,
+    compute_polar_day_par_surface_irradiance, compute_solar_correction, correct_irradiance_components
+};
+use crate::dwcpn::modules::time::{compute_daylight_regime, compute_sunrise, resolve_day_of_year, CalendarDate, DaylightRegime};
+use crate::dwcpn::modules::zenith::compute_zenith;
+use crate::{ModelInputs, ModelSettings, SolarSpectrum};
+
+// time steps from sunrise to noon for the clear sky daily PAR
+const DAILY_PAR_TIMESTEPS: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParScaling {
+    // the clear sky irradiance, reduced by cloud
+    ClearSky,
+    // scaled to a daily total of PAR just above the surface (einsteins m^-2 d^-1)
+    DailyPar(f64),
+}
+
+pub struct SpectralIrradiance {
+    pub solar_time: f64,
+    // degrees
+    pub zenith: f64,
+    pub direct: [f64; WL_COUNT],
+    pub diffuse: [f64; WL_COUNT],
+    // einsteins m^-2 h^-1, just above and just below the surface
+    pub par_above_surface: f64,
+    pub par_below_surface: f64,
+}
+
+pub struct SurfaceIrradianceDay {
+    pub day_of_year: u16,
+    pub lat: f64,
+    pub sunrise: f64,
+    pub day_length: f64,
+    pub daylight_regime: DaylightRegime,
+    pub solar_spectrum: SolarSpectrum,
+    delta: f64,
+    phi: f64,
+    solar_correction: f64,
+}
+
+impl SurfaceIrradianceDay {
+    pub fn new(day_of_year: u16, year_length: f64, lat: f64, solar_spectrum: SolarSpectrum) -> SurfaceIrradianceDay {
+        let (sunrise, delta, phi) = compute_sunrise(day_of_year, year_length, lat);
+        let solar_correction = compute_solar_correction(&solar_spectrum, day_of_year, year_length);
+
+        SurfaceIrradianceDay {
+            day_of_year,
+            lat,
+            sunrise,
+            day_length: 2.0 * (12.0 - sunrise),
+            daylight_regime: compute_daylight_regime(delta, phi),
+            solar_spectrum,
+            delta,
+            phi,
+            solar_correction,
+        }
+    }
+
+    pub fn for_date(date: CalendarDate, lat: f64, solar_spectrum: SolarSpectrum) -> SurfaceIrradianceDay {
+        SurfaceIrradianceDay::new(date.day_of_year(), date.days_in_year() as f64, lat, solar_spectrum)
+    }
+
+    // irradiance at a local solar time with cloud cover in percent, zero when the sun is down
+    pub fn at(&self, solar_time: f64, cloud: f64, scaling: ParScaling) -> SpectralIrradiance {
+        let zenith_r = compute_zenith(solar_time, self.delta, self.phi);
+        let zenith_d = zenith_r.to_degrees();
+
+        let mut irradiance = SpectralIrradiance {
+            solar_time,
+            zenith: zenith_d,
+            direct: [0.0; WL_COUNT],
+            diffuse: [0.0; WL_COUNT],
+            par_above_surface: 0.0,
+            par_below_surface: 0.0,
+        };
+
+        if zenith_d >= 90.0 || self.daylight_regime == DaylightRegime::PolarNight {
+            return irradiance;
+        }
+
+        let (direct, diffuse) = compute_irradiance_components(zenith_r, zenith_d, &self.solar_spectrum);
+        let (mut direct, mut diffuse, par_above_surface) = correct_irradiance_components(
+            direct,
+            diffuse,
+            self.solar_correction,
+            zenith_r,
+            cloud
+        );
+
+        // the same daily cycle of surface PAR as the model
+        let adjustment = match scaling {
+            ParScaling::ClearSky => 1.0,
+            ParScaling::DailyPar(par) => {
+                let par_surface_irradiance = match self.daylight_regime {
+                    DaylightRegime::PolarDay => compute_polar_day_par_surface_irradiance(par, zenith_r, self.delta, self.phi),
+                    _ => {
+                        let iom = compute_iom(par, self.daylight_regime, self.day_length, self.delta, self.phi);
+                        compute_par_surface_irradiance(iom, solar_time, self.sunrise, self.day_length).max(0.0)
+                    }
+                };
+                par_surface_irradiance / par_above_surface
+            }
+        };
+
+        for (direct_l, diffuse_l) in direct.iter_mut().zip(diffuse.iter_mut()) {
+            *direct_l *= adjustment;
+            *diffuse_l *= adjustment;
+        }
+
+        irradiance.direct = direct;
+        irradiance.diffuse = diffuse;
+        irradiance.par_above_surface = par_above_surface * adjustment;
+        irradiance.par_below_surface = direct.iter().zip(diffuse.iter())
+            .map(|(direct_l, diffuse_l)| (direct_l + diffuse_l) * DELTA_LAMBDA)
+            .sum();
+        irradiance
+    }
+
+    pub fn series(&self, solar_times: &[f64], cloud: f64, scaling: ParScaling) -> Vec<SpectralIrradiance> {
+        solar_times.iter().map(|time| self.at(*time, cloud, scaling)).collect()
+    }
+
+    // daily PAR just above the surface (einsteins m^-2 d^-1) of the clear sky irradiance reduced by
+    // cloud, integrated from sunrise to noon and doubled as in the model
+    pub fn daily_par(&self, cloud: f64) -> f64 {
+        if self.daylight_regime == DaylightRegime::PolarNight {
+            return 0.0;
+        }
+
+        let delta_t = (12.0 - self.sunrise) / DAILY_PAR_TIMESTEPS as f64;
+        let par: Vec<f64> = (0..=DAILY_PAR_TIMESTEPS)
+            .map(|i| self.at(self.sunrise + delta_t * i as f64, cloud, ParScaling::ClearSky).par_above_surface)
+            .collect();
+
+        2.0 * par.windows(2).map(|pair| (pair[0] + pair[1]) * delta_t / 2.0).sum::<f64>()
+    }
+}
+
+// daily surface PAR of the inputs, or the clear sky estimate for their day, latitude and cloud when
+// it is not given (e.g. for reconstructions before satellite PAR)
+pub fn resolve_daily_par(input: &ModelInputs, settings: &ModelSettings) -> f64 {
+    match input.par {
+        Some(par) => par,
+        None => {
+            let (day_of_year, year_length) = resolve_day_of_year(input);
+            SurfaceIrradianceDay::new(day_of_year, year_length, input.lat, settings.solar_spectrum.clone())
+                .daily_par(input.cloud)
+        }
+    }
+}
+
+// solar times from midnight to midnight inclusive at a step in hours
+pub fn regular_times(step: f64) -> Vec<f64> {
+    let count = (24.0 / step).round() as usize;
+    (0..=count).map(|i| i as f64 * 24.0 / count as f64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::dwcpn::modules::irradiance::correct_and_recompute_irradiance_components;
+
+    #[test]
+    fn test_matches_model_scaling() {
+        let day = SurfaceIrradianceDay::new(121, 365.0, 18.71, SolarSpectrum::Legacy);
+        let irradiance = day.at(9.0, 20.0, ParScaling::DailyPar(55.8677));
+
+        // as correct_and_recompute_irradiance_components for the time step in the model
+        let zenith_r = compute_zenith(9.0, day.delta, day.phi);
+        let (direct, diffuse) = compute_irradiance_components(zenith_r, zenith_r.to_degrees(), &SolarSpectrum::Legacy);
+        let iom = compute_iom(55.8677, day.daylight_regime, day.day_length, day.delta, day.phi);
+        let (expected_direct, expected_diffuse) = correct_and_recompute_irradiance_components(
+            direct,
+            diffuse,
+            day.solar_correction,
+            compute_par_surface_irradiance(iom, 9.0, day.sunrise, day.day_length),
+            zenith_r,
+            20.0
+        );
+
+        for l in 0..WL_COUNT {
+            assert!((irradiance.direct[l] / expected_direct[l] - 1.0).abs() < 1e-12);
+            assert!((irradiance.diffuse[l] / expected_diffuse[l] - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_daily_totals() {
+        // scaled to a daily total, the surface PAR integrates back to it
+        let times = regular_times(0.05);
+        for (day_of_year, lat) in [(121, 18.71), (172, 75.0), (355, 50.0)] {
+            let day = SurfaceIrradianceDay::new(day_of_year, 365.0, lat, SolarSpectrum::Legacy);
+            let series = day.series(&times, 0.0, ParScaling::DailyPar(40.0));
+            let total: f64 = series.windows(2)
+                .map(|pair| (pair[0].par_above_surface + pair[1].par_above_surface) * 0.05 / 2.0)
+                .sum();
+
+            assert!((total / 40.0 - 1.0).abs() < 0.01);
+            assert!(series.iter().all(|sample| sample.par_below_surface < sample.par_above_surface || sample.par_above_surface == 0.0));
+        }
+
+        // the clear sky day is symmetric about noon, dark at night and dimmed by cloud
+        let day = SurfaceIrradianceDay::new(80, 365.0, 0.0, SolarSpectrum::Legacy);
+        let morning = day.at(9.0, 0.0, ParScaling::ClearSky);
+        let afternoon = day.at(15.0, 0.0, ParScaling::ClearSky);
+        assert!((morning.par_below_surface / afternoon.par_below_surface - 1.0).abs() < 1e-12);
+        assert_eq!(day.at(3.0, 0.0, ParScaling::ClearSky).par_below_surface, 0.0);
+        assert!(day.at(9.0, 80.0, ParScaling::ClearSky).par_below_surface < morning.par_below_surface);
+    }
+}
@@ -0,0 +1,341 @@
+// Validation of modelled daily production against in-situ match-ups.
+//
+// Match-ups are read from a comma separated file with a header row. Every scalar field of
+// ModelInputs is given by a column of the same name (lat, lon, z_bottom, alpha_b, pmb, z_m, mld,
+// chl, rho, sigma, cloud, yel_sub, par), the day by either an iday column or a date column
+// (yyyy-mm-dd), and the observation by an observed_pp column (mgC m^-2 d^-1). A par of nan is
+// estimated from the clear sky irradiance, and the sst column may be left out. Optional id and
+// region columns label each match-up; statistics are broken down per region.
+
+use crate::dwcpn::dwcpn::calc_production_batch;
+use crate::dwcpn::modules::parameters::ModelParameter;
+use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
+use crate::dwcpn::modules::time::CalendarDate;
+use crate::{ModelInputs, ModelSettings};
+
+pub struct MatchUp {
+    pub id: String,
+    pub region: Option<String>,
+    pub inputs: ModelInputs,
+    pub observed_pp: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    Io(String),
+    MissingColumn(String),
+    Parse { line: usize, message: String },
+}
+
+pub struct ValidationStatistics {
+    pub count: usize,
+    // mean of modelled - observed
+    pub bias: f64,
+    pub rmsd: f64,
+    pub pearson_r: f64,
+    // reduced major axis (type II) regression of modelled on observed
+    pub type_ii_slope: f64,
+    pub type_ii_intercept: f64,
+    // the same statistics on log10 transformed values, only positive pairs are used
+    pub log_count: usize,
+    pub log_bias: f64,
+    pub log_rmsd: f64,
+    pub log_pearson_r: f64,
+    pub log_type_ii_slope: f64,
+}
+
+pub struct ValidationPair {
+    pub id: String,
+    pub region: Option<String>,
+    pub observed: f64,
+    pub modelled: f64,
+}
+
+pub struct ValidationReport {
+    pub overall: ValidationStatistics,
+    // statistics for each region, in order of first appearance
+    pub regions: Vec<(String, ValidationStatistics)>,
+    pub pairs: Vec<ValidationPair>,
+    // ids of match-ups for which the model failed
+    pub failed: Vec<String>,
+}
+
+pub fn read_matchups(path: &str) -> Result<Vec<MatchUp>, ValidationError> {
+    let text = std::fs::read_to_string(path).map_err(|e| ValidationError::Io(e.to_string()))?;
+    parse_matchups(&text)
+}
+
+pub fn parse_matchups(text: &str) -> Result<Vec<MatchUp>, ValidationError> {
+    let mut lines = text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => line.split(',').map(|column| column.trim().to_string()).collect(),
+        None => return Ok(Vec::new())
+    };
+
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let required = |name: &str| column(name).ok_or(ValidationError::MissingColumn(name.to_string()));
+
+    let parameter_columns: Vec<(ModelParameter, usize)> = ModelParameter::ALL.iter()
+        .filter(|parameter| !parameter.is_optional() || column(parameter.name()).is_some())
+        .map(|parameter| required(parameter.name()).map(|index| (*parameter, index)))
+        .collect::<Result<_, _>>()?;
+    let observed_column = required("observed_pp")?;
+    let iday_column = column("iday");
+    let date_column = column("date");
+    if iday_column.is_none() && date_column.is_none() {
+        return Err(ValidationError::MissingColumn("iday or date".to_string()));
+    }
+    let id_column = column("id");
+    let region_column = column("region");
+
+    let mut matchups: Vec<MatchUp> = Vec::new();
+
+    for (line_index, line) in lines {
+        let line_number = line_index + 1;
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+
+        let field = |index: usize| -> Result<&str, ValidationError> {
+            fields.get(index).copied().ok_or(ValidationError::Parse {
+                line: line_number,
+                message: format!("missing value for column {}", header[index])
+            })
+        };
+        let number = |index: usize| -> Result<f64, ValidationError> {
+            let value = field(index)?;
+            value.parse::<f64>().map_err(|_| ValidationError::Parse {
+                line: line_number,
+                message: format!("could not read {} value '{}'", header[index], value)
+            })
+        };
+
+        let date = match date_column {
+            Some(index) => Some(CalendarDate::parse(field(index)?).ok_or(ValidationError::Parse {
+                line: line_number,
+                message: format!("could not read date '{}'", field(index)?)
+            })?),
+            None => None
+        };
+        let iday = match (iday_column, date) {
+            (Some(index), _) => {
+                let value = field(index)?;
+                value.parse::<u16>().ok().filter(|iday| (1..=366).contains(iday)).ok_or(ValidationError::Parse {
+                    line: line_number,
+                    message: format!("iday value '{}' is not a day of the year from 1 to 366", value)
+                })?
+            },
+            (None, Some(date)) => date.day_of_year(),
+            (None, None) => unreachable!()
+        };
+
+        let mut inputs = ModelInputs {
+            lat: 0.0,
+            lon: 0.0,
+            z_bottom: 0.0,
+            iday,
+            date,
+            utc_offset: None,
+            alpha_b: 0.0,
+            pmb: 0.0,
+            z_m: 0.0,
+            mld: 0.0,
+            chl: 0.0,
+            rho: 0.0,
+            sigma: 0.0,
+            cloud: 0.0,
+            yel_sub: 0.0,
+            par: None,
+            par_series: None,
+            sst: None,
+            bw: calculate_bw(),
+            bbr: calculate_bbr(),
+            ay: calculate_ay()
+        };
+
+        for (parameter, index) in parameter_columns.iter() {
+            parameter.set(&mut inputs, number(*index)?);
+        }
+
+        matchups.push(
+            MatchUp {
+                id: match id_column {
+                    Some(index) => field(index)?.to_string(),
+                    None => format!("line {}", line_number)
+                },
+                region: match region_column {
+                    Some(index) => Some(field(index)?.to_string()),
+                    None => None
+                },
+                inputs,
+                observed_pp: number(observed_column)?
+            }
+        );
+    }
+
+    Ok(matchups)
+}
+
+// run the model for every match-up and compare with the observations
+pub fn validate(matchups: &[MatchUp], settings: &ModelSettings) -> ValidationReport {
+    let mut pairs: Vec<ValidationPair> = Vec::with_capacity(matchups.len());
+    let mut failed: Vec<String> = Vec::new();
+
+    let inputs = matchups.iter().map(|matchup| &matchup.inputs);
+
+    for (matchup, result) in matchups.iter().zip(calc_production_batch(inputs, settings)) {
+        match result {
+            Ok(outputs) if outputs.pp_day.is_some() => pairs.push(
+                ValidationPair {
+                    id: matchup.id.clone(),
+                    region: matchup.region.clone(),
+                    observed: matchup.observed_pp,
+                    modelled: outputs.pp_day.unwrap_or(0.0)
+                }
+            ),
+            _ => failed.push(matchup.id.clone())
+        }
+    }
+
+    let statistics_for = |pairs: &[&ValidationPair]| -> ValidationStatistics {
+        let observed: Vec<f64> = pairs.iter().map(|pair| pair.observed).collect();
+        let modelled: Vec<f64> = pairs.iter().map(|pair| pair.modelled).collect();
+        compute_statistics(&observed, &modelled)
+    };
+
+    let all_pairs: Vec<&ValidationPair> = pairs.iter().collect();
+    let overall = statistics_for(&all_pairs);
+
+    let mut region_names: Vec<String> = Vec::new();
+    for pair in pairs.iter() {
+        if let Some(region) = &pair.region {
+            if !region_names.contains(region) {
+                region_names.push(region.clone());
+            }
+        }
+    }
+
+    let regions = region_names.into_iter()
+        .map(|region| {
+            let region_pairs: Vec<&ValidationPair> = pairs.iter()
+                .filter(|pair| pair.region.as_ref() == Some(&region))
+                .collect();
+            let statistics = statistics_for(&region_pairs);
+            (region, statistics)
+        })
+        .collect();
+
+    ValidationReport { overall, regions, pairs, failed }
+}
+
+// (bias, rmsd, pearson r, type II slope, type II intercept)
+fn linear_statistics(observed: &[f64], modelled: &[f64]) -> (f64, f64, f64, f64, f64) {
+    let n = observed.len() as f64;
+    if observed.is_empty() {
+        return (f64::NAN, f64::NAN, f64::NAN, f64::NAN, f64::NAN);
+    }
+
+    let mean_o = observed.iter().sum::<f64>() / n;
+    let mean_m = modelled.iter().sum::<f64>() / n;
+
+    let mut sum_d = 0.0;
+    let mut sum_dd = 0.0;
+    let mut s_oo = 0.0;
+    let mut s_mm = 0.0;
+    let mut s_om = 0.0;
+    for (o, m) in observed.iter().zip(modelled.iter()) {
+        sum_d += m - o;
+        sum_dd += (m - o).powi(2);
+        s_oo += (o - mean_o).powi(2);
+        s_mm += (m - mean_m).powi(2);
+        s_om += (o - mean_o) * (m - mean_m);
+    }
+
+    let pearson_r = s_om / (s_oo * s_mm).sqrt();
+    let type_ii_slope = (s_mm / s_oo).sqrt() * if s_om < 0.0 { -1.0 } else { 1.0 };
+
+    (
+        sum_d / n,
+        (sum_dd / n).sqrt(),
+        pearson_r,
+        type_ii_slope,
+        mean_m - type_ii_slope * mean_o
+    )
+}
+
+pub fn compute_statistics(observed: &[f64], modelled: &[f64]) -> ValidationStatistics {
+    let (bias, rmsd, pearson_r, type_ii_slope, type_ii_intercept) = linear_statistics(observed, modelled);
+
+    let (log_observed, log_modelled): (Vec<f64>, Vec<f64>) = observed.iter().zip(modelled.iter())
+        .filter(|(o, m)| **o > 0.0 && **m > 0.0)
+        .map(|(o, m)| (o.log10(), m.log10()))
+        .unzip();
+
+    let (log_bias, log_rmsd, log_pearson_r, log_type_ii_slope, _) = linear_statistics(&log_observed, &log_modelled);
+
+    ValidationStatistics {
+        count: observed.len(),
+        bias,
+        rmsd,
+        pearson_r,
+        type_ii_slope,
+        type_ii_intercept,
+        log_count: log_observed.len(),
+        log_bias,
+        log_rmsd,
+        log_pearson_r,
+        log_type_ii_slope,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_statistics() {
+        let observed = [100.0, 200.0, 400.0, 800.0];
+        let modelled = [200.0, 400.0, 800.0, 1600.0];
+
+        let statistics = compute_statistics(&observed, &modelled);
+
+        assert_eq!(statistics.count, 4);
+        assert!((statistics.bias - 375.0).abs() < 1e-9);
+        assert!((statistics.pearson_r - 1.0).abs() < 1e-12);
+        assert!((statistics.type_ii_slope - 2.0).abs() < 1e-12);
+        assert!(statistics.type_ii_intercept.abs() < 1e-9);
+        assert!((statistics.log_bias - 2.0f64.log10()).abs() < 1e-12);
+        assert!((statistics.log_rmsd - 2.0f64.log10()).abs() < 1e-12);
+        assert!((statistics.log_type_ii_slope - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_matchups() {
+        let text = "\
+id,region,date,lat,lon,z_bottom,alpha_b,pmb,z_m,mld,chl,rho,sigma,cloud,yel_sub,par,observed_pp
+# comment lines are skipped
+bs1,black_sea,2003-05-01,43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,0.474,0.87,14.62,0.0,0.3,50.35,905.2
+";
+        let matchups = parse_matchups(text).unwrap();
+
+        assert_eq!(matchups.len(), 1);
+        assert_eq!(matchups[0].id, "bs1");
+        assert_eq!(matchups[0].region.as_deref(), Some("black_sea"));
+        assert_eq!(matchups[0].inputs.iday, 121);
+        assert_eq!(matchups[0].inputs.sigma, 14.62);
+        assert_eq!(matchups[0].observed_pp, 905.2);
+
+        let missing = parse_matchups("lat,lon\n1.0,2.0\n");
+        assert_eq!(missing.err(), Some(ValidationError::MissingColumn("z_bottom".to_string())));
+
+        // iday has to be a whole day of the year
+        let header = "iday,lat,lon,z_bottom,alpha_b,pmb,z_m,mld,chl,rho,sigma,cloud,yel_sub,par,observed_pp\n";
+        let row = ",43.2,33.7,2198.8,0.0578,3.294,49.44,11.9296,0.474,0.87,14.62,0.0,0.3,50.35,905.2\n";
+        assert_eq!(parse_matchups(&format!("{}366{}", header, row)).unwrap()[0].inputs.iday, 366);
+        for iday in ["400.7", "121.0", "-3", "0", "367", "NaN"] {
+            assert!(matches!(parse_matchups(&format!("{}{}{}", header, iday, row)), Err(ValidationError::Parse { line: 2, .. })));
+        }
+    }
+}
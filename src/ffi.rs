@@ -0,0 +1,545 @@
+// C ABI around calc_production for the C and Fortran processing chain. The header is generated
+// with cbindgen (see cbindgen.toml) into include/dwcpn.h and the Fortran interface module is in
+// fortran/dwcpn.f90; both must be kept in step with the structs below.
+//
+// Inputs, settings and outputs are plain structs with fixed size arrays so that they map directly
+// onto C structs and Fortran bind(C) derived types. Optional outputs are NaN when not computed.
+// Every function returns one of the DWCPN_* status codes.
+
+use crate::dwcpn::dwcpn::calc_production_with_clear_sky;
+use crate::dwcpn::modules::clear_sky::ClearSkyCache;
+use crate::dwcpn::modules::config::{DEPTH_PROFILE_COUNT, WL_COUNT};
+use crate::dwcpn::modules::par_series::ParTimeSeries;
+use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
+use crate::dwcpn::modules::temperature::EPPLEY_RATE;
+use crate::dwcpn::modules::time::{CalendarDate, DaylightRegime};
+use crate::{IntegrationLimit, LowSunMode, ModelInputs, ModelOutputs, ModelSettings, PPErrors, Precision, ProductionMode, SolarSpectrum, TemperatureDependence};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// literal values so that cbindgen can write them to the header
+pub const DWCPN_WL_COUNT: usize = 61;
+pub const DWCPN_DEPTH_PROFILE_COUNT: usize = 500;
+const _: () = assert!(DWCPN_WL_COUNT == WL_COUNT && DWCPN_DEPTH_PROFILE_COUNT == DEPTH_PROFILE_COUNT);
+
+pub const DWCPN_OK: i32 = 0;
+pub const DWCPN_MODEL_ERROR: i32 = 1;
+pub const DWCPN_PP_TOO_HIGH: i32 = 2;
+pub const DWCPN_NULL_POINTER: i32 = -1;
+pub const DWCPN_INVALID_ARGUMENT: i32 = -2;
+pub const DWCPN_PANIC: i32 = -3;
+
+pub const DWCPN_LOW_SUN_TRIANGLE: i32 = 0;
+pub const DWCPN_LOW_SUN_SPECTRAL: i32 = 1;
+
+pub const DWCPN_PRODUCTION_SPECTRAL: i32 = 0;
+pub const DWCPN_PRODUCTION_BROADBAND: i32 = 1;
+
+pub const DWCPN_PRECISION_F64: i32 = 0;
+pub const DWCPN_PRECISION_F32: i32 = 1;
+
+pub const DWCPN_SOLAR_SPECTRUM_LEGACY: i32 = 0;
+pub const DWCPN_SOLAR_SPECTRUM_REFERENCE: i32 = 1;
+
+pub const DWCPN_TEMPERATURE_NONE: i32 = 0;
+pub const DWCPN_TEMPERATURE_EPPLEY: i32 = 1;
+pub const DWCPN_TEMPERATURE_Q10: i32 = 2;
+
+pub const DWCPN_INTEGRATION_EUPHOTIC_DEPTH: i32 = 0;
+pub const DWCPN_INTEGRATION_TENTH_PERCENT_LIGHT: i32 = 1;
+pub const DWCPN_INTEGRATION_MIXED_LAYER: i32 = 2;
+pub const DWCPN_INTEGRATION_FIXED_DEPTH: i32 = 3;
+pub const DWCPN_INTEGRATION_SEABED: i32 = 4;
+
+pub const DWCPN_DAYLIGHT_NORMAL: i32 = 0;
+pub const DWCPN_DAYLIGHT_POLAR_DAY: i32 = 1;
+pub const DWCPN_DAYLIGHT_POLAR_NIGHT: i32 = 2;
+
+#[repr(C)]
+pub struct DwcpnInputs {
+    pub lat: f64,
+    pub lon: f64,
+    pub z_bottom: f64,
+    pub iday: i32,
+    // calendar date, used instead of iday when year is not zero
+    pub year: i32,
+    pub month: i32,
+    pub day: i32,
+    // hours ahead of UTC for the reported clock times, NaN for UTC
+    pub utc_offset: f64,
+    pub alpha_b: f64,
+    pub pmb: f64,
+    pub z_m: f64,
+    pub mld: f64,
+    pub chl: f64,
+    pub rho: f64,
+    pub sigma: f64,
+    pub cloud: f64,
+    pub yel_sub: f64,
+    // NaN to estimate the daily PAR from the clear sky irradiance and cloud
+    pub par: f64,
+    // sea surface temperature (degrees C), NaN when not known
+    pub sst: f64,
+    pub bw: [f64; DWCPN_WL_COUNT],
+    pub bbr: [f64; DWCPN_WL_COUNT],
+    pub ay: [f64; DWCPN_WL_COUNT],
+}
+
+#[repr(C)]
+pub struct DwcpnSettings {
+    pub mld_only: i32,
+    pub iom_only: i32,
+    pub zenith_cutoff: f64,
+    // one of DWCPN_LOW_SUN_*
+    pub low_sun_mode: i32,
+    // one of DWCPN_PRODUCTION_*
+    pub production_mode: i32,
+    // one of DWCPN_PRECISION_*
+    pub precision: i32,
+    // one of DWCPN_SOLAR_SPECTRUM_*, reference_spectrum (W m^-2 um^-1 on the model wavelengths) is
+    // only read for DWCPN_SOLAR_SPECTRUM_REFERENCE
+    pub solar_spectrum: i32,
+    pub reference_spectrum: [f64; DWCPN_WL_COUNT],
+    // one of DWCPN_TEMPERATURE_*, with the Eppley rate (per degree C) or the Q10 as the
+    // coefficient and pmb taken as the rate at the reference temperature (degrees C)
+    pub temperature_dependence: i32,
+    pub temperature_coefficient: f64,
+    pub reference_temperature: f64,
+    // one of DWCPN_INTEGRATION_*, fixed_depth (m) is only read for DWCPN_INTEGRATION_FIXED_DEPTH
+    pub integration_limit: i32,
+    pub fixed_depth: f64,
+}
+
+#[repr(C)]
+pub struct DwcpnOutputs {
+    pub pp_day: f64,
+    pub pp_low_sun: f64,
+    pub euphotic_depth: f64,
+    pub integration_depth: f64,
+    // parts of pp_day above and below the mixed layer depth
+    pub pp_above_mld: f64,
+    pub pp_below_mld: f64,
+    pub spectral_i_star: f64,
+    pub par_noon_max: f64,
+    // daily PAR the model was run with
+    pub par: f64,
+    pub sunrise: f64,
+    pub solar_noon: f64,
+    pub sunset: f64,
+    // one of DWCPN_DAYLIGHT_*, -1 if not computed
+    pub daylight_regime: i32,
+    pub pp_profile: [f64; DWCPN_DEPTH_PROFILE_COUNT],
+}
+
+impl DwcpnInputs {
+    fn to_model_inputs(&self) -> Result<ModelInputs, i32> {
+        let date = if self.year != 0 {
+            let (month, day) = (u8::try_from(self.month), u8::try_from(self.day));
+            match (month, day) {
+                (Ok(month), Ok(day)) => Some(CalendarDate::new(self.year, month, day).ok_or(DWCPN_INVALID_ARGUMENT)?),
+                _ => return Err(DWCPN_INVALID_ARGUMENT)
+            }
+        } else {
+            None
+        };
+
+        let iday = match (date, u16::try_from(self.iday)) {
+            (Some(date), _) => date.day_of_year(),
+            (None, Ok(iday @ 1..=366)) => iday,
+            (None, _) => return Err(DWCPN_INVALID_ARGUMENT)
+        };
+
+        Ok(
+            ModelInputs {
+                lat: self.lat,
+                lon: self.lon,
+                z_bottom: self.z_bottom,
+                iday,
+                date,
+                utc_offset: if self.utc_offset.is_nan() { None } else { Some(self.utc_offset) },
+                alpha_b: self.alpha_b,
+                pmb: self.pmb,
+                z_m: self.z_m,
+                mld: self.mld,
+                chl: self.chl,
+                rho: self.rho,
+                sigma: self.sigma,
+                cloud: self.cloud,
+                yel_sub: self.yel_sub,
+                par: if self.par.is_nan() { None } else { Some(self.par) },
+                par_series: None,
+                sst: Some(self.sst).filter(|sst| !sst.is_nan()),
+                bw: self.bw,
+                bbr: self.bbr,
+                ay: self.ay
+            }
+        )
+    }
+}
+
+impl DwcpnSettings {
+    fn to_model_settings(&self) -> Result<ModelSettings, i32> {
+        Ok(
+            ModelSettings {
+                mld_only: self.mld_only != 0,
+                iom_only: self.iom_only != 0,
+                zenith_cutoff: self.zenith_cutoff,
+                low_sun_mode: match self.low_sun_mode {
+                    DWCPN_LOW_SUN_TRIANGLE => LowSunMode::Triangle,
+                    DWCPN_LOW_SUN_SPECTRAL => LowSunMode::Spectral,
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                production_mode: match self.production_mode {
+                    DWCPN_PRODUCTION_SPECTRAL => ProductionMode::Spectral,
+                    DWCPN_PRODUCTION_BROADBAND => ProductionMode::Broadband,
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                precision: match self.precision {
+                    DWCPN_PRECISION_F64 => Precision::F64,
+                    DWCPN_PRECISION_F32 => Precision::F32,
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                solar_spectrum: match self.solar_spectrum {
+                    DWCPN_SOLAR_SPECTRUM_LEGACY => SolarSpectrum::Legacy,
+                    DWCPN_SOLAR_SPECTRUM_REFERENCE => SolarSpectrum::Reference(Box::new(self.reference_spectrum)),
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                temperature_dependence: match self.temperature_dependence {
+                    DWCPN_TEMPERATURE_NONE => TemperatureDependence::None,
+                    DWCPN_TEMPERATURE_EPPLEY => TemperatureDependence::Eppley {
+                        rate: self.temperature_coefficient,
+                        reference_temperature: self.reference_temperature
+                    },
+                    DWCPN_TEMPERATURE_Q10 => TemperatureDependence::Q10 {
+                        q10: self.temperature_coefficient,
+                        reference_temperature: self.reference_temperature
+                    },
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                integration_limit: match self.integration_limit {
+                    DWCPN_INTEGRATION_EUPHOTIC_DEPTH => IntegrationLimit::EuphoticDepth,
+                    DWCPN_INTEGRATION_TENTH_PERCENT_LIGHT => IntegrationLimit::TenthPercentLight,
+                    DWCPN_INTEGRATION_MIXED_LAYER => IntegrationLimit::MixedLayer,
+                    DWCPN_INTEGRATION_FIXED_DEPTH => IntegrationLimit::FixedDepth(self.fixed_depth),
+                    DWCPN_INTEGRATION_SEABED => IntegrationLimit::Seabed,
+                    _ => return Err(DWCPN_INVALID_ARGUMENT)
+                },
+                ..Default::default()
+            }
+        )
+    }
+}
+
+impl DwcpnOutputs {
+    fn set(&mut self, outputs: &ModelOutputs) {
+        self.pp_day = outputs.pp_day.unwrap_or(f64::NAN);
+        self.pp_low_sun = outputs.pp_low_sun.unwrap_or(f64::NAN);
+        self.euphotic_depth = outputs.euphotic_depth.unwrap_or(f64::NAN);
+        self.integration_depth = outputs.integration_depth.unwrap_or(f64::NAN);
+        self.pp_above_mld = outputs.pp_above_mld.unwrap_or(f64::NAN);
+        self.pp_below_mld = outputs.pp_below_mld.unwrap_or(f64::NAN);
+        self.spectral_i_star = outputs.spectral_i_star.unwrap_or(f64::NAN);
+        self.par_noon_max = outputs.par_noon_max.unwrap_or(f64::NAN);
+        self.par = outputs.par.unwrap_or(f64::NAN);
+        self.sunrise = outputs.sunrise.unwrap_or(f64::NAN);
+        self.solar_noon = outputs.solar_noon.unwrap_or(f64::NAN);
+        self.sunset = outputs.sunset.unwrap_or(f64::NAN);
+        self.daylight_regime = match outputs.daylight_regime {
+            Some(DaylightRegime::Normal) => DWCPN_DAYLIGHT_NORMAL,
+            Some(DaylightRegime::PolarDay) => DWCPN_DAYLIGHT_POLAR_DAY,
+            Some(DaylightRegime::PolarNight) => DWCPN_DAYLIGHT_POLAR_NIGHT,
+            None => -1
+        };
+        self.pp_profile = outputs.pp_profile.unwrap_or([f64::NAN; DEPTH_PROFILE_COUNT]);
+    }
+
+    fn empty() -> DwcpnOutputs {
+        DwcpnOutputs {
+            pp_day: f64::NAN,
+            pp_low_sun: f64::NAN,
+            euphotic_depth: f64::NAN,
+            integration_depth: f64::NAN,
+            pp_above_mld: f64::NAN,
+            pp_below_mld: f64::NAN,
+            spectral_i_star: f64::NAN,
+            par_noon_max: f64::NAN,
+            par: f64::NAN,
+            sunrise: f64::NAN,
+            solar_noon: f64::NAN,
+            sunset: f64::NAN,
+            daylight_regime: -1,
+            pp_profile: [f64::NAN; DEPTH_PROFILE_COUNT],
+        }
+    }
+}
+
+// status and outputs for one pixel, the outputs are all NaN unless the status is DWCPN_OK. The
+// clear sky irradiance comes from the cache, so a batch computes it once per day and latitude.
+fn run(
+    inputs: &DwcpnInputs,
+    settings: &DwcpnSettings,
+    par_series: Option<ParTimeSeries>,
+    clear_sky_cache: &mut ClearSkyCache
+) -> (i32, DwcpnOutputs) {
+    let mut outputs = DwcpnOutputs::empty();
+
+    let model_inputs = match inputs.to_model_inputs() {
+        Ok(model_inputs) => ModelInputs { par_series, ..model_inputs },
+        Err(status) => return (status, outputs)
+    };
+    let model_settings = match settings.to_model_settings() {
+        Ok(model_settings) => model_settings,
+        Err(status) => return (status, outputs)
+    };
+
+    // a panic while computing the irradiance leaves nothing in the cache
+    let status = match catch_unwind(AssertUnwindSafe(|| {
+        let clear_sky = clear_sky_cache.for_inputs(&model_inputs, &model_settings);
+        calc_production_with_clear_sky(&model_inputs, &model_settings, clear_sky)
+    })) {
+        Ok(Ok(model_outputs)) => {
+            outputs.set(&model_outputs);
+            DWCPN_OK
+        },
+        Ok(Err(PPErrors::PPTooHigh)) => DWCPN_PP_TOO_HIGH,
+        Ok(Err(PPErrors::InvalidParSeries)) => DWCPN_INVALID_ARGUMENT,
+        Ok(Err(PPErrors::UnsupportedSettings)) => DWCPN_INVALID_ARGUMENT,
+        Ok(Err(_)) => DWCPN_MODEL_ERROR,
+        Err(_) => DWCPN_PANIC
+    };
+
+    (status, outputs)
+}
+
+/// Fill inputs with zeros, iday 1, utc_offset NaN and the default water, backscattering and
+/// yellow substance spectra, so that callers only need to set the per pixel values.
+///
+/// # Safety
+/// inputs must be null or point to a writable DwcpnInputs.
+#[no_mangle]
+pub unsafe extern "C" fn dwcpn_default_inputs(inputs: *mut DwcpnInputs) -> i32 {
+    if inputs.is_null() {
+        return DWCPN_NULL_POINTER;
+    }
+
+    inputs.write(DwcpnInputs {
+        lat: 0.0,
+        lon: 0.0,
+        z_bottom: 0.0,
+        iday: 1,
+        year: 0,
+        month: 0,
+        day: 0,
+        utc_offset: f64::NAN,
+        alpha_b: 0.0,
+        pmb: 0.0,
+        z_m: 0.0,
+        mld: 0.0,
+        chl: 0.0,
+        rho: 0.0,
+        sigma: 0.0,
+        cloud: 0.0,
+        yel_sub: 0.0,
+        par: 0.0,
+        sst: f64::NAN,
+        bw: calculate_bw(),
+        bbr: calculate_bbr(),
+        ay: calculate_ay()
+    });
+
+    DWCPN_OK
+}
+
+/// Fill settings with the defaults of the original model: the Gaussian chlorophyll profile, an
+/// 80 degree zenith cutoff with the triangle low sun approximation and the spectral model in f64.
+///
+/// # Safety
+/// settings must be null or point to a writable DwcpnSettings.
+#[no_mangle]
+pub unsafe extern "C" fn dwcpn_default_settings(settings: *mut DwcpnSettings) -> i32 {
+    if settings.is_null() {
+        return DWCPN_NULL_POINTER;
+    }
+
+    settings.write(DwcpnSettings {
+        mld_only: 0,
+        iom_only: 0,
+        zenith_cutoff: 80.0,
+        low_sun_mode: DWCPN_LOW_SUN_TRIANGLE,
+        production_mode: DWCPN_PRODUCTION_SPECTRAL,
+        precision: DWCPN_PRECISION_F64,
+        solar_spectrum: DWCPN_SOLAR_SPECTRUM_LEGACY,
+        reference_spectrum: [0.0; DWCPN_WL_COUNT],
+        temperature_dependence: DWCPN_TEMPERATURE_NONE,
+        temperature_coefficient: EPPLEY_RATE,
+        reference_temperature: 20.0,
+        integration_limit: DWCPN_INTEGRATION_EUPHOTIC_DEPTH,
+        fixed_depth: 0.0
+    });
+
+    DWCPN_OK
+}
+
+/// Run the model for one pixel.
+///
+/// # Safety
+/// inputs and settings must be null or point to valid structs, outputs must be null or point to
+/// a writable DwcpnOutputs.
+#[no_mangle]
+pub unsafe extern "C" fn dwcpn_calc_production(
+    inputs: *const DwcpnInputs,
+    settings: *const DwcpnSettings,
+    outputs: *mut DwcpnOutputs
+) -> i32 {
+    match (inputs.as_ref(), settings.as_ref()) {
+        (Some(inputs), Some(settings)) if !outputs.is_null() => {
+            let (status, pixel_outputs) = run(inputs, settings, None, &mut ClearSkyCache::default());
+            outputs.write(pixel_outputs);
+            status
+        },
+        _ => DWCPN_NULL_POINTER
+    }
+}
+
+/// Run the model for one pixel with surface PAR from a time series of count samples in place of
+/// inputs.par: times are clock hours (UTC plus utc_offset), increasing, and par is in einsteins
+/// m^-2 h^-1. The series must cover sunrise to sunset and DWCPN_INVALID_ARGUMENT is returned if
+/// it does not.
+///
+/// # Safety
+/// inputs and settings must be null or point to valid structs, times and par must be null or
+/// point to arrays of at least count elements, and outputs must be null or point to a writable
+/// DwcpnOutputs.
+#[no_mangle]
+pub unsafe extern "C" fn dwcpn_calc_production_par_series(
+    inputs: *const DwcpnInputs,
+    settings: *const DwcpnSettings,
+    times: *const f64,
+    par: *const f64,
+    count: usize,
+    outputs: *mut DwcpnOutputs
+) -> i32 {
+    if times.is_null() || par.is_null() {
+        return DWCPN_NULL_POINTER;
+    }
+
+    let par_series = ParTimeSeries::new(
+        std::slice::from_raw_parts(times, count).to_vec(),
+        std::slice::from_raw_parts(par, count).to_vec()
+    );
+
+    match (inputs.as_ref(), settings.as_ref()) {
+        (Some(inputs), Some(settings)) if !outputs.is_null() => {
+            let (status, pixel_outputs) = run(inputs, settings, Some(par_series), &mut ClearSkyCache::default());
+            outputs.write(pixel_outputs);
+            status
+        },
+        _ => DWCPN_NULL_POINTER
+    }
+}
+
+/// Run the model for count pixels, writing the status of each pixel to statuses. Returns
+/// DWCPN_OK if all of the arguments are valid, whatever the status of the individual pixels.
+/// The clear sky irradiance is computed once for each distinct day and latitude in the batch.
+///
+/// # Safety
+/// inputs, outputs and statuses must be null or point to arrays of at least count elements, and
+/// settings must be null or point to a valid DwcpnSettings.
+#[no_mangle]
+pub unsafe extern "C" fn dwcpn_calc_production_batch(
+    inputs: *const DwcpnInputs,
+    count: usize,
+    settings: *const DwcpnSettings,
+    outputs: *mut DwcpnOutputs,
+    statuses: *mut i32
+) -> i32 {
+    if inputs.is_null() || settings.is_null() || outputs.is_null() || statuses.is_null() {
+        return DWCPN_NULL_POINTER;
+    }
+
+    let inputs = std::slice::from_raw_parts(inputs, count);
+    let settings = &*settings;
+
+    let mut clear_sky_cache = ClearSkyCache::default();
+
+    for (i, input) in inputs.iter().enumerate() {
+        let (status, pixel_outputs) = run(input, settings, None, &mut clear_sky_cache);
+        outputs.add(i).write(pixel_outputs);
+        statuses.add(i).write(status);
+    }
+
+    DWCPN_OK
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::dwcpn::dwcpn::calc_production;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_calc_production() {
+        unsafe {
+            let mut inputs = MaybeUninit::<DwcpnInputs>::uninit();
+            let mut settings = MaybeUninit::<DwcpnSettings>::uninit();
+            assert_eq!(dwcpn_default_inputs(inputs.as_mut_ptr()), DWCPN_OK);
+            assert_eq!(dwcpn_default_settings(settings.as_mut_ptr()), DWCPN_OK);
+            let mut inputs = inputs.assume_init();
+            let settings = settings.assume_init();
+
+            inputs.lat = 18.71;
+            inputs.lon = -18.625;
+            inputs.z_bottom = 2950.468;
+            inputs.iday = 121;
+            inputs.alpha_b = 0.1518;
+            inputs.pmb = 3.9059;
+            inputs.z_m = 23.094;
+            inputs.mld = 31.975;
+            inputs.chl = 1.718;
+            inputs.rho = 0.8247;
+            inputs.sigma = 27.556;
+            inputs.yel_sub = 0.3;
+            inputs.par = 55.8677;
+
+            let mut outputs = Box::new(MaybeUninit::<DwcpnOutputs>::uninit());
+            assert_eq!(dwcpn_calc_production(&inputs, &settings, outputs.as_mut_ptr()), DWCPN_OK);
+            let outputs = outputs.assume_init();
+
+            let expected = calc_production(&inputs.to_model_inputs().unwrap(), &settings.to_model_settings().unwrap())
+                .unwrap();
+            assert_eq!(Some(outputs.pp_day), expected.pp_day);
+            assert_eq!(outputs.daylight_regime, DWCPN_DAYLIGHT_NORMAL);
+
+            inputs.iday = 0;
+            let mut outputs = Box::new(MaybeUninit::<DwcpnOutputs>::uninit());
+            assert_eq!(dwcpn_calc_production(&inputs, &settings, outputs.as_mut_ptr()), DWCPN_INVALID_ARGUMENT);
+            assert_eq!(dwcpn_calc_production(std::ptr::null(), &settings, outputs.as_mut_ptr()), DWCPN_NULL_POINTER);
+
+            // a PAR time series that stops before sunset
+            inputs.iday = 121;
+            let (times, par) = ([5.0, 12.0, 16.0], [0.0, 7.0, 3.0]);
+            assert_eq!(
+                dwcpn_calc_production_par_series(&inputs, &settings, times.as_ptr(), par.as_ptr(), 3, outputs.as_mut_ptr()),
+                DWCPN_INVALID_ARGUMENT
+            );
+
+            // a batch on two latitudes, with one invalid pixel
+            // the inputs are plain data, so copies can be read out of them
+            let mut batch: [DwcpnInputs; 3] = std::array::from_fn(|_| std::ptr::read(&inputs));
+            batch[1].chl = 0.5;
+            batch[2].lat = -30.0;
+            batch[2].iday = 0;
+            let mut batch_outputs: Vec<MaybeUninit<DwcpnOutputs>> = (0..3).map(|_| MaybeUninit::uninit()).collect();
+            let mut statuses = [0; 3];
+            assert_eq!(
+                dwcpn_calc_production_batch(batch.as_ptr(), 3, &settings, batch_outputs.as_mut_ptr() as *mut DwcpnOutputs, statuses.as_mut_ptr()),
+                DWCPN_OK
+            );
+            assert_eq!(statuses, [DWCPN_OK, DWCPN_OK, DWCPN_INVALID_ARGUMENT]);
+            for (pixel, pixel_outputs) in batch.iter().zip(batch_outputs.iter()).take(2) {
+                let expected = calc_production(&pixel.to_model_inputs().unwrap(), &settings.to_model_settings().unwrap()).unwrap();
+                assert_eq!(Some(pixel_outputs.assume_init_ref().pp_day), expected.pp_day);
+            }
+        }
+    }
+}
@@ -1,12 +1,22 @@
 use crate::dwcpn::modules::config::{DEPTH_PROFILE_COUNT, WL_COUNT};
+use crate::dwcpn::modules::par_series::ParTimeSeries;
+use crate::dwcpn::modules::time::{CalendarDate, DaylightRegime};
 
 pub mod dwcpn;
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
 
+#[derive(Clone)]
 pub struct ModelInputs {
     pub lat: f64,
     pub lon: f64,
     pub z_bottom: f64,
     pub iday: u16,
+    // calendar date, takes precedence over iday when given so that leap years are handled
+    pub date: Option<CalendarDate>,
+    // time zone of reported clock times in hours ahead of UTC, times are UTC when not given
+    pub utc_offset: Option<f64>,
     pub alpha_b: f64,
     pub pmb: f64,
     pub z_m: f64,
@@ -16,7 +26,13 @@ pub struct ModelInputs {
     pub sigma: f64,
     pub cloud: f64,
     pub yel_sub: f64,
-    pub par: f64,
+    // daily surface PAR (einsteins m^-2 d^-1), estimated from the clear sky irradiance and cloud
+    // when not given, see surface_irradiance::resolve_daily_par
+    pub par: Option<f64>,
+    // sub-daily surface PAR used in place of the sine curve from par, see modules::par_series
+    pub par_series: Option<ParTimeSeries>,
+    // sea surface temperature (degrees C) for settings.temperature_dependence
+    pub sst: Option<f64>,
     pub bw: [f64; WL_COUNT],
     pub bbr: [f64; WL_COUNT],
     pub ay: [f64; WL_COUNT],
@@ -27,34 +43,223 @@ pub struct ProchloroInputs {
     pub prochloro_maximum: f64
 }
 
+// how production between sunrise and the time the sun climbs above the zenith cutoff is treated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LowSunMode {
+    // linear ramp from zero at sunrise to the production at the cutoff, except during polar day
+    // when the sun is up at midnight and the spectral model is used instead
+    Triangle,
+    // run the spectral model at extra time steps through the low sun period
+    Spectral
+}
+
+// depth down to which the production profile is integrated at each time step, never deeper than
+// z_bottom or the bottom of the model depth grid (249.5 m, DEPTH_PROFILE_COUNT steps of
+// DEPTH_PROFILE_STEP). Limits below the grid are clamped to it, and
+// ModelOutputs.integration_depth reports the clamped depth
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntegrationLimit {
+    // the euphotic depth (1% of the surface PAR) of the original model
+    EuphoticDepth,
+    // 0.1% of the surface PAR, the bottom of the grid in water clear enough not to reach it
+    TenthPercentLight,
+    // inputs.mld, at most the bottom of the grid
+    MixedLayer,
+    // a fixed depth (m), at most the bottom of the grid
+    FixedDepth(f64),
+    // z_bottom, the full profile to the seabed in water shallower than the grid, and otherwise
+    // only to the bottom of the grid (e.g. 249.5 m rather than 2950 m for a deep station)
+    Seabed
+}
+
+// photosynthetic parameters of the pico, nano and microphytoplankton (in that order), whose
+// chlorophyll is split from the total as modules::absorption::calc_size_class_chl
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SizeClassInputs {
+    pub alpha_b: [f64; 3],
+    // at the reference temperature when settings.temperature_dependence is used, as inputs.pmb
+    pub pmb: [f64; 3]
+}
+
+// which model computes production
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProductionMode {
+    // the full spectral model
+    Spectral,
+    // broadband (non-spectral) attenuation with the depth integral of Platt and Sathyendranath,
+    // see modules::broadband
+    Broadband
+}
+
+// floating point precision of the spectral light field, see modules::float
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    F64,
+    // halves the size of the optical profile, changing pp_day by less than 1e-6 relative
+    F32
+}
+
+// extra-terrestrial solar spectrum and Earth-Sun distance correction of the clear sky irradiance
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolarSpectrum {
+    // the 24 point spectrum and Thekaekara distance table of the original model
+    Legacy,
+    // a reference spectrum on WL_ARRAY in W m^-2 um^-1 (e.g. Thuillier 2003, binned with
+    // dwcpn::solar_spectrum::read_reference_spectrum) with the Spencer (1971) distance correction
+    Reference(Box<[f64; WL_COUNT]>)
+}
+
+// dependence of the maximum photosynthetic rate pmb on sea surface temperature, applied to the pmb
+// of the inputs at the reference temperature (degrees C), see modules::temperature
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TemperatureDependence {
+    // pmb is used as given
+    None,
+    // exponential after Eppley (1972), pmb * exp(rate * (sst - reference_temperature)) with a
+    // rate of 0.0633 per degree C in the original
+    Eppley { rate: f64, reference_temperature: f64 },
+    // pmb * q10^((sst - reference_temperature) / 10)
+    Q10 { q10: f64, reference_temperature: f64 }
+}
+
 pub struct ModelSettings {
     pub mld_only: bool,
     pub iom_only: bool,
-    pub prochloro_inputs: Option<ProchloroInputs>
+    pub prochloro_inputs: Option<ProchloroInputs>,
+    // zenith angle (degrees) below which the full time integration starts, 80.0 in the original model,
+    // between 0 and 90
+    pub zenith_cutoff: f64,
+    pub low_sun_mode: LowSunMode,
+    pub production_mode: ProductionMode,
+    pub precision: Precision,
+    pub solar_spectrum: SolarSpectrum,
+    pub temperature_dependence: TemperatureDependence,
+    // production of each size class as well as the total, spectral production mode only. The
+    // classes are not a partition of the total, see SizeClassOutputs
+    pub size_classes: Option<SizeClassInputs>,
+    // only the euphotic depth is supported by the broadband production mode
+    pub integration_limit: IntegrationLimit
+}
+
+// the behaviour of the original model
+impl Default for ModelSettings {
+    fn default() -> ModelSettings {
+        ModelSettings {
+            mld_only: false,
+            iom_only: false,
+            prochloro_inputs: None,
+            zenith_cutoff: 80.0,
+            low_sun_mode: LowSunMode::Triangle,
+            production_mode: ProductionMode::Spectral,
+            precision: Precision::F64,
+            solar_spectrum: SolarSpectrum::Legacy,
+            temperature_dependence: TemperatureDependence::None,
+            size_classes: None,
+            integration_limit: IntegrationLimit::EuphoticDepth
+        }
+    }
+}
+
+// production of the size classes of settings.size_classes, each array ordered pico, nano, micro.
+// Each class is run with its own absorption spectrum and photosynthetic parameters in the light
+// field of the whole population, while ModelOutputs.pp_day and pp_profile use the absorption of
+// the mixture and the parameters of the inputs, so the classes do not sum to the total (they are
+// within 10% of it when every class has the parameters of the inputs)
+#[derive(Clone, Debug)]
+pub struct SizeClassOutputs {
+    // not a share of ModelOutputs.pp_day, see above
+    pub pp_day: [f64; 3],
+    // integrated over the day as ModelOutputs.pp_profile, and no more a share of it than pp_day
+    pub pp_profile: [[f64; DEPTH_PROFILE_COUNT]; 3],
+    pub chl_profile: [[f64; DEPTH_PROFILE_COUNT]; 3]
 }
 
 pub struct ModelOutputs {
     pub pp_day: Option<f64>,
+    // part of pp_day produced while the sun is lower than the zenith cutoff
+    pub pp_low_sun: Option<f64>,
+    // production at each depth of the model depth grid (see gen_depth_array) integrated over the day
+    pub pp_profile: Option<[f64; DEPTH_PROFILE_COUNT]>,
     pub euphotic_depth: Option<f64>,
+    // deepest integration limit of the day, the same as euphotic_depth for IntegrationLimit::EuphoticDepth
+    pub integration_depth: Option<f64>,
+    // parts of pp_day produced above and below the mixed layer depth
+    pub pp_above_mld: Option<f64>,
+    pub pp_below_mld: Option<f64>,
     pub spectral_i_star: Option<f64>,
     pub par_noon_max: Option<f64>,
+    // daily surface PAR the model was run with, the clear sky estimate when inputs.par is None
+    pub par: Option<f64>,
+    // sunrise, solar noon and sunset in clock time (UTC shifted by utc_offset)
+    pub sunrise: Option<f64>,
+    pub solar_noon: Option<f64>,
+    pub sunset: Option<f64>,
+    // flags days with continuous daylight or darkness
+    pub daylight_regime: Option<DaylightRegime>,
     pub pro_1_profile: Option<[f64; DEPTH_PROFILE_COUNT]>,
     pub pro_2_profile: Option<[f64; DEPTH_PROFILE_COUNT]>,
     pub pro_total_profile: Option<[f64; DEPTH_PROFILE_COUNT]>,
-    pub pp_prochloro_profile: Option<[f64; DEPTH_PROFILE_COUNT]>
+    pub pp_prochloro_profile: Option<[f64; DEPTH_PROFILE_COUNT]>,
+    pub size_classes: Option<SizeClassOutputs>
 }
 
 #[derive(Debug)]
 pub enum PPErrors {
     DWCPNError,
-    PPTooHigh
+    PPTooHigh,
+    // the PAR time series is malformed or does not cover the hours of daylight
+    InvalidParSeries,
+    // the settings ask for something the production mode does not do
+    UnsupportedSettings,
+    // precomputed clear sky irradiance was for a different day, latitude or settings
+    ClearSkyMismatch
 }
 
+// inputs shared by the tests, a mid-latitude open ocean station on the first of May to be
+// adjusted with struct update syntax
 #[cfg(test)]
-mod integration_tests {
-    use crate::dwcpn::dwcpn::{calc_production};
+pub(crate) mod test_fixtures {
     use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
-    use crate::{ModelInputs, ModelOutputs, ModelSettings};
+    use crate::ModelInputs;
+
+    pub fn model_inputs() -> ModelInputs {
+        ModelInputs {
+            lat: 45.0,
+            lon: 15.0,
+            z_bottom: 1000.0,
+            iday: 121,
+            date: None,
+            utc_offset: None,
+            alpha_b: 0.08,
+            pmb: 3.0,
+            z_m: 30.0,
+            mld: 20.0,
+            chl: 0.5,
+            rho: 0.8,
+            sigma: 20.0,
+            cloud: 0.0,
+            yel_sub: 0.3,
+            par: Some(45.0),
+            par_series: None,
+            sst: None,
+            bw: calculate_bw(),
+            bbr: calculate_bbr(),
+            ay: calculate_ay()
+        }
+    }
+}
+
+#[cfg(test)]
+mod integration_tests {
+    use crate::dwcpn::dwcpn::{calc_production, calc_production_batch, calc_production_with_clear_sky};
+    use crate::dwcpn::modules::clear_sky::{ClearSkyCache, ClearSkyIrradiance};
+    use crate::dwcpn::modules::config::WL_COUNT;
+    use crate::dwcpn::modules::irradiance::compute_irradiance_components;
+    use crate::dwcpn::modules::par_series::ParTimeSeries;
+    use crate::test_fixtures::model_inputs;
+    use crate::dwcpn::modules::time::{CalendarDate, DaylightRegime};
+    use crate::dwcpn::surface_irradiance::{regular_times, ParScaling, SurfaceIrradianceDay};
+    use crate::{IntegrationLimit, LowSunMode, ModelInputs, ModelOutputs, ModelSettings, PPErrors, Precision, ProductionMode, SizeClassInputs, SolarSpectrum, TemperatureDependence};
 
     struct TestCase {
         name: String,
@@ -114,29 +319,32 @@ mod integration_tests {
             chl: 0.26096588,
             rho: 0.878,
             sigma: 34.6,
-            cloud: 0.0,
-            yel_sub: 0.3,
-            par: 49.1697464,
-            bw: calculate_bw(),
-            bbr: calculate_bbr(),
-            ay: calculate_ay()
+            par: Some(49.1697464),
+            ..model_inputs()
         };
 
-        let settings = ModelSettings {
-            mld_only: false,
-            iom_only: false,
-            prochloro_inputs: None
-        };
+        let settings = ModelSettings::default();
 
         let expected_result = ModelOutputs {
             pp_day: Some(721.7),
+            pp_low_sun: None,
+            pp_profile: None,
             euphotic_depth: None,
+            integration_depth: None,
+            pp_above_mld: None,
+            pp_below_mld: None,
             spectral_i_star: None,
             par_noon_max: None,
+            par: None,
+            sunrise: None,
+            solar_noon: None,
+            sunset: None,
+            daylight_regime: None,
             pro_1_profile: None,
             pro_2_profile: None,
             pro_total_profile: None,
-            pp_prochloro_profile: None
+            pp_prochloro_profile: None,
+            size_classes: None
         };
 
         let test_case = TestCase {
@@ -150,6 +358,40 @@ mod integration_tests {
 
     }
 
+    #[test]
+    fn east_pacific_clock_times_test() {
+        let inputs = ModelInputs {
+            lat: -5.792,
+            lon: -96.62,
+            z_bottom: 100.0,
+            iday: 1,
+            date: CalendarDate::new(2021, 1, 1),
+            alpha_b: 0.0844,
+            pmb: 4.756,
+            z_m: 46.1,
+            mld: 19.35091019,
+            chl: 0.26096588,
+            rho: 0.878,
+            sigma: 34.6,
+            par: Some(49.1697464),
+            ..model_inputs()
+        };
+
+        let settings = ModelSettings {
+            iom_only: true,
+            ..Default::default()
+        };
+
+        let outputs = calc_production(&inputs, &settings).unwrap();
+
+        // solar noon at 96.62°W in early January is at about 18:30 UTC
+        let solar_noon = outputs.solar_noon.unwrap();
+        assert!((solar_noon - 18.497).abs() < 0.01);
+
+        let day_length = outputs.sunset.unwrap() - outputs.sunrise.unwrap();
+        assert!((outputs.sunrise.unwrap() + day_length / 2.0 - solar_noon).abs() < 1e-9);
+    }
+
     #[test]
     fn black_sea_test_pp() {
         let inputs = ModelInputs {
@@ -164,29 +406,32 @@ mod integration_tests {
             chl: 0.474,
             rho: 0.87,
             sigma: 14.62,
-            cloud: 0.0,
-            yel_sub: 0.3,
-            par: 50.35,
-            bw: calculate_bw(),
-            bbr: calculate_bbr(),
-            ay: calculate_ay()
+            par: Some(50.35),
+            ..model_inputs()
         };
 
-        let settings = ModelSettings {
-            mld_only: false,
-            iom_only: false,
-            prochloro_inputs: None
-        };
+        let settings = ModelSettings::default();
 
         let expected_result = ModelOutputs {
             pp_day: Some(905.18976),
+            pp_low_sun: None,
+            pp_profile: None,
             euphotic_depth: None,
+            integration_depth: None,
+            pp_above_mld: None,
+            pp_below_mld: None,
             spectral_i_star: None,
             par_noon_max: None,
+            par: None,
+            sunrise: None,
+            solar_noon: None,
+            sunset: None,
+            daylight_regime: None,
             pro_1_profile: None,
             pro_2_profile: None,
             pro_total_profile: None,
-            pp_prochloro_profile: None
+            pp_prochloro_profile: None,
+            size_classes: None
         };
 
         let test_case = TestCase {
@@ -213,29 +458,32 @@ mod integration_tests {
             chl: 0.058,
             rho: 0.75,
             sigma: 20.64,
-            cloud: 0.0,
-            yel_sub: 0.3,
-            par: 25.482,
-            bw: calculate_bw(),
-            bbr: calculate_bbr(),
-            ay: calculate_ay()
+            par: Some(25.482),
+            ..model_inputs()
         };
 
-        let settings = ModelSettings {
-            mld_only: false,
-            iom_only: false,
-            prochloro_inputs: None
-        };
+        let settings = ModelSettings::default();
 
         let expected_result = ModelOutputs {
             pp_day: Some(108.63),
+            pp_low_sun: None,
+            pp_profile: None,
             euphotic_depth: None,
+            integration_depth: None,
+            pp_above_mld: None,
+            pp_below_mld: None,
             spectral_i_star: None,
             par_noon_max: None,
+            par: None,
+            sunrise: None,
+            solar_noon: None,
+            sunset: None,
+            daylight_regime: None,
             pro_1_profile: None,
             pro_2_profile: None,
             pro_total_profile: None,
-            pp_prochloro_profile: None
+            pp_prochloro_profile: None,
+            size_classes: None
         };
 
         let test_case = TestCase {
@@ -262,29 +510,32 @@ mod integration_tests {
             chl: 1.718,
             rho: 0.8247,
             sigma: 27.556,
-            cloud: 0.0,
-            yel_sub: 0.3,
-            par: 55.8677,
-            bw: calculate_bw(),
-            bbr: calculate_bbr(),
-            ay: calculate_ay()
+            par: Some(55.8677),
+            ..model_inputs()
         };
 
-        let settings = ModelSettings {
-            mld_only: false,
-            iom_only: false,
-            prochloro_inputs: None
-        };
+        let settings = ModelSettings::default();
 
         let expected_result = ModelOutputs {
             pp_day: Some(2341.988),
+            pp_low_sun: None,
+            pp_profile: None,
             euphotic_depth: None,
+            integration_depth: None,
+            pp_above_mld: None,
+            pp_below_mld: None,
             spectral_i_star: None,
             par_noon_max: None,
+            par: None,
+            sunrise: None,
+            solar_noon: None,
+            sunset: None,
+            daylight_regime: None,
             pro_1_profile: None,
             pro_2_profile: None,
             pro_total_profile: None,
-            pp_prochloro_profile: None
+            pp_prochloro_profile: None,
+            size_classes: None
         };
 
         let test_case = TestCase {
@@ -311,29 +562,32 @@ mod integration_tests {
             chl: 0.1032,
             rho: 0.856,
             sigma: 23.523,
-            cloud: 0.0,
-            yel_sub: 0.3,
-            par: 56.255,
-            bw: calculate_bw(),
-            bbr: calculate_bbr(),
-            ay: calculate_ay()
+            par: Some(56.255),
+            ..model_inputs()
         };
 
-        let settings = ModelSettings {
-            mld_only: false,
-            iom_only: false,
-            prochloro_inputs: None
-        };
+        let settings = ModelSettings::default();
 
         let expected_result = ModelOutputs {
             pp_day: Some(694.43),
+            pp_low_sun: None,
+            pp_profile: None,
             euphotic_depth: None,
+            integration_depth: None,
+            pp_above_mld: None,
+            pp_below_mld: None,
             spectral_i_star: None,
             par_noon_max: None,
+            par: None,
+            sunrise: None,
+            solar_noon: None,
+            sunset: None,
+            daylight_regime: None,
             pro_1_profile: None,
             pro_2_profile: None,
             pro_total_profile: None,
-            pp_prochloro_profile: None
+            pp_prochloro_profile: None,
+            size_classes: None
         };
 
         let test_case = TestCase {
@@ -346,4 +600,379 @@ mod integration_tests {
         assert!(run_test_case(&test_case, 0.1));
     }
 
+
+    fn high_latitude_inputs(lat: f64, iday: u16) -> ModelInputs {
+        ModelInputs { lat, iday, ..model_inputs() }
+    }
+
+    #[test]
+    fn polar_night_test() {
+        for lat in [75.0, 80.0, 85.0] {
+            let outputs = calc_production(&high_latitude_inputs(lat, 355), &ModelSettings::default()).unwrap();
+
+            assert_eq!(outputs.daylight_regime, Some(DaylightRegime::PolarNight));
+            assert_eq!(outputs.pp_day, Some(0.0));
+            assert!(outputs.sunrise.is_none() && outputs.sunset.is_none());
+
+            // there is no euphotic layer or light field to describe
+            assert!(outputs.euphotic_depth.is_none() && outputs.integration_depth.is_none());
+            assert!(outputs.pp_above_mld.is_none() && outputs.pp_below_mld.is_none());
+            assert!(outputs.spectral_i_star.is_none());
+        }
+    }
+
+    #[test]
+    fn polar_day_test() {
+        for lat in [75.0, 80.0, 85.0] {
+            let outputs = calc_production(&high_latitude_inputs(lat, 172), &ModelSettings::default()).unwrap();
+            let pp_day = outputs.pp_day.unwrap();
+
+            assert_eq!(outputs.daylight_regime, Some(DaylightRegime::PolarDay));
+            assert!(pp_day.is_finite() && pp_day > 0.0);
+            assert!(outputs.sunrise.is_none() && outputs.sunset.is_none());
+
+            // the sun is up at midnight, so the low sun period is always computed spectrally
+            // rather than as a triangle rising from zero
+            let settings = ModelSettings { low_sun_mode: LowSunMode::Spectral, ..Default::default() };
+            let spectral = calc_production(&high_latitude_inputs(lat, 172), &settings).unwrap();
+            assert_eq!(outputs.pp_day, spectral.pp_day);
+        }
+    }
+
+    #[test]
+    fn polar_day_continuity_test() {
+        // production should not jump when crossing into the midnight sun, where the low sun period
+        // is always computed spectrally
+        let settings = ModelSettings { low_sun_mode: LowSunMode::Spectral, ..Default::default() };
+        let normal = calc_production(&high_latitude_inputs(66.5, 172), &settings).unwrap();
+        let polar = calc_production(&high_latitude_inputs(67.0, 172), &settings).unwrap();
+
+        assert_eq!(normal.daylight_regime, Some(DaylightRegime::Normal));
+        assert_eq!(polar.daylight_regime, Some(DaylightRegime::PolarDay));
+
+        let ratio = polar.pp_day.unwrap() / normal.pp_day.unwrap();
+        assert!((ratio - 1.0).abs() < 0.05);
+    }
+
+
+    #[test]
+    fn low_sun_spectral_test() {
+        let inputs = high_latitude_inputs(70.0, 172);
+        let mut settings = ModelSettings { low_sun_mode: LowSunMode::Spectral, ..Default::default() };
+
+        // computing the low sun period spectrally should make the result insensitive to the cutoff
+        settings.zenith_cutoff = 70.0;
+        let pp_70 = calc_production(&inputs, &settings).unwrap().pp_day.unwrap();
+        settings.zenith_cutoff = 85.0;
+        let pp_85 = calc_production(&inputs, &settings).unwrap().pp_day.unwrap();
+
+        assert!((pp_70 / pp_85 - 1.0).abs() < 0.01);
+
+        // the sun never climbs above 80° zenith here, so only the spectral mode sees any light
+        let inputs = high_latitude_inputs(78.0, 60);
+        settings.zenith_cutoff = 80.0;
+        let outputs = calc_production(&inputs, &settings).unwrap();
+
+        assert!(outputs.pp_day.unwrap() > 0.0);
+        assert_eq!(outputs.pp_day, outputs.pp_low_sun);
+    }
+
+    #[test]
+    fn f32_precision_test() {
+        // clear and turbid water, a deep chlorophyll maximum, polar day, the low sun period and
+        // the whole water column
+        let cases = [
+            (high_latitude_inputs(45.0, 121), LowSunMode::Triangle, IntegrationLimit::EuphoticDepth),
+            (ModelInputs { chl: 5.0, z_m: 10.0, ..high_latitude_inputs(20.0, 200) }, LowSunMode::Triangle, IntegrationLimit::EuphoticDepth),
+            (ModelInputs { chl: 0.05, z_m: 110.0, sigma: 15.0, rho: 0.9, ..high_latitude_inputs(-25.0, 30) }, LowSunMode::Triangle, IntegrationLimit::EuphoticDepth),
+            (high_latitude_inputs(75.0, 172), LowSunMode::Triangle, IntegrationLimit::EuphoticDepth),
+            (high_latitude_inputs(70.0, 172), LowSunMode::Spectral, IntegrationLimit::EuphoticDepth),
+            (high_latitude_inputs(45.0, 121), LowSunMode::Triangle, IntegrationLimit::Seabed),
+        ];
+
+        for (inputs, low_sun_mode, integration_limit) in cases.iter() {
+            let settings = |precision| ModelSettings { low_sun_mode: *low_sun_mode, integration_limit: *integration_limit, precision, ..Default::default() };
+            let f64_outputs = calc_production(inputs, &settings(Precision::F64)).unwrap();
+            let f32_outputs = calc_production(inputs, &settings(Precision::F32)).unwrap();
+
+            let (f64_pp, f32_pp) = (f64_outputs.pp_day.unwrap(), f32_outputs.pp_day.unwrap());
+            assert!((f32_pp / f64_pp - 1.0).abs() < 1e-6);
+            assert_ne!(f32_pp, f64_pp);
+            assert!((f32_outputs.euphotic_depth.unwrap() - f64_outputs.euphotic_depth.unwrap()).abs() < 1e-3);
+
+            // the production profile wherever it is not negligible
+            let (f64_profile, f32_profile) = (f64_outputs.pp_profile.unwrap(), f32_outputs.pp_profile.unwrap());
+            let threshold = 1e-3 * f64_profile.iter().cloned().fold(0.0, f64::max);
+            for (f64_pp_z, f32_pp_z) in f64_profile.iter().zip(f32_profile.iter()).filter(|(pp_z, _)| **pp_z > threshold) {
+                assert!((f32_pp_z / f64_pp_z - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn reference_solar_spectrum_test() {
+        // the legacy spectrum as seen on the model wavelengths, from the clear sky direct
+        // irradiance with and without it
+        let zenith_r = 30.0_f64.to_radians();
+        let (legacy, _) = compute_irradiance_components(zenith_r, 30.0, &SolarSpectrum::Legacy);
+        let (transmittance, _) = compute_irradiance_components(zenith_r, 30.0, &SolarSpectrum::Reference(Box::new([1.0; WL_COUNT])));
+        let mut reference = [0.0; WL_COUNT];
+        for l in 0..WL_COUNT {
+            reference[l] = legacy[l] / transmittance[l];
+        }
+
+        let inputs = high_latitude_inputs(45.0, 121);
+        let mut settings = ModelSettings::default();
+        let legacy_outputs = calc_production(&inputs, &settings).unwrap();
+        settings.solar_spectrum = SolarSpectrum::Reference(Box::new(reference));
+        let reference_outputs = calc_production(&inputs, &settings).unwrap();
+
+        // the distance correction cancels in the PAR scaling, which leaves the shape of the spectrum
+        assert!((reference_outputs.pp_day.unwrap() / legacy_outputs.pp_day.unwrap() - 1.0).abs() < 1e-3);
+
+        settings.solar_spectrum = SolarSpectrum::Reference(Box::new([1800.0; WL_COUNT]));
+        let flat_outputs = calc_production(&inputs, &settings).unwrap();
+        assert!((flat_outputs.pp_day.unwrap() / legacy_outputs.pp_day.unwrap() - 1.0).abs() > 1e-3);
+    }
+
+    #[test]
+    fn clear_sky_par_test() {
+        let mut inputs = high_latitude_inputs(45.0, 121);
+        let settings = ModelSettings::default();
+        let given = calc_production(&inputs, &settings).unwrap();
+        assert_eq!(given.par, inputs.par);
+
+        // without satellite PAR the model runs on its clear sky estimate, reduced by cloud
+        inputs.par = None;
+        let clear = calc_production(&inputs, &settings).unwrap();
+        let clear_par = clear.par.unwrap();
+        assert!(clear_par > 50.0 && clear_par < 80.0);
+
+        inputs.cloud = 50.0;
+        let cloudy = calc_production(&inputs, &settings).unwrap();
+        assert!(cloudy.par.unwrap() < clear_par && cloudy.pp_day.unwrap() < clear.pp_day.unwrap());
+
+        // and is the same as a run given that PAR
+        inputs.par = cloudy.par;
+        assert_eq!(calc_production(&inputs, &settings).unwrap().pp_day, cloudy.pp_day);
+    }
+
+    #[test]
+    fn par_time_series_test() {
+        let mut inputs = high_latitude_inputs(45.0, 121);
+        let mut settings = ModelSettings::default();
+        let daily = calc_production(&inputs, &settings).unwrap();
+
+        // the sine curve of the daily PAR sampled every quarter hour in clock time
+        let clock_offset = daily.solar_noon.unwrap() - 12.0;
+        let day = SurfaceIrradianceDay::new(121, 365.0, inputs.lat, SolarSpectrum::Legacy);
+        let solar_times = regular_times(0.25);
+        let series = ParTimeSeries::new(
+            solar_times.iter().map(|time| time + clock_offset).collect(),
+            day.series(&solar_times, 0.0, ParScaling::DailyPar(45.0)).iter().map(|sample| sample.par_above_surface).collect()
+        );
+
+        inputs.par_series = Some(series.clone());
+        let sampled = calc_production(&inputs, &settings).unwrap();
+        assert!((sampled.par.unwrap() / 45.0 - 1.0).abs() < 0.01);
+        assert!((sampled.pp_day.unwrap() / daily.pp_day.unwrap() - 1.0).abs() < 0.01);
+
+        // a cloudy afternoon
+        let mut cloudy_afternoon = series.clone();
+        for (time, value) in cloudy_afternoon.times.iter().zip(cloudy_afternoon.values.iter_mut()) {
+            if *time > daily.solar_noon.unwrap() {
+                *value *= 0.3;
+            }
+        }
+        inputs.par_series = Some(cloudy_afternoon.clone());
+        let asymmetric = calc_production(&inputs, &settings).unwrap();
+        assert!(asymmetric.pp_day.unwrap() < sampled.pp_day.unwrap());
+        assert!(asymmetric.pp_day.unwrap() > sampled.pp_day.unwrap() / 2.0);
+        assert_eq!(
+            asymmetric.par,
+            Some(cloudy_afternoon.integrate(daily.sunrise.unwrap(), daily.sunset.unwrap()))
+        );
+
+        settings.production_mode = ProductionMode::Broadband;
+        let broadband = calc_production(&inputs, &settings).unwrap();
+        inputs.par_series = Some(series.clone());
+        assert!(broadband.pp_day.unwrap() < calc_production(&inputs, &settings).unwrap().pp_day.unwrap());
+
+        // the series has to cover the hours of daylight
+        inputs.par_series = Some(ParTimeSeries::new(vec![6.0, 12.0], vec![0.0, 5.0]));
+        assert!(matches!(calc_production(&inputs, &settings), Err(PPErrors::InvalidParSeries)));
+    }
+
+    #[test]
+    fn temperature_dependence_test() {
+        let mut inputs = high_latitude_inputs(45.0, 121);
+        let mut settings = ModelSettings::default();
+        let fixed = calc_production(&inputs, &settings).unwrap().pp_day.unwrap();
+
+        // pmb is the rate at the reference temperature, and is left alone without an sst
+        settings.temperature_dependence = TemperatureDependence::Eppley { rate: 0.0633, reference_temperature: 10.0 };
+        assert_eq!(calc_production(&inputs, &settings).unwrap().pp_day, Some(fixed));
+        inputs.sst = Some(10.0);
+        assert_eq!(calc_production(&inputs, &settings).unwrap().pp_day, Some(fixed));
+
+        inputs.sst = Some(15.0);
+        let warm = calc_production(&inputs, &settings).unwrap().pp_day.unwrap();
+        inputs.sst = Some(5.0);
+        let cold = calc_production(&inputs, &settings).unwrap().pp_day.unwrap();
+        assert!(cold < fixed && fixed < warm);
+
+        // the same as running with the adjusted pmb
+        let pmb = inputs.pmb;
+        settings.temperature_dependence = TemperatureDependence::Q10 { q10: 2.0, reference_temperature: 15.0 };
+        let q10 = calc_production(&inputs, &settings).unwrap().pp_day;
+        settings.temperature_dependence = TemperatureDependence::None;
+        inputs.pmb = pmb / 2.0;
+        assert_eq!(calc_production(&inputs, &settings).unwrap().pp_day, q10);
+    }
+
+    #[test]
+    fn size_class_test() {
+        let mut inputs = high_latitude_inputs(45.0, 121);
+        inputs.chl = 1.0;
+        let mut settings = ModelSettings::default();
+        let total = calc_production(&inputs, &settings).unwrap();
+        assert!(total.size_classes.is_none());
+
+        // the same photosynthetic parameters for every class
+        settings.size_classes = Some(SizeClassInputs { alpha_b: [inputs.alpha_b; 3], pmb: [inputs.pmb; 3] });
+        let outputs = calc_production(&inputs, &settings).unwrap();
+        assert_eq!(outputs.pp_day, total.pp_day);
+        let size_classes = outputs.size_classes.unwrap();
+
+        assert!(size_classes.pp_day.iter().all(|pp| *pp > 0.0));
+
+        // the class absorption spectra differ from the mixture, but not by much on the whole
+        let pp_sum: f64 = size_classes.pp_day.iter().sum();
+        assert!((pp_sum / total.pp_day.unwrap() - 1.0).abs() < 0.1);
+        let profile_sum: f64 = size_classes.pp_profile.iter().map(|profile| profile[0]).sum();
+        assert!((profile_sum / total.pp_profile.unwrap()[0] - 1.0).abs() < 0.1);
+
+        // a faster growing micro class
+        settings.size_classes = Some(SizeClassInputs { alpha_b: [inputs.alpha_b; 3], pmb: [inputs.pmb, inputs.pmb, 2.0 * inputs.pmb] });
+        let fast_micro = calc_production(&inputs, &settings).unwrap().size_classes.unwrap();
+        assert_eq!(fast_micro.pp_day[0], size_classes.pp_day[0]);
+        assert!(fast_micro.pp_day[2] > size_classes.pp_day[2]);
+
+        settings.production_mode = ProductionMode::Broadband;
+        assert!(calc_production(&inputs, &settings).unwrap().size_classes.is_none());
+    }
+
+    #[test]
+    fn integration_limit_test() {
+        let mut inputs = high_latitude_inputs(45.0, 121);
+        let mut settings = ModelSettings::default();
+        let run = |inputs: &ModelInputs, settings: &ModelSettings| calc_production(inputs, settings).unwrap();
+
+        // the original euphotic depth, split at the mixed layer depth
+        let euphotic = run(&inputs, &settings);
+        assert_eq!(euphotic.integration_depth, euphotic.euphotic_depth);
+        let (above, below) = (euphotic.pp_above_mld.unwrap(), euphotic.pp_below_mld.unwrap());
+        assert!(above > 0.0 && below > 0.0);
+        assert!(((above + below) / euphotic.pp_day.unwrap() - 1.0).abs() < 1e-9);
+
+        settings.integration_limit = IntegrationLimit::TenthPercentLight;
+        let tenth = run(&inputs, &settings);
+        assert!(tenth.integration_depth.unwrap() > 1.4 * euphotic.euphotic_depth.unwrap());
+        assert_eq!(tenth.euphotic_depth, euphotic.euphotic_depth);
+        assert!(tenth.pp_day.unwrap() > euphotic.pp_day.unwrap());
+
+        settings.integration_limit = IntegrationLimit::Seabed;
+        let seabed = run(&inputs, &settings);
+        assert_eq!(seabed.integration_depth, Some(249.5));
+        assert!(seabed.pp_day.unwrap() > tenth.pp_day.unwrap());
+        assert!((seabed.pp_day.unwrap() / tenth.pp_day.unwrap() - 1.0) < 0.01);
+
+        // all of the production within the mixed layer
+        settings.integration_limit = IntegrationLimit::MixedLayer;
+        let mixed_layer = run(&inputs, &settings);
+        assert_eq!(mixed_layer.integration_depth, Some(inputs.mld));
+        assert_eq!(mixed_layer.pp_below_mld, Some(0.0));
+        assert!((mixed_layer.pp_day.unwrap() / above - 1.0).abs() < 1e-9);
+
+        // fixed depths, and never deeper than the seabed
+        settings.integration_limit = IntegrationLimit::FixedDepth(10.0);
+        let shallow = run(&inputs, &settings);
+        assert_eq!(shallow.integration_depth, Some(10.0));
+        assert!(shallow.pp_day.unwrap() < mixed_layer.pp_day.unwrap());
+        inputs.z_bottom = 8.0;
+        assert_eq!(run(&inputs, &settings).integration_depth, Some(8.0));
+        settings.integration_limit = IntegrationLimit::Seabed;
+        assert_eq!(run(&inputs, &settings).integration_depth, Some(8.0));
+
+        // and never deeper than the bottom of the depth grid
+        let deep = ModelInputs { z_bottom: 2950.0, ..inputs.clone() };
+        assert_eq!(run(&deep, &settings).integration_depth, Some(249.5));
+        settings.integration_limit = IntegrationLimit::FixedDepth(400.0);
+        assert_eq!(run(&deep, &settings).integration_depth, Some(249.5));
+        settings.integration_limit = IntegrationLimit::Seabed;
+
+        settings.production_mode = ProductionMode::Broadband;
+        assert!(matches!(calc_production(&inputs, &settings), Err(PPErrors::UnsupportedSettings)));
+    }
+
+    #[test]
+    fn zenith_cutoff_test() {
+        for zenith_cutoff in [f64::NAN, -1.0, 90.5, 180.0] {
+            let settings = ModelSettings { zenith_cutoff, ..Default::default() };
+            assert!(matches!(calc_production(&model_inputs(), &settings), Err(PPErrors::UnsupportedSettings)));
+        }
+
+        let settings = ModelSettings { zenith_cutoff: 90.0, ..Default::default() };
+        assert!(calc_production(&model_inputs(), &settings).unwrap().pp_day.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn broadband_mode_test() {
+        let inputs = high_latitude_inputs(45.0, 121);
+        let mut settings = ModelSettings::default();
+
+        let spectral = calc_production(&inputs, &settings).unwrap();
+        settings.production_mode = ProductionMode::Broadband;
+        let broadband = calc_production(&inputs, &settings).unwrap();
+
+        // the broadband Riley attenuation is stronger than the spectral attenuation for this
+        // water, so the broadband model is somewhat lower
+        let ratio = broadband.pp_day.unwrap() / spectral.pp_day.unwrap();
+        assert!(ratio > 0.7 && ratio < 1.0);
+        assert_eq!(broadband.par_noon_max, spectral.par_noon_max);
+        assert!(broadband.pp_low_sun.is_none());
+    }
+
+    #[test]
+    fn clear_sky_cache_test() {
+        let settings = ModelSettings { low_sun_mode: LowSunMode::Spectral, ..Default::default() };
+
+        // a row of pixels on the same day and latitude, the same day given as a date, and one
+        // pixel on another latitude
+        let mut inputs: Vec<ModelInputs> = [0.5, 1.0, 2.0, 4.0].iter()
+            .map(|chl| ModelInputs { chl: *chl, ..high_latitude_inputs(45.0, 172) })
+            .collect();
+        inputs.push(ModelInputs { date: CalendarDate::new(2021, 6, 21), ..high_latitude_inputs(45.0, 1) });
+        inputs.push(high_latitude_inputs(50.0, 172));
+
+        let mut cache = ClearSkyCache::default();
+        for pixel in inputs.iter() {
+            let single = calc_production(pixel, &settings).unwrap();
+            let cached = calc_production_with_clear_sky(pixel, &settings, cache.for_inputs(pixel, &settings)).unwrap();
+            assert_eq!(cached.pp_day, single.pp_day);
+            assert_eq!(cached.pp_low_sun, single.pp_low_sun);
+            assert_eq!(cached.euphotic_depth, single.euphotic_depth);
+        }
+        assert_eq!(cache.computed(), 2);
+
+        let batch = calc_production_batch(&inputs, &settings);
+        for (pixel, outputs) in inputs.iter().zip(batch.iter()) {
+            assert_eq!(outputs.as_ref().unwrap().pp_day, calc_production(pixel, &settings).unwrap().pp_day);
+        }
+
+        // irradiance for the wrong latitude is rejected
+        let clear_sky = ClearSkyIrradiance::for_inputs(&inputs[0], &settings);
+        assert!(calc_production_with_clear_sky(&inputs[5], &settings, &clear_sky).is_err());
+    }
+
 }
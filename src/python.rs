@@ -0,0 +1,697 @@
+// Python extension module, built with maturin (see pyproject.toml) when the python feature is
+// enabled. ModelInputs, ModelSettings and ModelOutputs mirror the Rust structs as Python classes
+// for single pixel runs with calc_production, and calc_production_arrays runs the model over
+// numpy arrays of per pixel inputs.
+//
+// calc_production_arrays takes every input as an array, or a scalar, and returns a dict of arrays
+// with the common shape of the inputs. Pixels that fail, or that have a NaN input other than par
+// (e.g. land or cloud in a satellite image), are NaN in the outputs and their reason is given by the status
+// array, which uses the DWCPN_* status codes of the C interface.
+
+use crate::dwcpn::dwcpn::{calc_production as model_calc_production, calc_production_batch};
+use crate::dwcpn::modules::config::{DEPTH_PROFILE_COUNT, WL_COUNT};
+use crate::dwcpn::modules::par_series::ParTimeSeries;
+use crate::dwcpn::modules::pp_profile::{calculate_ay, calculate_bbr, calculate_bw};
+use crate::dwcpn::modules::temperature::EPPLEY_RATE;
+use crate::dwcpn::modules::time::{CalendarDate, DaylightRegime};
+use crate::dwcpn::solar_spectrum::read_reference_spectrum as model_read_reference_spectrum;
+use crate::ffi::{
+    DWCPN_DAYLIGHT_NORMAL, DWCPN_DAYLIGHT_POLAR_DAY, DWCPN_DAYLIGHT_POLAR_NIGHT, DWCPN_INVALID_ARGUMENT,
+    DWCPN_MODEL_ERROR, DWCPN_OK, DWCPN_PP_TOO_HIGH
+};
+use crate::{IntegrationLimit, LowSunMode, ModelInputs, ModelOutputs, ModelSettings, PPErrors, Precision, ProchloroInputs, ProductionMode, SizeClassInputs, SolarSpectrum, TemperatureDependence};
+use numpy::ndarray::{ArrayD, IxDyn};
+use numpy::{AllowTypeChange, Element, IntoPyArray, PyArrayDyn, PyArrayLikeDyn};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+create_exception!(dwcpn, ModelError, PyRuntimeError);
+
+// names of the per pixel inputs of calc_production_arrays, in argument order
+pub const ARRAY_INPUT_NAMES: [&str; 15] = [
+    "lat", "lon", "z_bottom", "iday", "alpha_b", "pmb", "z_m", "mld", "chl", "rho", "sigma", "cloud",
+    "yel_sub", "par", "sst"
+];
+const PAR_COLUMN: usize = 13;
+const SST_COLUMN: usize = 14;
+
+// scalar outputs of calc_production_arrays, in the order of ArrayOutputs::values
+pub const ARRAY_OUTPUT_NAMES: [&str; 12] = [
+    "pp_day", "pp_low_sun", "euphotic_depth", "spectral_i_star", "par_noon_max", "sunrise", "solar_noon",
+    "sunset", "par", "integration_depth", "pp_above_mld", "pp_below_mld"
+];
+
+#[pyclass(name = "ModelInputs", module = "dwcpn", get_all, set_all)]
+#[derive(Clone)]
+pub struct PyModelInputs {
+    pub lat: f64,
+    pub lon: f64,
+    pub z_bottom: f64,
+    pub iday: u16,
+    // (year, month, day), used instead of iday when given
+    pub date: Option<(i32, u8, u8)>,
+    pub utc_offset: Option<f64>,
+    pub alpha_b: f64,
+    pub pmb: f64,
+    pub z_m: f64,
+    pub mld: f64,
+    pub chl: f64,
+    pub rho: f64,
+    pub sigma: f64,
+    pub cloud: f64,
+    pub yel_sub: f64,
+    // None for the clear sky estimate
+    pub par: Option<f64>,
+    // clock times (hours) and surface PAR (einsteins m^-2 h^-1) used in place of par
+    pub par_series: Option<(Vec<f64>, Vec<f64>)>,
+    // degrees C, for ModelSettings.temperature_dependence
+    pub sst: Option<f64>,
+    pub bw: Vec<f64>,
+    pub bbr: Vec<f64>,
+    pub ay: Vec<f64>,
+}
+
+#[pymethods]
+impl PyModelInputs {
+    // the spectra default to those of the original model, as in dwcpn_default_inputs
+    #[new]
+    #[pyo3(signature = (
+        lat = 0.0, lon = 0.0, z_bottom = 0.0, iday = 1, date = None, utc_offset = None, alpha_b = 0.0,
+        pmb = 0.0, z_m = 0.0, mld = 0.0, chl = 0.0, rho = 0.0, sigma = 0.0, cloud = 0.0, yel_sub = 0.0,
+        par = None, par_series = None, sst = None, bw = None, bbr = None, ay = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        lat: f64,
+        lon: f64,
+        z_bottom: f64,
+        iday: u16,
+        date: Option<(i32, u8, u8)>,
+        utc_offset: Option<f64>,
+        alpha_b: f64,
+        pmb: f64,
+        z_m: f64,
+        mld: f64,
+        chl: f64,
+        rho: f64,
+        sigma: f64,
+        cloud: f64,
+        yel_sub: f64,
+        par: Option<f64>,
+        par_series: Option<(Vec<f64>, Vec<f64>)>,
+        sst: Option<f64>,
+        bw: Option<Vec<f64>>,
+        bbr: Option<Vec<f64>>,
+        ay: Option<Vec<f64>>
+    ) -> PyModelInputs {
+        PyModelInputs {
+            lat,
+            lon,
+            z_bottom,
+            iday,
+            date,
+            utc_offset,
+            alpha_b,
+            pmb,
+            z_m,
+            mld,
+            chl,
+            rho,
+            sigma,
+            cloud,
+            yel_sub,
+            par,
+            par_series,
+            sst,
+            bw: bw.unwrap_or_else(|| calculate_bw().to_vec()),
+            bbr: bbr.unwrap_or_else(|| calculate_bbr().to_vec()),
+            ay: ay.unwrap_or_else(|| calculate_ay().to_vec())
+        }
+    }
+}
+
+impl PyModelInputs {
+    fn to_model_inputs(&self) -> PyResult<ModelInputs> {
+        let date = match self.date {
+            Some((year, month, day)) => Some(
+                CalendarDate::new(year, month, day)
+                    .ok_or_else(|| PyValueError::new_err(format!("invalid date {}-{}-{}", year, month, day)))?
+            ),
+            None => None
+        };
+
+        let iday = match date {
+            Some(date) => date.day_of_year(),
+            None if (1..=366).contains(&self.iday) => self.iday,
+            None => return Err(PyValueError::new_err(format!("iday {} is not between 1 and 366", self.iday)))
+        };
+
+        Ok(
+            ModelInputs {
+                lat: self.lat,
+                lon: self.lon,
+                z_bottom: self.z_bottom,
+                iday,
+                date,
+                utc_offset: self.utc_offset,
+                alpha_b: self.alpha_b,
+                pmb: self.pmb,
+                z_m: self.z_m,
+                mld: self.mld,
+                chl: self.chl,
+                rho: self.rho,
+                sigma: self.sigma,
+                cloud: self.cloud,
+                yel_sub: self.yel_sub,
+                par: self.par,
+                par_series: match &self.par_series {
+                    Some((times, values)) => {
+                        let series = ParTimeSeries::new(times.clone(), values.clone());
+                        if !series.is_valid() {
+                            return Err(PyValueError::new_err("par_series needs at least two samples at increasing times"));
+                        }
+                        Some(series)
+                    },
+                    None => None
+                },
+                sst: self.sst,
+                bw: spectrum("bw", &self.bw)?,
+                bbr: spectrum("bbr", &self.bbr)?,
+                ay: spectrum("ay", &self.ay)?
+            }
+        )
+    }
+}
+
+fn spectrum(name: &str, values: &[f64]) -> PyResult<[f64; WL_COUNT]> {
+    values.try_into().map_err(|_| {
+        PyValueError::new_err(format!("{} has {} values, expected {}", name, values.len(), WL_COUNT))
+    })
+}
+
+#[pyclass(name = "ModelSettings", module = "dwcpn", get_all, set_all)]
+#[derive(Clone)]
+pub struct PyModelSettings {
+    pub mld_only: bool,
+    pub iom_only: bool,
+    // both must be given to use the Prochlorococcus profile
+    pub prochloro_surface: Option<f64>,
+    pub prochloro_maximum: Option<f64>,
+    pub zenith_cutoff: f64,
+    // "triangle" or "spectral"
+    pub low_sun_mode: String,
+    // "spectral" or "broadband"
+    pub production_mode: String,
+    // "f64" or "f32"
+    pub precision: String,
+    // extra-terrestrial spectrum on the model wavelengths for SolarSpectrum::Reference (see
+    // read_reference_spectrum), the legacy tables when not given
+    pub reference_spectrum: Option<Vec<f64>>,
+    // "none", "eppley" or "q10", with pmb taken as the rate at reference_temperature (degrees C).
+    // The coefficient is the Eppley rate per degree C (0.0633 when not given) or the Q10
+    pub temperature_dependence: String,
+    pub temperature_coefficient: Option<f64>,
+    pub reference_temperature: f64,
+    // pico, nano and micro values, both must be given for the size class production
+    pub size_class_alpha_b: Option<[f64; 3]>,
+    pub size_class_pmb: Option<[f64; 3]>,
+    // "euphotic_depth", "tenth_percent_light", "mixed_layer", "fixed_depth" (at fixed_depth m) or
+    // "seabed"
+    pub integration_limit: String,
+    pub fixed_depth: Option<f64>,
+}
+
+#[pymethods]
+impl PyModelSettings {
+    // defaults of the original model, as in dwcpn_default_settings
+    #[new]
+    #[pyo3(signature = (
+        mld_only = false, iom_only = false, prochloro_surface = None, prochloro_maximum = None,
+        zenith_cutoff = 80.0, low_sun_mode = "triangle".to_string(), production_mode = "spectral".to_string(),
+        precision = "f64".to_string(), reference_spectrum = None, temperature_dependence = "none".to_string(),
+        temperature_coefficient = None, reference_temperature = 20.0, size_class_alpha_b = None,
+        size_class_pmb = None, integration_limit = "euphotic_depth".to_string(), fixed_depth = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        mld_only: bool,
+        iom_only: bool,
+        prochloro_surface: Option<f64>,
+        prochloro_maximum: Option<f64>,
+        zenith_cutoff: f64,
+        low_sun_mode: String,
+        production_mode: String,
+        precision: String,
+        reference_spectrum: Option<Vec<f64>>,
+        temperature_dependence: String,
+        temperature_coefficient: Option<f64>,
+        reference_temperature: f64,
+        size_class_alpha_b: Option<[f64; 3]>,
+        size_class_pmb: Option<[f64; 3]>,
+        integration_limit: String,
+        fixed_depth: Option<f64>
+    ) -> PyModelSettings {
+        PyModelSettings {
+            mld_only,
+            iom_only,
+            prochloro_surface,
+            prochloro_maximum,
+            zenith_cutoff,
+            low_sun_mode,
+            production_mode,
+            precision,
+            reference_spectrum,
+            temperature_dependence,
+            temperature_coefficient,
+            reference_temperature,
+            size_class_alpha_b,
+            size_class_pmb,
+            integration_limit,
+            fixed_depth
+        }
+    }
+}
+
+impl Default for PyModelSettings {
+    fn default() -> PyModelSettings {
+        PyModelSettings::new(false, false, None, None, 80.0, "triangle".to_string(), "spectral".to_string(), "f64".to_string(), None, "none".to_string(), None, 20.0, None, None, "euphotic_depth".to_string(), None)
+    }
+}
+
+impl PyModelSettings {
+    fn to_model_settings(&self) -> PyResult<ModelSettings> {
+        Ok(
+            ModelSettings {
+                mld_only: self.mld_only,
+                iom_only: self.iom_only,
+                prochloro_inputs: match (self.prochloro_surface, self.prochloro_maximum) {
+                    (Some(prochloro_surface), Some(prochloro_maximum)) => Some(
+                        ProchloroInputs { prochloro_surface, prochloro_maximum }
+                    ),
+                    (None, None) => None,
+                    _ => return Err(PyValueError::new_err("prochloro_surface and prochloro_maximum must be given together"))
+                },
+                zenith_cutoff: self.zenith_cutoff,
+                low_sun_mode: match self.low_sun_mode.as_str() {
+                    "triangle" => LowSunMode::Triangle,
+                    "spectral" => LowSunMode::Spectral,
+                    mode => return Err(PyValueError::new_err(format!("unknown low_sun_mode {:?}", mode)))
+                },
+                production_mode: match self.production_mode.as_str() {
+                    "spectral" => ProductionMode::Spectral,
+                    "broadband" => ProductionMode::Broadband,
+                    mode => return Err(PyValueError::new_err(format!("unknown production_mode {:?}", mode)))
+                },
+                precision: match self.precision.as_str() {
+                    "f64" => Precision::F64,
+                    "f32" => Precision::F32,
+                    precision => return Err(PyValueError::new_err(format!("unknown precision {:?}", precision)))
+                },
+                solar_spectrum: match &self.reference_spectrum {
+                    None => SolarSpectrum::Legacy,
+                    Some(reference) => SolarSpectrum::Reference(Box::new(reference.as_slice().try_into().map_err(|_| {
+                        PyValueError::new_err(format!("reference_spectrum must have {} values", WL_COUNT))
+                    })?))
+                },
+                temperature_dependence: match (self.temperature_dependence.as_str(), self.temperature_coefficient) {
+                    ("none", _) => TemperatureDependence::None,
+                    ("eppley", rate) => TemperatureDependence::Eppley {
+                        rate: rate.unwrap_or(EPPLEY_RATE),
+                        reference_temperature: self.reference_temperature
+                    },
+                    ("q10", Some(q10)) => TemperatureDependence::Q10 { q10, reference_temperature: self.reference_temperature },
+                    ("q10", None) => return Err(PyValueError::new_err("temperature_coefficient must give the q10")),
+                    (dependence, _) => return Err(PyValueError::new_err(format!("unknown temperature_dependence {:?}", dependence)))
+                },
+                size_classes: match (self.size_class_alpha_b, self.size_class_pmb) {
+                    (Some(alpha_b), Some(pmb)) => Some(SizeClassInputs { alpha_b, pmb }),
+                    (None, None) => None,
+                    _ => return Err(PyValueError::new_err("size_class_alpha_b and size_class_pmb must be given together"))
+                },
+                integration_limit: match (self.integration_limit.as_str(), self.fixed_depth) {
+                    ("euphotic_depth", _) => IntegrationLimit::EuphoticDepth,
+                    ("tenth_percent_light", _) => IntegrationLimit::TenthPercentLight,
+                    ("mixed_layer", _) => IntegrationLimit::MixedLayer,
+                    ("fixed_depth", Some(depth)) => IntegrationLimit::FixedDepth(depth),
+                    ("fixed_depth", None) => return Err(PyValueError::new_err("fixed_depth must give the depth")),
+                    ("seabed", _) => IntegrationLimit::Seabed,
+                    (limit, _) => return Err(PyValueError::new_err(format!("unknown integration_limit {:?}", limit)))
+                }
+            }
+        )
+    }
+}
+
+#[pyclass(name = "ModelOutputs", module = "dwcpn", get_all, frozen)]
+pub struct PyModelOutputs {
+    pub pp_day: Option<f64>,
+    pub pp_low_sun: Option<f64>,
+    pub pp_profile: Option<Vec<f64>>,
+    pub euphotic_depth: Option<f64>,
+    pub integration_depth: Option<f64>,
+    pub pp_above_mld: Option<f64>,
+    pub pp_below_mld: Option<f64>,
+    pub spectral_i_star: Option<f64>,
+    pub par_noon_max: Option<f64>,
+    pub par: Option<f64>,
+    pub sunrise: Option<f64>,
+    pub solar_noon: Option<f64>,
+    pub sunset: Option<f64>,
+    // "normal", "polar_day" or "polar_night"
+    pub daylight_regime: Option<&'static str>,
+    pub pro_1_profile: Option<Vec<f64>>,
+    pub pro_2_profile: Option<Vec<f64>>,
+    pub pro_total_profile: Option<Vec<f64>>,
+    pub pp_prochloro_profile: Option<Vec<f64>>,
+    // daily production and profiles of production and chlorophyll for each size class, ordered
+    // pico, nano, micro. The classes are run with their own absorption and parameters and do not
+    // sum to pp_day (see SizeClassOutputs)
+    pub size_class_pp_day: Option<[f64; 3]>,
+    pub size_class_pp_profile: Option<Vec<Vec<f64>>>,
+    pub size_class_chl_profile: Option<Vec<Vec<f64>>>,
+}
+
+impl From<ModelOutputs> for PyModelOutputs {
+    fn from(outputs: ModelOutputs) -> PyModelOutputs {
+        PyModelOutputs {
+            pp_day: outputs.pp_day,
+            pp_low_sun: outputs.pp_low_sun,
+            pp_profile: outputs.pp_profile.map(|profile| profile.to_vec()),
+            euphotic_depth: outputs.euphotic_depth,
+            integration_depth: outputs.integration_depth,
+            pp_above_mld: outputs.pp_above_mld,
+            pp_below_mld: outputs.pp_below_mld,
+            spectral_i_star: outputs.spectral_i_star,
+            par_noon_max: outputs.par_noon_max,
+            par: outputs.par,
+            sunrise: outputs.sunrise,
+            solar_noon: outputs.solar_noon,
+            sunset: outputs.sunset,
+            daylight_regime: outputs.daylight_regime.map(|regime| match regime {
+                DaylightRegime::Normal => "normal",
+                DaylightRegime::PolarDay => "polar_day",
+                DaylightRegime::PolarNight => "polar_night"
+            }),
+            pro_1_profile: outputs.pro_1_profile.map(|profile| profile.to_vec()),
+            pro_2_profile: outputs.pro_2_profile.map(|profile| profile.to_vec()),
+            pro_total_profile: outputs.pro_total_profile.map(|profile| profile.to_vec()),
+            pp_prochloro_profile: outputs.pp_prochloro_profile.map(|profile| profile.to_vec()),
+            size_class_pp_day: outputs.size_classes.as_ref().map(|size_classes| size_classes.pp_day),
+            size_class_pp_profile: outputs.size_classes.as_ref()
+                .map(|size_classes| size_classes.pp_profile.iter().map(|profile| profile.to_vec()).collect()),
+            size_class_chl_profile: outputs.size_classes.as_ref()
+                .map(|size_classes| size_classes.chl_profile.iter().map(|profile| profile.to_vec()).collect())
+        }
+    }
+}
+
+fn model_error(error: PPErrors) -> PyErr {
+    ModelError::new_err(format!("{:?}", error))
+}
+
+// outputs of calc_production_arrays for n pixels, NaN (or -1 for daylight_regime) where the
+// status is not DWCPN_OK
+pub struct ArrayOutputs {
+    pub values: [Vec<f64>; ARRAY_OUTPUT_NAMES.len()],
+    pub daylight_regime: Vec<i32>,
+    pub status: Vec<i32>,
+    // n * DEPTH_PROFILE_COUNT values, only when the profiles are requested
+    pub pp_profile: Option<Vec<f64>>,
+}
+
+// common shape of the inputs, each of which must have that shape or a single value
+pub fn broadcast_shape(shapes: &[&[usize]]) -> Result<Vec<usize>, String> {
+    let mut shape: Vec<usize> = Vec::new();
+    let mut size = 1;
+
+    for (name, input_shape) in ARRAY_INPUT_NAMES.iter().zip(shapes) {
+        let input_size: usize = input_shape.iter().product();
+        if input_size == 1 {
+            continue;
+        }
+        if size == 1 {
+            shape = input_shape.to_vec();
+            size = input_size;
+        } else if *input_shape != shape.as_slice() {
+            return Err(format!("{} has shape {:?}, expected {:?} or a single value", name, input_shape, shape));
+        }
+    }
+
+    Ok(shape)
+}
+
+// run the model for each pixel, with columns holding the inputs in the order of ARRAY_INPUT_NAMES
+// as either n values or a single value. A NaN par is estimated from the clear sky irradiance and a
+// NaN sst leaves pmb as given, any other NaN input makes the pixel invalid
+pub fn calc_production_columns(
+    columns: &[Vec<f64>],
+    n: usize,
+    settings: &ModelSettings,
+    profiles: bool
+) -> ArrayOutputs {
+    let value = |column: usize, pixel: usize| -> f64 {
+        let values = &columns[column];
+        if values.len() == 1 { values[0] } else { values[pixel] }
+    };
+
+    let mut status = vec![DWCPN_OK; n];
+    let mut pixels: Vec<usize> = Vec::with_capacity(n);
+    let mut inputs: Vec<ModelInputs> = Vec::with_capacity(n);
+    let (bw, bbr, ay) = (calculate_bw(), calculate_bbr(), calculate_ay());
+
+    for (pixel, pixel_status) in status.iter_mut().enumerate() {
+        let iday = value(3, pixel);
+        if (0..PAR_COLUMN).any(|column| value(column, pixel).is_nan())
+            || !(1.0..=366.0).contains(&iday) || iday.fract() != 0.0 {
+            *pixel_status = DWCPN_INVALID_ARGUMENT;
+            continue;
+        }
+
+        pixels.push(pixel);
+        inputs.push(ModelInputs {
+            lat: value(0, pixel),
+            lon: value(1, pixel),
+            z_bottom: value(2, pixel),
+            iday: iday as u16,
+            date: None,
+            utc_offset: None,
+            alpha_b: value(4, pixel),
+            pmb: value(5, pixel),
+            z_m: value(6, pixel),
+            mld: value(7, pixel),
+            chl: value(8, pixel),
+            rho: value(9, pixel),
+            sigma: value(10, pixel),
+            cloud: value(11, pixel),
+            yel_sub: value(12, pixel),
+            par: Some(value(PAR_COLUMN, pixel)).filter(|par| !par.is_nan()),
+            par_series: None,
+            sst: Some(value(SST_COLUMN, pixel)).filter(|sst| !sst.is_nan()),
+            bw,
+            bbr,
+            ay
+        });
+    }
+
+    let mut outputs = ArrayOutputs {
+        values: std::array::from_fn(|_| vec![f64::NAN; n]),
+        daylight_regime: vec![-1; n],
+        status,
+        pp_profile: if profiles { Some(vec![f64::NAN; n * DEPTH_PROFILE_COUNT]) } else { None },
+    };
+
+    for (pixel, result) in pixels.into_iter().zip(calc_production_batch(&inputs, settings)) {
+        let model_outputs = match result {
+            Ok(model_outputs) => model_outputs,
+            Err(error) => {
+                outputs.status[pixel] = match error {
+                    PPErrors::PPTooHigh => DWCPN_PP_TOO_HIGH,
+                    PPErrors::UnsupportedSettings => DWCPN_INVALID_ARGUMENT,
+                    _ => DWCPN_MODEL_ERROR
+                };
+                continue;
+            }
+        };
+
+        let values = [
+            model_outputs.pp_day,
+            model_outputs.pp_low_sun,
+            model_outputs.euphotic_depth,
+            model_outputs.spectral_i_star,
+            model_outputs.par_noon_max,
+            model_outputs.sunrise,
+            model_outputs.solar_noon,
+            model_outputs.sunset,
+            model_outputs.par,
+            model_outputs.integration_depth,
+            model_outputs.pp_above_mld,
+            model_outputs.pp_below_mld
+        ];
+        for (output, value) in outputs.values.iter_mut().zip(values) {
+            output[pixel] = value.unwrap_or(f64::NAN);
+        }
+
+        outputs.daylight_regime[pixel] = match model_outputs.daylight_regime {
+            Some(DaylightRegime::Normal) => DWCPN_DAYLIGHT_NORMAL,
+            Some(DaylightRegime::PolarDay) => DWCPN_DAYLIGHT_POLAR_DAY,
+            Some(DaylightRegime::PolarNight) => DWCPN_DAYLIGHT_POLAR_NIGHT,
+            None => -1
+        };
+
+        if let (Some(pp_profile), Some(profile)) = (outputs.pp_profile.as_mut(), model_outputs.pp_profile) {
+            pp_profile[pixel * DEPTH_PROFILE_COUNT..(pixel + 1) * DEPTH_PROFILE_COUNT].copy_from_slice(&profile);
+        }
+    }
+
+    outputs
+}
+
+fn to_pyarray<'py, T: Element>(py: Python<'py>, values: Vec<T>, shape: &[usize]) -> PyResult<Bound<'py, PyArrayDyn<T>>> {
+    ArrayD::from_shape_vec(IxDyn(shape), values)
+        .map(|array| array.into_pyarray(py))
+        .map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+/// Read a reference extra-terrestrial solar spectrum file (e.g. Thuillier 2003) binned onto the
+/// model wavelengths, for ModelSettings.reference_spectrum.
+#[pyfunction]
+fn read_reference_spectrum(path: &str) -> PyResult<Vec<f64>> {
+    model_read_reference_spectrum(path)
+        .map(|reference| reference.to_vec())
+        .map_err(|error| PyValueError::new_err(format!("{:?}", error)))
+}
+
+/// Run the model for one pixel, raising ModelError if the model fails.
+#[pyfunction]
+#[pyo3(signature = (inputs, settings = None))]
+fn calc_production(inputs: &PyModelInputs, settings: Option<&PyModelSettings>) -> PyResult<PyModelOutputs> {
+    let settings = settings.cloned().unwrap_or_else(PyModelSettings::default);
+
+    model_calc_production(&inputs.to_model_inputs()?, &settings.to_model_settings()?)
+        .map(PyModelOutputs::from)
+        .map_err(model_error)
+}
+
+/// Run the model for every pixel of the input arrays, which must share a shape or be scalars, and
+/// return a dict of output arrays of that shape. Where par is omitted or NaN the daily PAR is
+/// estimated from the clear sky irradiance and cloud, and sst (degrees C) is only needed for a
+/// temperature_dependence in settings. The GIL is released while the model runs.
+#[pyfunction]
+#[pyo3(signature = (
+    lat, lon, z_bottom, iday, alpha_b, pmb, z_m, mld, chl, rho, sigma, cloud, yel_sub, par = None,
+    sst = None, settings = None, profiles = false
+))]
+#[allow(clippy::too_many_arguments)]
+fn calc_production_arrays<'py>(
+    py: Python<'py>,
+    lat: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    lon: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    z_bottom: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    iday: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    alpha_b: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    pmb: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    z_m: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    mld: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    chl: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    rho: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    sigma: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    cloud: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    yel_sub: PyArrayLikeDyn<'py, f64, AllowTypeChange>,
+    par: Option<PyArrayLikeDyn<'py, f64, AllowTypeChange>>,
+    sst: Option<PyArrayLikeDyn<'py, f64, AllowTypeChange>>,
+    settings: Option<&PyModelSettings>,
+    profiles: bool
+) -> PyResult<Bound<'py, PyDict>> {
+    let settings = settings.cloned().unwrap_or_else(PyModelSettings::default).to_model_settings()?;
+
+    let arrays = [&lat, &lon, &z_bottom, &iday, &alpha_b, &pmb, &z_m, &mld, &chl, &rho, &sigma, &cloud, &yel_sub];
+    let views: Vec<_> = arrays.iter()
+        .map(|array| Some(array.as_array()))
+        .chain([&par, &sst].iter().map(|array| array.as_ref().map(|array| array.as_array())))
+        .collect();
+    // a par or sst that is not given is a single NaN
+    let shapes: Vec<&[usize]> = views.iter().map(|view| view.as_ref().map_or(&[1][..], |view| view.shape())).collect();
+    let shape = broadcast_shape(&shapes).map_err(PyValueError::new_err)?;
+    let n: usize = shape.iter().product();
+
+    // copied in logical order so that any memory layout is accepted
+    let columns: Vec<Vec<f64>> = views.iter()
+        .map(|view| view.as_ref().map_or(vec![f64::NAN], |view| view.iter().copied().collect()))
+        .collect();
+    let outputs = py.detach(|| calc_production_columns(&columns, n, &settings, profiles));
+
+    let dict = PyDict::new(py);
+    for (name, values) in ARRAY_OUTPUT_NAMES.iter().zip(outputs.values) {
+        dict.set_item(*name, to_pyarray(py, values, &shape)?)?;
+    }
+    dict.set_item("daylight_regime", to_pyarray(py, outputs.daylight_regime, &shape)?)?;
+    dict.set_item("status", to_pyarray(py, outputs.status, &shape)?)?;
+    if let Some(pp_profile) = outputs.pp_profile {
+        let mut profile_shape = shape.clone();
+        profile_shape.push(DEPTH_PROFILE_COUNT);
+        dict.set_item("pp_profile", to_pyarray(py, pp_profile, &profile_shape)?)?;
+    }
+
+    Ok(dict)
+}
+
+#[pymodule]
+#[pyo3(name = "dwcpn")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyModelInputs>()?;
+    m.add_class::<PyModelSettings>()?;
+    m.add_class::<PyModelOutputs>()?;
+    m.add_function(wrap_pyfunction!(calc_production, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_production_arrays, m)?)?;
+    m.add_function(wrap_pyfunction!(read_reference_spectrum, m)?)?;
+    m.add("ModelError", m.py().get_type::<ModelError>())?;
+    m.add("STATUS_OK", DWCPN_OK)?;
+    m.add("STATUS_MODEL_ERROR", DWCPN_MODEL_ERROR)?;
+    m.add("STATUS_PP_TOO_HIGH", DWCPN_PP_TOO_HIGH)?;
+    m.add("STATUS_INVALID_ARGUMENT", DWCPN_INVALID_ARGUMENT)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_calc_production_columns() {
+        // the Mauritania pixel of the integration tests, repeated with a land pixel and a bad iday
+        let mut columns: Vec<Vec<f64>> = vec![
+            vec![18.71, 18.71, 18.71], vec![-18.625], vec![2950.468], vec![121.0, 121.0, 400.0], vec![0.1518],
+            vec![3.9059], vec![23.094], vec![31.975], vec![1.718, f64::NAN, 1.718], vec![0.8247], vec![27.556],
+            vec![0.0], vec![0.3], vec![55.8677], vec![f64::NAN]
+        ];
+        let settings = PyModelSettings::default().to_model_settings().unwrap();
+
+        let shapes: Vec<Vec<usize>> = columns.iter().map(|column| vec![column.len()]).collect();
+        let shapes: Vec<&[usize]> = shapes.iter().map(|shape| shape.as_slice()).collect();
+        assert_eq!(broadcast_shape(&shapes).unwrap(), vec![3]);
+
+        let outputs = calc_production_columns(&columns, 3, &settings, true);
+        assert_eq!(outputs.status, vec![DWCPN_OK, DWCPN_INVALID_ARGUMENT, DWCPN_INVALID_ARGUMENT]);
+        assert!(outputs.values[1..].iter().all(|values| values[1].is_nan() && values[2].is_nan()));
+
+        // the same as a single pixel run
+        columns.iter_mut().for_each(|column| column.truncate(1));
+        let input = PyModelInputs::new(
+            18.71, -18.625, 2950.468, 121, None, None, 0.1518, 3.9059, 23.094, 31.975, 1.718, 0.8247, 27.556,
+            0.0, 0.3, Some(55.8677), None, None, None, None, None
+        ).to_model_inputs().unwrap();
+        let expected = model_calc_production(&input, &settings).unwrap();
+        assert_eq!(outputs.values[0][0], expected.pp_day.unwrap());
+        assert_eq!(outputs.values[2][0], expected.euphotic_depth.unwrap());
+        assert_eq!(outputs.pp_profile.unwrap()[..DEPTH_PROFILE_COUNT], expected.pp_profile.unwrap());
+
+        columns[0] = vec![0.0, 0.0];
+        columns[1] = vec![0.0, 0.0, 0.0];
+        let shapes: Vec<Vec<usize>> = columns.iter().map(|column| vec![column.len()]).collect();
+        let shapes: Vec<&[usize]> = shapes.iter().map(|shape| shape.as_slice()).collect();
+        assert!(broadcast_shape(&shapes).is_err());
+    }
+}